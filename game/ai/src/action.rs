@@ -1 +1,602 @@
+use crate::bt::{BehaviorArgs, BehaviorNode, BehaviorStatus, BehaviorStorage};
+use rl_core::{
+    components::{BlackboardComponent, NeedsComponent, PositionComponent},
+    defs::{
+        item::{ItemAbilityKind, ItemDefinition, ItemKind},
+        needs::NeedKind,
+        DefinitionComponent, DefinitionStorage,
+    },
+    fnv,
+    fxhash::{self, FxHashSet},
+    legion::prelude::*,
+    math::Vec3i,
+    slotmap,
+    smallvec::SmallVec,
+    time::Time,
+    GameStateRef, NamedSlotMap,
+};
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Arc};
 
+/// Number of nodes the planner will expand before giving up on a goal
+pub const MAX_PLAN_ITERATIONS: usize = 2048;
+/// Number of times a failed plan is rebuilt before the goal is abandoned
+pub const MAX_REPLANS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorldFact {
+    HasItemAbility(ItemAbilityKind),
+    HasItemKind(ItemKind),
+    AtLocation(Vec3i),
+    NeedSatisfied(NeedKind),
+    /// A task needing an item with the ability, or no item for `None`, was worked
+    WorkedTask(Option<ItemAbilityKind>),
+    /// Facts which cannot be sensed and only become true through action effects
+    Custom(u64),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct WorldState(SmallVec<[(WorldFact, bool); 8]>);
+impl WorldState {
+    pub fn with(mut self, fact: WorldFact, value: bool) -> Self {
+        self.set(fact, value);
+        self
+    }
+
+    pub fn set(&mut self, fact: WorldFact, value: bool) {
+        if let Some(entry) = self.0.iter_mut().find(|(f, _)| *f == fact) {
+            entry.1 = value;
+        } else {
+            self.0.push((fact, value));
+        }
+    }
+
+    pub fn get(&self, fact: WorldFact) -> Option<bool> {
+        self.0.iter().find(|(f, _)| *f == fact).map(|(_, v)| *v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(WorldFact, bool)> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Facts missing from this state are treated as false
+    pub fn satisfies(&self, other: &Self) -> bool {
+        other
+            .iter()
+            .all(|(fact, value)| self.get(*fact).unwrap_or(false) == *value)
+    }
+
+    pub fn unsatisfied(&self, other: &Self) -> usize {
+        other
+            .iter()
+            .filter(|(fact, value)| self.get(*fact).unwrap_or(false) != *value)
+            .count()
+    }
+
+    pub fn apply(&self, effects: &Self) -> Self {
+        let mut result = self.clone();
+        effects
+            .iter()
+            .for_each(|(fact, value)| result.set(*fact, *value));
+        // Keep a stable order so equal states hash the same
+        result.0.sort_by_key(|(fact, _)| fxhash::hash64(fact));
+        result
+    }
+}
+
+slotmap::new_key_type! { pub struct ActionHandle; }
+
+pub type ActionStorage = NamedSlotMap<ActionHandle, Action>;
+
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub name: String,
+    pub cost: f32,
+    pub preconditions: WorldState,
+    pub effects: WorldState,
+    /// Behavior executed to perform this step of a plan
+    pub behavior: String,
+}
+impl Action {
+    pub fn new(name: &str, behavior: &str, cost: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            behavior: behavior.to_string(),
+            cost,
+            preconditions: WorldState::default(),
+            effects: WorldState::default(),
+        }
+    }
+
+    pub fn with_precondition(mut self, fact: WorldFact, value: bool) -> Self {
+        self.preconditions.set(fact, value);
+        self
+    }
+
+    pub fn with_effect(mut self, fact: WorldFact, value: bool) -> Self {
+        self.effects.set(fact, value);
+        self
+    }
+}
+
+struct PlanNode {
+    state: WorldState,
+    steps: Vec<ActionHandle>,
+    cost: f32,
+    estimate: f32,
+}
+impl PartialEq for PlanNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for PlanNode {}
+impl PartialOrd for PlanNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for PlanNode {
+    // Reversed so the binary heap pops the cheapest node first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A* search forward from `current` over the action effects until `goal` is satisfied
+#[allow(clippy::cast_precision_loss)]
+pub fn plan(
+    current: &WorldState,
+    goal: &WorldState,
+    actions: &ActionStorage,
+) -> Option<Vec<ActionHandle>> {
+    let mut open = BinaryHeap::new();
+    let mut closed = FxHashSet::default();
+
+    open.push(PlanNode {
+        state: current.apply(&WorldState::default()),
+        steps: Vec::new(),
+        cost: 0.0,
+        estimate: current.unsatisfied(goal) as f32,
+    });
+
+    let mut iterations = 0;
+    while let Some(node) = open.pop() {
+        if node.state.satisfies(goal) {
+            return Some(node.steps);
+        }
+
+        iterations += 1;
+        if iterations > MAX_PLAN_ITERATIONS {
+            break;
+        }
+
+        if !closed.insert(node.state.clone()) {
+            continue;
+        }
+
+        for (_, handle, action) in actions.iter() {
+            if !node.state.satisfies(&action.preconditions) {
+                continue;
+            }
+
+            let state = node.state.apply(&action.effects);
+            if closed.contains(&state) {
+                continue;
+            }
+
+            let mut steps = node.steps.clone();
+            steps.push(*handle);
+
+            let cost = node.cost + action.cost;
+            open.push(PlanNode {
+                estimate: cost + state.unsatisfied(goal) as f32,
+                state,
+                steps,
+                cost,
+            });
+        }
+    }
+
+    None
+}
+
+/// Sense the current value of every fact the goal and the available actions care about
+pub fn sense(
+    world: &World,
+    resources: &Resources,
+    entity: Entity,
+    goal: &WorldState,
+    actions: &ActionStorage,
+) -> WorldState {
+    let items = resources.get::<DefinitionStorage<ItemDefinition>>().unwrap();
+
+    let mut state = WorldState::default();
+
+    let facts = goal.iter().chain(
+        actions
+            .iter()
+            .flat_map(|(_, _, action)| action.preconditions.iter()),
+    );
+
+    for (fact, _) in facts {
+        if state.get(*fact).is_some() {
+            continue;
+        }
+
+        let value = match *fact {
+            WorldFact::HasItemAbility(ability) => rl_core::inventory::find_item_recursive(
+                entity,
+                world,
+                |_, (_, comp)| {
+                    comp.fetch(&items)
+                        .abilities
+                        .iter()
+                        .any(|a| a.kind == ability)
+                },
+            )
+            .is_some(),
+            WorldFact::HasItemKind(kind) => {
+                rl_core::inventory::find_item_recursive(entity, world, |_, (_, comp)| {
+                    comp.fetch(&items).kind == kind
+                })
+                .is_some()
+            }
+            WorldFact::AtLocation(coord) => world
+                .get_component::<PositionComponent>(entity)
+                .map_or(false, |position| **position == coord),
+            WorldFact::NeedSatisfied(kind) => world
+                .get_component::<NeedsComponent>(entity)
+                .map_or(false, |needs| needs.get(kind).value >= 0),
+            WorldFact::WorkedTask(_) | WorldFact::Custom(_) => false,
+        };
+
+        state.set(*fact, value);
+    }
+
+    state
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoalComponent {
+    pub goal: WorldState,
+    /// Frame the plan was last executed on, the goal is dropped once the behavior stops
+    pub renewed: u64,
+}
+impl GoalComponent {
+    /// Frames a goal lasts without its plan being executed
+    pub const TIMEOUT: u64 = 2;
+
+    pub fn new(goal: WorldState, frame: u64) -> Self {
+        Self {
+            goal,
+            renewed: frame,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanStatus {
+    Ready,
+    Failed,
+    Impossible,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanComponent {
+    pub goal: WorldState,
+    pub steps: Vec<ActionHandle>,
+    pub current: usize,
+    pub status: PlanStatus,
+    pub attempts: u32,
+    /// Blackboard keys present before the first step, anything else belongs to a step
+    pub scope: Option<Vec<u64>>,
+}
+impl PlanComponent {
+    pub fn current_step(&self) -> Option<ActionHandle> {
+        self.steps.get(self.current).copied()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.steps.len()
+    }
+}
+
+pub fn build_planning_system(
+    _: &mut World,
+    _: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    let query = <Read<GoalComponent>>::query();
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("action_planning_system");
+
+        let (actions, time) = <(Read<ActionStorage>, Read<Time>)>::fetch(resources);
+
+        // Goals of behaviors which were pre-empted or finished without cleaning up
+        let lapsed = query
+            .iter_entities(world)
+            .filter(|(_, goal)| time.frame.saturating_sub(goal.renewed) > GoalComponent::TIMEOUT)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in lapsed {
+            world.remove_component::<GoalComponent>(entity).ok();
+            world.remove_component::<PlanComponent>(entity).ok();
+        }
+
+        let pending = query
+            .iter_entities(world)
+            .filter_map(|(entity, goal)| {
+                let needs_plan = world
+                    .get_component::<PlanComponent>(entity)
+                    .map_or(true, |plan| {
+                        plan.goal != goal.goal || plan.status == PlanStatus::Failed
+                    });
+
+                if needs_plan {
+                    Some((entity, goal.goal.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (entity, goal) in pending {
+            let attempts = world
+                .get_component::<PlanComponent>(entity)
+                .filter(|plan| plan.goal == goal)
+                .map_or(0, |plan| plan.attempts + 1);
+
+            let current = sense(world, resources, entity, &goal, &actions);
+
+            let result = if attempts > MAX_REPLANS {
+                None
+            } else {
+                plan(&current, &goal, &actions)
+            };
+
+            let component = match result {
+                Some(steps) => PlanComponent {
+                    goal,
+                    steps,
+                    current: 0,
+                    status: PlanStatus::Ready,
+                    attempts,
+                    scope: None,
+                },
+                None => PlanComponent {
+                    goal,
+                    steps: Vec::new(),
+                    current: 0,
+                    status: PlanStatus::Impossible,
+                    attempts,
+                    scope: None,
+                },
+            };
+
+            world.add_component(entity, component).unwrap();
+        }
+    })
+}
+
+pub mod nodes {
+    use super::*;
+
+    /// Request a plan for the goal and execute each of its actions behaviors in order
+    pub fn make_plan_for(goal: WorldState) -> Arc<dyn BehaviorNode> {
+        crate::bt::make::closure(None, move |state, args| execute_plan(state, args, &goal))
+    }
+
+    /// Like `make_plan_for`, with the goal chosen by `choose` when the node starts and kept until
+    /// the plan finishes. Fails if no goal is chosen.
+    pub fn make_plan_with<F>(choose: F) -> Arc<dyn BehaviorNode>
+    where
+        F: 'static + Fn(GameStateRef, &mut BehaviorArgs<'_>) -> Option<WorldState> + Send + Sync,
+    {
+        crate::bt::make::closure(None, move |state, args| {
+            let goal = match args.blackboard.get::<WorldState>(fnv!("PlanGoal")).cloned() {
+                Some(goal) => goal,
+                None => match choose(state, args) {
+                    Some(goal) => {
+                        args.blackboard.insert(fnv!("PlanGoal"), goal.clone());
+                        goal
+                    }
+                    None => return BehaviorStatus::failure(),
+                },
+            };
+
+            let status = execute_plan(state, args, &goal);
+            if status.is_complete() {
+                args.blackboard.remove(fnv!("PlanGoal"));
+            }
+            status
+        })
+    }
+
+    /// Removes the blackboard keys a finished step added, leaving the ones in `scope`
+    pub fn prune(blackboard: &mut BlackboardComponent, scope: &[u64]) {
+        blackboard.retain(|key, _| scope.contains(key));
+    }
+
+    pub fn execute_plan(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+        goal: &WorldState,
+    ) -> BehaviorStatus {
+        let frame = state.resources.get::<Time>().unwrap().frame;
+
+        match unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<GoalComponent>(args.entity)
+        } {
+            Some(mut current) if current.goal == *goal => current.renewed = frame,
+            _ => {
+                args.command_buffer
+                    .remove_component::<PlanComponent>(args.entity);
+                args.command_buffer
+                    .add_component(args.entity, GoalComponent::new(goal.clone(), frame));
+                return BehaviorStatus::running(true);
+            }
+        }
+
+        let mut plan =
+            match unsafe { state.world.get_component_mut_unchecked::<PlanComponent>(args.entity) }
+            {
+                Some(plan) if plan.goal == *goal => plan,
+                // Still waiting on the planner
+                _ => return BehaviorStatus::running(true),
+            };
+
+        let finish = |args: &mut BehaviorArgs<'_>, status: BehaviorStatus| {
+            args.command_buffer
+                .remove_component::<GoalComponent>(args.entity);
+            args.command_buffer
+                .remove_component::<PlanComponent>(args.entity);
+            status
+        };
+
+        match plan.status {
+            PlanStatus::Impossible => return finish(args, BehaviorStatus::failure()),
+            PlanStatus::Failed => return BehaviorStatus::running(true),
+            PlanStatus::Ready => {}
+        }
+
+        let (actions, behaviors) =
+            <(Read<ActionStorage>, Read<BehaviorStorage>)>::fetch(state.resources);
+
+        if plan.scope.is_none() {
+            plan.scope = Some(args.blackboard.keys().copied().collect());
+        }
+
+        while let Some(handle) = plan.current_step() {
+            let node = actions
+                .get(handle)
+                .and_then(|action| behaviors.get_by_name(&action.behavior))
+                .cloned();
+
+            let status = match node {
+                Some(node) => node.eval(state, args),
+                None => BehaviorStatus::failure(),
+            };
+
+            if status.is_running() {
+                return status;
+            }
+
+            // Each step starts from the blackboard the plan started with
+            prune(args.blackboard, plan.scope.as_ref().unwrap());
+
+            if status == BehaviorStatus::success() {
+                plan.current += 1;
+            } else {
+                // Replan from the current world state
+                plan.status = PlanStatus::Failed;
+                return BehaviorStatus::running(true);
+            }
+        }
+
+        finish(args, BehaviorStatus::success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOOL: WorldFact = WorldFact::HasItemAbility(ItemAbilityKind::Digging);
+    const DUG: WorldFact = WorldFact::Custom(1);
+    const HAULED: WorldFact = WorldFact::Custom(2);
+
+    fn names(actions: &ActionStorage, steps: &[ActionHandle]) -> Vec<String> {
+        steps
+            .iter()
+            .map(|step| actions.get(*step).unwrap().name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn plan_chain_test() {
+        let mut actions = ActionStorage::default();
+        actions.insert(
+            "haul",
+            Action::new("haul", "haul", 1.0)
+                .with_precondition(DUG, true)
+                .with_effect(HAULED, true),
+        );
+        actions.insert(
+            "dig",
+            Action::new("dig", "dig", 1.0)
+                .with_precondition(TOOL, true)
+                .with_effect(DUG, true),
+        );
+        actions.insert(
+            "fetch_tool",
+            Action::new("fetch_tool", "fetch_tool", 1.0).with_effect(TOOL, true),
+        );
+        // A step shorter, but dearer than fetching the tool first
+        actions.insert(
+            "dig_by_hand",
+            Action::new("dig_by_hand", "dig_by_hand", 5.0).with_effect(DUG, true),
+        );
+
+        let goal = WorldState::default().with(HAULED, true);
+
+        let steps = plan(&WorldState::default(), &goal, &actions).unwrap();
+        assert_eq!(names(&actions, &steps), ["fetch_tool", "dig", "haul"]);
+
+        // Facts already true are skipped
+        let current = WorldState::default().with(TOOL, true);
+        let steps = plan(&current, &goal, &actions).unwrap();
+        assert_eq!(names(&actions, &steps), ["dig", "haul"]);
+
+        // A goal which already holds needs nothing done
+        let current = WorldState::default().with(HAULED, true);
+        assert!(plan(&current, &goal, &actions).unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_test() {
+        let mut blackboard = BlackboardComponent::default();
+        blackboard.insert(fnv!("current_task"), 1_u32);
+        let scope = blackboard.keys().copied().collect::<Vec<_>>();
+
+        blackboard.insert(fnv!("PickupParameters"), 2_u32);
+        blackboard.insert(fnv!("current_task"), 3_u32);
+        nodes::prune(&mut blackboard, &scope);
+
+        assert!(!blackboard.contains(fnv!("PickupParameters")));
+        assert_eq!(blackboard.get::<u32>(fnv!("current_task")), Some(&3));
+    }
+
+    #[test]
+    fn plan_unreachable_test() {
+        let mut actions = ActionStorage::default();
+        actions.insert(
+            "dig",
+            Action::new("dig", "dig", 1.0)
+                .with_precondition(TOOL, true)
+                .with_effect(DUG, true),
+        );
+        actions.insert(
+            "haul",
+            Action::new("haul", "haul", 1.0)
+                .with_precondition(DUG, true)
+                .with_effect(HAULED, true),
+        );
+
+        // Nothing gives the tool
+        let goal = WorldState::default().with(HAULED, true);
+        assert_eq!(plan(&WorldState::default(), &goal, &actions), None);
+
+        // Nothing gives the fact at all
+        let goal = WorldState::default().with(WorldFact::Custom(3), true);
+        assert_eq!(plan(&WorldState::default(), &goal, &actions), None);
+    }
+}
@@ -1 +1,394 @@
+use crate::{
+    combat::{self, CombatEvent, CombatOutcome, Hit},
+    task::{self, TaskReservationEvent},
+};
+use rl_core::{
+    components::{
+        ActivePickupComponent, CarryComponent, CorpseComponent, DeadTag, Destroy,
+        ItemContainerChildComponent, ItemContainerComponent, MovementComponent, NameComponent,
+        PositionComponent,
+    },
+    data::{SpawnArguments, SpawnEvent, SpawnPosition, SpawnTarget},
+    defs::{
+        body::{BodyComponent, BodyDefinition, PartFlag, Wound},
+        building::BedComponent,
+        creature::{CreatureComponent, CreatureDefinition},
+        item::ItemDefinition,
+        material::{MaterialComponent, MaterialState},
+        Definition, DefinitionComponent, DefinitionStorage,
+    },
+    event::Channel,
+    legion::prelude::*,
+    rand::Rng,
+    time::Time,
+    Random,
+};
 
+/// Blood lost per second for each point of wound severity on a `CIRCULATION` part
+pub const BLEED_RATE: f32 = 0.1;
+
+/// Seconds for a wound's bleeding to clot to about a third
+pub const CLOT_TIME: f32 = 300.0;
+
+/// Chance a fresh wound becomes infected on an average immune system
+pub const INFECTION_CHANCE: f64 = 0.1;
+
+/// Infection an infected wound gains per hour
+pub const INFECTION_RATE: f32 = 0.1;
+
+/// Infection an average immune system clears per hour
+pub const IMMUNITY_RATE: f32 = 0.12;
+
+/// Wound severity and layer thickness an average healer restores per hour
+pub const HEAL_RATE: f32 = 1.0;
+
+/// Fraction of blood an average healer regenerates per hour
+pub const BLOOD_REGEN: f32 = 0.1;
+
+/// World seconds between infection and healing updates
+const HEALTH_INTERVAL: f64 = 600.0;
+
+/// Round with the fraction as the chance of rounding up, so small amounts still add up
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn stochastic_round<R: Rng>(rng: &mut R, value: f32) -> u32 {
+    let whole = value.floor();
+    let up = rng.gen_bool(f64::from(value - whole).max(0.0).min(1.0));
+    whole.max(0.0) as u32 + u32::from(up)
+}
+
+fn apply_hit<R: Rng>(
+    rng: &mut R,
+    body: &mut BodyComponent,
+    def: &BodyDefinition,
+    hit: &Hit,
+    immunity: f64,
+) {
+    let part = match def.graph.node_weight(hit.part) {
+        Some(part) => part,
+        None => return,
+    };
+    let state = match body.part_states.get_mut(&hit.part) {
+        Some(state) => state,
+        None => return,
+    };
+
+    for damage in &hit.layers {
+        state.damage_layer(part, damage.layer, damage.amount);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let penetration = hit.penetration as f32;
+    let severity = hit.layers.iter().map(|d| d.amount as f32).sum::<f32>() + penetration;
+    if severity <= 0.0 {
+        return;
+    }
+
+    let bleeding = if part.flags.contains(PartFlag::CIRCULATION) {
+        severity * BLEED_RATE
+    } else {
+        0.0
+    };
+    let infection = if rng.gen_bool((INFECTION_CHANCE / immunity.max(0.1)).min(1.0)) {
+        0.01
+    } else {
+        0.0
+    };
+
+    state.wounds.push(Wound {
+        severity,
+        bleeding,
+        infection,
+    });
+}
+
+/// Bleed every wound for `delta` seconds, clotting them as they go
+fn bleed(body: &mut BodyComponent, delta: f32) {
+    let clot = (-delta / CLOT_TIME).exp();
+
+    let mut lost = 0.0;
+    for state in body.part_states.values_mut() {
+        for wound in &mut state.wounds {
+            lost += wound.bleeding * delta;
+            wound.bleeding *= clot;
+        }
+    }
+
+    body.blood = (body.blood - lost).max(0.0);
+}
+
+/// Advance infection and healing by `hours`. Infected wounds fester until the immune system
+/// clears them and only then heal, a part is lost once its infection runs its course.
+fn heal<R: Rng>(rng: &mut R, body: &mut BodyComponent, hours: f32, healing: f32, immunity: f32) {
+    let infection_delta = (INFECTION_RATE - IMMUNITY_RATE * immunity) * hours;
+    let heal = HEAL_RATE * healing * hours;
+
+    for state in body.part_states.values_mut() {
+        if state.destroyed {
+            continue;
+        }
+
+        let mut infected = false;
+        for wound in &mut state.wounds {
+            if wound.infection > 0.0 {
+                wound.infection = (wound.infection + infection_delta).max(0.0);
+                infected |= wound.infection > 0.0;
+            } else {
+                wound.severity -= heal;
+            }
+        }
+
+        if state.infection() >= 1.0 {
+            state.destroyed = true;
+            continue;
+        }
+
+        state
+            .wounds
+            .retain(|wound| wound.severity > 0.0 || wound.infection > 0.0);
+        if infected {
+            continue;
+        }
+
+        let amount = stochastic_round(rng, heal);
+        for damage in state.layer_damage.iter_mut() {
+            *damage = damage.saturating_sub(amount);
+        }
+    }
+
+    let regen = body.max_blood * BLOOD_REGEN * healing * hours;
+    body.blood = (body.blood + regen).min(body.max_blood);
+}
+
+/// Replace a dead entity with a corpse item made of what is left of its body
+fn spawn_corpse(world: &World, resources: &Resources, entity: Entity, now: f64) {
+    let (bodies, creatures, items, spawn_channel) = <(
+        Read<DefinitionStorage<BodyDefinition>>,
+        Read<DefinitionStorage<CreatureDefinition>>,
+        Read<DefinitionStorage<ItemDefinition>>,
+        Read<Channel<SpawnEvent>>,
+    )>::fetch(resources);
+
+    let (corpse_def, position, body) = match (
+        items.get_by_name("Corpse"),
+        world.get_component::<PositionComponent>(entity),
+        world.get_component::<BodyComponent>(entity),
+    ) {
+        (Some(corpse_def), Some(position), Some(body)) => (corpse_def, **position, body),
+        _ => return,
+    };
+
+    let name = world
+        .get_component::<NameComponent>(entity)
+        .map(|name| name.name.clone())
+        .or_else(|| {
+            world
+                .get_component::<CreatureComponent>(entity)
+                .map(|creature| creature.fetch(&creatures).name().to_owned())
+        })
+        .unwrap_or_else(|| "Unknown".to_owned());
+
+    let corpse = CorpseComponent::new(name, body.id(), body.tissues(body.fetch(&bodies)), now);
+    let material = match corpse.material() {
+        Some(material) => MaterialComponent::new(material, MaterialState::Solid),
+        None => return,
+    };
+
+    spawn_channel
+        .write(SpawnEvent {
+            target: SpawnTarget::Position(SpawnPosition::Tile(position)),
+            kind: SpawnArguments::Corpse { material, corpse },
+            id: corpse_def.id().into(),
+            arguments: (),
+        })
+        .unwrap();
+}
+
+/// Drops everything a dying entity holds on its tile and gives up its pickups, tasks and bed, so
+/// nothing is left pointing at it once it is destroyed
+fn release_belongings(world: &mut World, resources: &Resources, entity: Entity) {
+    let position = match world.get_component::<PositionComponent>(entity) {
+        Some(position) => *position,
+        None => return,
+    };
+
+    let mut held = world
+        .get_component::<CarryComponent>(entity)
+        .map_or_else(Vec::new, |carry| carry.iter().collect::<Vec<_>>());
+    if let Some(container) = world.get_component::<ItemContainerComponent>(entity) {
+        held.extend(container.inside.iter().chain(&container.queued_inside));
+    }
+    for item in held {
+        if let Some(mut item_position) = world.get_component_mut::<PositionComponent>(item) {
+            *item_position = position;
+        }
+        world
+            .remove_component::<ItemContainerChildComponent>(item)
+            .ok();
+    }
+
+    release_claims(world, resources, entity);
+}
+
+/// Drops whatever `entity` holds in limbs which no longer work onto its tile
+fn drop_from_disabled_limbs(
+    world: &mut World,
+    bodies: &DefinitionStorage<BodyDefinition>,
+    entity: Entity,
+) {
+    let position = match world.get_component::<PositionComponent>(entity) {
+        Some(position) => *position,
+        None => return,
+    };
+
+    let disabled = match (
+        world.get_component::<BodyComponent>(entity),
+        world.get_component::<CarryComponent>(entity),
+    ) {
+        (Some(body), Some(carry)) => {
+            let def = body.fetch(bodies);
+            carry
+                .limbs
+                .iter()
+                .filter(|(limb, item)| item.is_some() && !body.is_part_functional(def, *limb))
+                .map(|(limb, _)| *limb)
+                .collect::<Vec<_>>()
+        }
+        _ => return,
+    };
+    if disabled.is_empty() {
+        return;
+    }
+
+    let mut dropped = Vec::new();
+    if let Some(mut carry) = world.get_component_mut::<CarryComponent>(entity) {
+        for (limb, item) in &mut carry.limbs {
+            if disabled.contains(limb) {
+                dropped.extend(item.take());
+            }
+        }
+    }
+
+    for item in dropped {
+        if let Some(mut item_position) = world.get_component_mut::<PositionComponent>(item) {
+            *item_position = position;
+        }
+        world
+            .remove_component::<ItemContainerChildComponent>(item)
+            .ok();
+    }
+}
+
+/// Gives up the pickups, tasks and bed held by `entity`
+pub fn release_claims(world: &mut World, resources: &Resources, entity: Entity) {
+    let pickups = <Read<ActivePickupComponent>>::query()
+        .iter_entities(world)
+        .filter(|(_, pickup)| pickup.initiator == entity)
+        .map(|(item, _)| item)
+        .collect::<Vec<_>>();
+    for item in pickups {
+        world.remove_component::<ActivePickupComponent>(item).ok();
+    }
+
+    for mut bed in <Write<BedComponent>>::query().iter_mut(world) {
+        if bed.owner == Some(entity) {
+            bed.owner = None;
+        }
+    }
+
+    if let Some(channel) = resources.get::<Channel<TaskReservationEvent>>() {
+        task::release_held_tasks(world, &channel, entity);
+    }
+}
+
+/// Applies combat hits to bodies, bleeds and heals them over time and kills anything which bled
+/// out or lost a vital part, leaving a corpse in its place.
+pub fn build_health_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<Channel<CombatEvent>>() {
+        resources.insert(Channel::<CombatEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<CombatEvent>>()
+        .unwrap()
+        .bind_listener(256);
+
+    let query = <Read<BodyComponent>>::query().filter(!tag::<DeadTag>());
+    let mut last_update = None;
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("health_system");
+
+        let (channel, bodies, time, random) = <(
+            Read<Channel<CombatEvent>>,
+            Read<DefinitionStorage<BodyDefinition>>,
+            Read<Time>,
+            Read<Random>,
+        )>::fetch(resources);
+        let mut rng = random.make();
+
+        while let Some(event) = channel.read(listener_id) {
+            let hit = match &event.outcome {
+                CombatOutcome::Hit(hit) => hit,
+                CombatOutcome::Miss => continue,
+            };
+            let immunity = combat::attribute(world, event.defender, |a| a.immunity);
+
+            if let Some(mut body) = world.get_component_mut::<BodyComponent>(event.defender) {
+                let def = body.fetch(&bodies);
+                apply_hit(&mut rng, &mut body, def, hit, immunity);
+            }
+
+            // A crippled hand lets go of what it held
+            drop_from_disabled_limbs(world, &bodies, event.defender);
+        }
+
+        let delta = time.world_delta.as_secs_f32();
+        let since = time.world_time - *last_update.get_or_insert(time.world_time);
+        let hours = if since >= HEALTH_INTERVAL {
+            last_update = Some(time.world_time);
+            Some(since / 3600.0)
+        } else {
+            None
+        };
+
+        let entities = query
+            .iter_entities(world)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        let mut dead = Vec::new();
+        for entity in entities {
+            #[allow(clippy::cast_possible_truncation)]
+            let (healing, immunity) = (
+                combat::attribute(world, entity, |a| a.healing) as f32,
+                combat::attribute(world, entity, |a| a.immunity) as f32,
+            );
+
+            let mut body = world.get_component_mut::<BodyComponent>(entity).unwrap();
+            let def = body.fetch(&bodies);
+
+            bleed(&mut body, delta);
+            if let Some(hours) = hours {
+                #[allow(clippy::cast_possible_truncation)]
+                heal(&mut rng, &mut body, hours as f32, healing, immunity);
+            }
+
+            if body.is_dead(def) {
+                dead.push(entity);
+            }
+        }
+
+        for entity in dead {
+            release_belongings(world, resources, entity);
+            spawn_corpse(world, resources, entity, time.world_time);
+
+            world.add_tag(entity, DeadTag).unwrap();
+            world.add_component(entity, Destroy::default()).unwrap();
+            if let Some(mut movement) = world.get_component_mut::<MovementComponent>(entity) {
+                movement.current = None;
+            }
+        }
+    })
+}
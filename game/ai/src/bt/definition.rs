@@ -0,0 +1,315 @@
+use crate::{
+    body,
+    bt::{
+        make, BehaviorArgs, BehaviorHandle, BehaviorNode, BehaviorResult, BehaviorStatus,
+        BehaviorStorage, BehaviorTreeComponent,
+    },
+    SensesComponent,
+};
+use rl_core::{
+    components::{BlackboardComponent, MovementComponent},
+    defs::{
+        behavior::{BehaviorDefinition, BehaviorNodeDefinition, LoopCondition},
+        DefaultDefinitionLoader, Definition, DefinitionLoader, DefinitionStorage,
+    },
+    failure,
+    fxhash::{FxHashMap, FxHashSet},
+    legion::prelude::*,
+    shrinkwrap::Shrinkwrap,
+    time::Time,
+    GameStateRef,
+};
+use std::{sync::Arc, time::SystemTime};
+
+/// Registered node run on entities whose behavior is restarted by a reload, before their
+/// blackboard is cleared
+pub const RELEASE_NODE: &str = "release_held";
+
+/// Leaf nodes which can be referenced by name from behavior definitions
+#[derive(Shrinkwrap, Default, Clone)]
+pub struct BehaviorNodeRegistry(pub FxHashMap<String, Arc<dyn BehaviorNode>>);
+impl BehaviorNodeRegistry {
+    pub fn register(&mut self, name: &str, node: Arc<dyn BehaviorNode>) {
+        if self.0.insert(name.to_lowercase(), node).is_some() {
+            panic!("Duplicate behavior node registered: {}", name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn BehaviorNode>> {
+        self.0.get(&name.to_lowercase())
+    }
+}
+
+pub fn validate(
+    def: &BehaviorDefinition,
+    registry: &BehaviorNodeRegistry,
+    storage: &BehaviorStorage,
+) -> Result<(), failure::Error> {
+    let mut result = Ok(());
+
+    def.root.visit(&mut |node| {
+        if result.is_err() {
+            return;
+        }
+
+        result = match node {
+            BehaviorNodeDefinition::Node(name) if registry.get(name).is_none() => Err(
+                failure::format_err!("[{}]: Unknown behavior node '{}'", def.name(), name),
+            ),
+            BehaviorNodeDefinition::Sub(name) | BehaviorNodeDefinition::Switch(name)
+                if storage.get_handle(name).is_none() =>
+            {
+                Err(failure::format_err!(
+                    "[{}]: Unknown behavior reference '{}'",
+                    def.name(),
+                    name
+                ))
+            }
+            _ => Ok(()),
+        };
+    });
+
+    result
+}
+
+pub fn build_node(
+    node: &BehaviorNodeDefinition,
+    registry: &BehaviorNodeRegistry,
+    storage: &BehaviorStorage,
+) -> Result<Arc<dyn BehaviorNode>, failure::Error> {
+    let build_all = |children: &[BehaviorNodeDefinition]| {
+        children
+            .iter()
+            .map(|child| build_node(child, registry, storage))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    Ok(match node {
+        BehaviorNodeDefinition::Node(name) => registry
+            .get(name)
+            .ok_or_else(|| failure::format_err!("Unknown behavior node '{}'", name))?
+            .clone(),
+        BehaviorNodeDefinition::Sequence(children) => make::sequence(&build_all(children)?),
+        BehaviorNodeDefinition::Selector(children) => make::selector(&build_all(children)?),
+        BehaviorNodeDefinition::All(children) => make::all(&build_all(children)?),
+        BehaviorNodeDefinition::Not(child) => make::not(build_node(child, registry, storage)?),
+        BehaviorNodeDefinition::IfElse {
+            condition,
+            success,
+            failure,
+        } => make::if_else(
+            build_node(condition, registry, storage)?,
+            build_node(success, registry, storage)?,
+            build_node(failure, registry, storage)?,
+        ),
+        BehaviorNodeDefinition::TryUntil { limit, until, node } => make::try_until(
+            *limit,
+            match until {
+                LoopCondition::Success => BehaviorResult::Success,
+                LoopCondition::Failure => BehaviorResult::Failure,
+            },
+            build_node(node, registry, storage)?,
+        ),
+        BehaviorNodeDefinition::Sub(name) | BehaviorNodeDefinition::Switch(name)
+            if storage.get_handle(name).is_none() =>
+        {
+            return Err(failure::format_err!(
+                "Unknown behavior reference '{}'",
+                name
+            ));
+        }
+        BehaviorNodeDefinition::Sub(name) => make::sub(name, storage),
+        BehaviorNodeDefinition::Switch(name) => make::switch(name, storage),
+    })
+}
+
+/// Builds all behavior definitions into the storage. Existing behaviors with the same name keep their
+/// handle and are replaced in place. Returns the handles of every behavior which was built.
+pub fn load(
+    storage: &mut BehaviorStorage,
+    registry: &BehaviorNodeRegistry,
+    defs: &DefinitionStorage<BehaviorDefinition>,
+) -> Result<Vec<BehaviorHandle>, failure::Error> {
+    // Reserve handles first so definitions can reference each other regardless of order
+    let handles = defs
+        .iter()
+        .map(|def| {
+            storage.get_handle(def.name()).unwrap_or_else(|| {
+                storage.insert(
+                    def.name(),
+                    make::closure(None, |_, _| BehaviorStatus::failure()),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for def in defs.iter() {
+        validate(def, registry, storage)?;
+    }
+
+    let built = defs
+        .iter()
+        .map(|def| {
+            build_node(&def.root, registry, storage)
+                .map_err(|e| failure::format_err!("[{}]: {}", def.name(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (handle, node) in handles.iter().zip(built) {
+        *storage.get_mut(*handle).unwrap() = node;
+    }
+
+    Ok(handles)
+}
+
+/// Lets go of everything the running behaviors of `entities` hold: whatever the registered
+/// `RELEASE_NODE` releases from their blackboards, then their pickups, tasks and beds.
+fn release(world: &mut World, resources: &Resources, entities: &[Entity]) {
+    let mut command_buffer = CommandBuffer::new(world);
+
+    if let Some(node) = resources
+        .get::<BehaviorNodeRegistry>()
+        .and_then(|registry| registry.get(RELEASE_NODE).cloned())
+    {
+        for entity in entities {
+            let (senses, tree) = match (
+                world.get_component::<SensesComponent>(*entity),
+                world.get_component::<BehaviorTreeComponent>(*entity),
+            ) {
+                (Some(senses), Some(tree)) => (senses, tree),
+                _ => continue,
+            };
+            let mut blackboard = match unsafe {
+                world.get_component_mut_unchecked::<BlackboardComponent>(*entity)
+            } {
+                Some(blackboard) => blackboard,
+                None => continue,
+            };
+
+            node.eval(
+                GameStateRef { world, resources },
+                &mut BehaviorArgs {
+                    entity: *entity,
+                    blackboard: &mut blackboard,
+                    tree: &tree,
+                    senses: &senses,
+                    command_buffer: &mut command_buffer,
+                },
+            );
+        }
+    }
+
+    command_buffer.write(world);
+
+    for entity in entities {
+        body::release_claims(world, resources, *entity);
+    }
+}
+
+/// Reload behavior definitions from disk and restart any entity running one of them. The previous
+/// definitions and behaviors stay in place if any of the new ones fail to load.
+pub fn reload(world: &mut World, resources: &mut Resources) -> Result<(), failure::Error> {
+    let previous = resources
+        .remove::<DefinitionStorage<BehaviorDefinition>>()
+        .ok_or_else(|| failure::err_msg("Behavior definitions were never loaded"))?;
+
+    // Keep the old definitions around if the new ones fail to parse
+    if let Err(e) = DefinitionStorage::<BehaviorDefinition>::from_folder(
+        resources,
+        previous.source().to_path_buf(),
+    ) {
+        resources.insert(previous);
+        return Err(e);
+    }
+
+    let reloaded = {
+        let (mut storage, registry, defs) = <(
+            Write<BehaviorStorage>,
+            Read<BehaviorNodeRegistry>,
+            Read<DefinitionStorage<BehaviorDefinition>>,
+        )>::fetch_mut(resources);
+
+        // Build into a copy so a bad definition can't leave placeholders or half a reload behind
+        let mut staged = BehaviorStorage::clone(&storage);
+        load(&mut staged, &registry, &defs).map(|handles| {
+            *storage = staged;
+            handles.into_iter().collect::<FxHashSet<_>>()
+        })
+    };
+    let reloaded = match reloaded {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            resources.insert(previous);
+            return Err(e);
+        }
+    };
+
+    let entities = <Read<BehaviorTreeComponent>>::query()
+        .iter_entities(world)
+        .filter_map(|(entity, tree)| {
+            tree.root
+                .handle()
+                .filter(|handle| reloaded.contains(handle))
+                .map(|_| entity)
+        })
+        .collect::<Vec<_>>();
+
+    release(world, &*resources, &entities);
+
+    for entity in entities {
+        if let Some(mut tree) = world.get_component_mut::<BehaviorTreeComponent>(entity) {
+            *tree = BehaviorTreeComponent::default();
+        }
+        if let Some(mut blackboard) = world.get_component_mut::<BlackboardComponent>(entity) {
+            blackboard.clear();
+        }
+        if let Some(mut movement) = world.get_component_mut::<MovementComponent>(entity) {
+            movement.current = None;
+        }
+    }
+
+    log::info!(target: "behavior", "Reloaded {} behaviors", reloaded.len());
+
+    Ok(())
+}
+
+fn last_modified(defs: &DefinitionStorage<BehaviorDefinition>) -> Option<SystemTime> {
+    <DefaultDefinitionLoader<BehaviorDefinition> as DefinitionLoader>::collect_files(defs.source())
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+pub fn build_reload_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    const POLL_INTERVAL: f64 = 1.0;
+
+    let mut last_poll = 0.0;
+    let mut last_change = resources
+        .get::<DefinitionStorage<BehaviorDefinition>>()
+        .and_then(|defs| last_modified(&defs));
+
+    Box::new(move |world, resources| {
+        {
+            let time = resources.get::<Time>().unwrap();
+            if time.real_time - last_poll < POLL_INTERVAL {
+                return;
+            }
+            last_poll = time.real_time;
+        }
+
+        let modified = resources
+            .get::<DefinitionStorage<BehaviorDefinition>>()
+            .and_then(|defs| last_modified(&defs));
+
+        if modified != last_change {
+            last_change = modified;
+
+            if let Err(e) = reload(world, resources) {
+                log::error!(target: "behavior", "Failed to reload behaviors: {}", e);
+            }
+        }
+    })
+}
@@ -2,6 +2,7 @@ use crate::SensesComponent;
 use rl_core::{
     components::{BlackboardComponent, DeadTag, MovementComponent},
     derivative::Derivative,
+    fxhash::FxHashMap,
     legion::prelude::*,
     shrinkwrap::Shrinkwrap,
     slotmap,
@@ -10,6 +11,8 @@ use rl_core::{
 };
 use std::sync::Arc;
 
+pub mod definition;
+
 slotmap::new_key_type! { pub struct BehaviorHandle; }
 
 #[derive(Shrinkwrap, Default, Clone)]
@@ -67,6 +70,16 @@ impl Default for BehaviorTreeComponent {
     }
 }
 
+/// The behavior every tree was running when the current behavior pass began. Nodes look up
+/// other entities here, as their trees are borrowed while the pass runs.
+#[derive(Debug, Default)]
+pub struct RunningBehaviors(pub FxHashMap<Entity, BehaviorHandle>);
+impl RunningBehaviors {
+    pub fn get(&self, entity: Entity) -> Option<BehaviorHandle> {
+        self.0.get(&entity).copied()
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct BehaviorArgs<'a> {
@@ -136,6 +149,10 @@ impl BehaviorStatus {
         }
     }
 
+    pub fn result(self) -> BehaviorResult {
+        self.result
+    }
+
     pub fn is_running(self) -> bool {
         self.result == BehaviorResult::Running
     }
@@ -169,9 +186,14 @@ pub trait BehaviorNode: Send + Sync {
     }
 }
 
-pub fn system(world: &mut World, _: &mut Resources) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+pub fn system(
+    world: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
     game_metrics::scope!("behavior_execution_system");
 
+    resources.insert(RunningBehaviors::default());
+
     let pawn_query = <(
         Read<SensesComponent>,
         Write<BehaviorTreeComponent>,
@@ -182,6 +204,14 @@ pub fn system(world: &mut World, _: &mut Resources) -> Box<dyn FnMut(&mut World,
     let mut command_buffer = CommandBuffer::new(world);
 
     Box::new(move |world: &mut World, resources: &mut Resources| {
+        let running = unsafe {
+            pawn_query
+                .iter_entities_unchecked(world)
+                .filter_map(|(entity, (_, tree, _))| tree.root.handle().map(|root| (entity, root)))
+                .collect()
+        };
+        resources.insert(RunningBehaviors(running));
+
         let behavior_storage = <Read<BehaviorStorage>>::fetch(&resources);
 
         let entities = unsafe {
@@ -375,6 +405,21 @@ impl BehaviorNode for BehaviorKind {
     }
 }
 
+/// Late bound reference to another behavior, so replacing the target in storage is picked up by its users
+pub struct SubBehavior(BehaviorHandle);
+impl BehaviorNode for SubBehavior {
+    fn eval(&self, state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let node = state
+            .resources
+            .get::<BehaviorStorage>()
+            .unwrap()
+            .get(self.0)
+            .cloned();
+
+        node.map_or_else(BehaviorStatus::failure, |node| node.eval(state, args))
+    }
+}
+
 pub mod make {
     use super::*;
 
@@ -437,7 +482,7 @@ pub mod make {
     }
 
     pub fn sub(name: &str, storage: &BehaviorStorage) -> Arc<dyn BehaviorNode> {
-        storage.get_by_name(name).unwrap().clone()
+        Arc::new(SubBehavior(storage.get_handle(name).unwrap()))
     }
 
     pub fn closure<F>(condition: Option<&str>, f: F) -> Arc<dyn BehaviorNode>
@@ -0,0 +1,521 @@
+use crate::perception::SensesComponent;
+use rl_core::{
+    components::{
+        AttributesComponent, CarryComponent, CreatureTag, DeadTag, PawnTag, PositionComponent,
+    },
+    data::{NoiseEvent, NoiseKind, PartGraphId},
+    defs::{
+        body::{AttackKind, BodyComponent, BodyDefinition, PartDefinition, PartFlag, PartLayer},
+        creature::{CreatureComponent, CreatureDefinition},
+        item::{ItemAbilityKind, ItemComponent, ItemDefinition, ItemKind},
+        material::{MaterialComponent, MaterialDefinition, MaterialState, MaterialStateDefinition},
+        Definition, DefinitionComponent, DefinitionStorage,
+    },
+    event::Channel,
+    legion::prelude::*,
+    math::Vec3i,
+    rand::Rng,
+    smallvec::SmallVec,
+    time::Time,
+};
+use std::collections::VecDeque;
+
+/// Seconds between attacks for an average pawn
+pub const ATTACK_INTERVAL: f64 = 2.0;
+
+/// Seconds a hit is remembered for when deciding to fight back or retreat
+pub const RECENT_HIT: f64 = 30.0;
+
+/// Entries kept in the combat log
+pub const COMBAT_LOG_CAPACITY: usize = 256;
+
+/// Distance in tiles the sound of a fight carries
+pub const COMBAT_NOISE: u32 = 16;
+
+/// Force of a blow from a part the size of a human arm, before strength
+const PART_FORCE: f64 = 5.0;
+
+/// Force of a swing with an item, on top of its weight
+const ITEM_FORCE: f64 = 5.0;
+
+/// Attribute value an average member of a race has
+const AVERAGE_ATTRIBUTE: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttackSource {
+    Part(PartGraphId),
+    Item(Entity),
+}
+
+/// One way an entity can strike right now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attack {
+    pub source: AttackSource,
+    pub kind: AttackKind,
+    /// Hardness of the striking surface, edges glance off anything at least as hard
+    pub hardness: i64,
+    pub force: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerDamage {
+    /// Index into the part's layers, outermost first
+    pub layer: usize,
+    /// Thickness of the layer destroyed
+    pub amount: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub part: PartGraphId,
+    pub layers: SmallVec<[LayerDamage; 4]>,
+    /// Force which made it through every layer, into the part itself
+    pub penetration: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CombatOutcome {
+    Miss,
+    Hit(Hit),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombatEvent {
+    pub attacker: Entity,
+    pub defender: Entity,
+    pub attack: Attack,
+    pub outcome: CombatOutcome,
+    pub time: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CombatComponent {
+    /// World time the next attack can be made
+    pub next_attack: f64,
+    pub last_attacker: Option<Entity>,
+    /// World time we were last attacked
+    pub last_hit: f64,
+}
+impl CombatComponent {
+    /// 1.0 right after being attacked, falling to 0.0 over `RECENT_HIT` seconds
+    pub fn recently_hit(&self, now: f64) -> f64 {
+        if self.last_attacker.is_none() {
+            return 0.0;
+        }
+
+        (1.0 - (now - self.last_hit) / RECENT_HIT).max(0.0)
+    }
+}
+
+/// The most recent combat events, oldest first
+#[derive(Debug, Default)]
+pub struct CombatLog {
+    pub entries: VecDeque<CombatEvent>,
+}
+impl CombatLog {
+    pub fn push(&mut self, event: CombatEvent) {
+        if self.entries.len() >= COMBAT_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(event);
+    }
+
+    pub fn involving(&self, entity: Entity) -> impl Iterator<Item = &CombatEvent> {
+        self.entries
+            .iter()
+            .filter(move |event| event.attacker == entity || event.defender == entity)
+    }
+}
+
+/// An attribute of `entity` relative to the average, 1.0 for anything without attributes
+pub(crate) fn attribute(
+    world: &World,
+    entity: Entity,
+    f: impl Fn(&AttributesComponent) -> u16,
+) -> f64 {
+    world
+        .get_component::<AttributesComponent>(entity)
+        .map_or(AVERAGE_ATTRIBUTE, |attributes| f64::from(f(&attributes)))
+        / AVERAGE_ATTRIBUTE
+}
+
+fn solid_state(material: &MaterialDefinition) -> Option<&MaterialStateDefinition> {
+    material
+        .states
+        .get(&MaterialState::Solid)
+        .or_else(|| material.states.values().next())
+}
+
+/// The hardest layer of a part, which is what it strikes with
+fn part_hardness(part: &PartDefinition, materials: &DefinitionStorage<MaterialDefinition>) -> i64 {
+    part.layers
+        .iter()
+        .filter_map(|layer| layer.material.fetch(materials))
+        .filter_map(solid_state)
+        .map(|state| state.hardness)
+        .max()
+        .unwrap_or(0)
+}
+
+fn item_attack_kind(item: &ItemDefinition) -> AttackKind {
+    if item
+        .abilities
+        .iter()
+        .any(|a| a.kind == ItemAbilityKind::Cutting || a.kind == ItemAbilityKind::Chopping)
+    {
+        AttackKind::Edged
+    } else if item
+        .abilities
+        .iter()
+        .any(|a| a.kind == ItemAbilityKind::Digging)
+    {
+        AttackKind::Pierce
+    } else {
+        AttackKind::Blunt
+    }
+}
+
+/// Every attack `entity` can make: wielded weapons, `WEAPON` parts and empty `MANIPULATE` parts
+pub fn available_attacks(
+    world: &World,
+    resources: &Resources,
+    entity: Entity,
+) -> SmallVec<[Attack; 4]> {
+    let (bodies, items, materials) = <(
+        Read<DefinitionStorage<BodyDefinition>>,
+        Read<DefinitionStorage<ItemDefinition>>,
+        Read<DefinitionStorage<MaterialDefinition>>,
+    )>::fetch(resources);
+
+    let strength = attribute(world, entity, |a| a.strength);
+    let mut attacks = SmallVec::new();
+
+    let carry = world.get_component::<CarryComponent>(entity);
+    let holding = |part: PartGraphId| {
+        carry.as_ref().map_or(false, |carry| {
+            carry
+                .limbs
+                .iter()
+                .any(|(limb, item)| *limb == part && item.is_some())
+        })
+    };
+
+    if let Some(carry) = &carry {
+        for held in carry.iter() {
+            let item = match world.get_component::<ItemComponent>(held) {
+                Some(item) => item.fetch(&items),
+                None => continue,
+            };
+            if item.kind != ItemKind::Weapon && item.abilities.is_empty() {
+                continue;
+            }
+
+            let hardness = world
+                .get_component::<MaterialComponent>(held)
+                .map_or(0, |material| material.fetch_state(&materials).hardness);
+
+            attacks.push(Attack {
+                source: AttackSource::Item(held),
+                kind: item_attack_kind(item),
+                hardness,
+                force: strength * (ITEM_FORCE + item.weight as f64),
+            });
+        }
+    }
+
+    if let Some(body) = world.get_component::<BodyComponent>(entity) {
+        let def = body.fetch(&bodies);
+        for (idx, state) in &body.part_states {
+            let part = match def.graph.node_weight(*idx) {
+                Some(part) if state.is_functional(part) => part,
+                _ => continue,
+            };
+
+            let kind = if part.flags.contains(PartFlag::WEAPON) {
+                part.attack.unwrap_or_default()
+            } else if part.flags.contains(PartFlag::MANIPULATE) && !holding(*idx) {
+                AttackKind::Blunt
+            } else {
+                continue;
+            };
+
+            attacks.push(Attack {
+                source: AttackSource::Part(*idx),
+                kind,
+                hardness: part_hardness(part, &materials),
+                force: strength * PART_FORCE * f64::from(part.relative_size) / 100.0,
+            });
+        }
+    }
+
+    attacks
+}
+
+/// Pick a part of `body` to hit, bigger parts are hit more often and lost parts never
+pub fn pick_target_part<R: Rng>(
+    rng: &mut R,
+    def: &BodyDefinition,
+    body: &BodyComponent,
+) -> Option<PartGraphId> {
+    let parts = body
+        .part_states
+        .iter()
+        .filter(|(_, state)| !state.destroyed)
+        .filter_map(|(idx, _)| {
+            def.graph
+                .node_weight(*idx)
+                .map(|part| (*idx, part.relative_size.max(1)))
+        })
+        .collect::<SmallVec<[(PartGraphId, u32); 16]>>();
+
+    let total = parts.iter().map(|(_, size)| size).sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for (idx, size) in parts {
+        if roll < size {
+            return Some(idx);
+        }
+        roll -= size;
+    }
+
+    None
+}
+
+/// Pass an attack through the layers of a part. Each layer absorbs force in proportion to its
+/// thickness; edges cut against shear and glance into blunt blows off hard layers, blunt blows
+/// are resisted by impact toughness.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn resolve_layers(
+    attack: &Attack,
+    layers: &[PartLayer],
+    materials: &DefinitionStorage<MaterialDefinition>,
+) -> (SmallVec<[LayerDamage; 4]>, f64) {
+    let mut force = attack.force;
+    let mut kind = attack.kind;
+    let mut damage = SmallVec::new();
+
+    for (index, layer) in layers.iter().enumerate() {
+        if force <= 0.0 {
+            break;
+        }
+
+        let state = match layer.material.fetch(materials).and_then(solid_state) {
+            Some(state) => state,
+            None => continue,
+        };
+
+        if kind != AttackKind::Blunt && attack.hardness <= state.hardness {
+            kind = AttackKind::Blunt;
+            force /= 2.0;
+        }
+
+        let resistance = match kind {
+            AttackKind::Blunt => state.impact_toughness,
+            AttackKind::Edged => state.shear_modulus,
+            AttackKind::Pierce => state.shear_modulus / 2,
+        };
+        let cost =
+            (f64::from(layer.thickness) * resistance.max(1) as f64 / AVERAGE_ATTRIBUTE).max(0.1);
+
+        let fraction = (force / cost).min(1.0);
+        let amount = (f64::from(layer.thickness) * fraction).round() as u32;
+        if amount > 0 {
+            damage.push(LayerDamage {
+                layer: index,
+                amount,
+            });
+        }
+
+        force -= cost;
+    }
+
+    (damage, force.max(0.0))
+}
+
+/// Roll a single attack against `defender`, agile defenders dodge more often
+pub fn roll_attack<R: Rng>(
+    rng: &mut R,
+    world: &World,
+    resources: &Resources,
+    attacker: Entity,
+    defender: Entity,
+    attack: Attack,
+) -> CombatEvent {
+    let (bodies, materials, time) = <(
+        Read<DefinitionStorage<BodyDefinition>>,
+        Read<DefinitionStorage<MaterialDefinition>>,
+        Read<Time>,
+    )>::fetch(resources);
+
+    let chance = (0.5
+        + (attribute(world, attacker, |a| a.agility) - attribute(world, defender, |a| a.agility))
+            / 2.0)
+        .max(0.1)
+        .min(0.95);
+
+    let hit = if rng.gen_bool(chance) {
+        world
+            .get_component::<BodyComponent>(defender)
+            .and_then(|body| {
+                let def = body.fetch(&bodies);
+                let part = pick_target_part(rng, def, &body)?;
+                let (layers, penetration) =
+                    resolve_layers(&attack, &def.graph.node_weight(part)?.layers, &materials);
+
+                Some(Hit {
+                    part,
+                    layers,
+                    penetration,
+                })
+            })
+    } else {
+        None
+    };
+
+    CombatEvent {
+        attacker,
+        defender,
+        attack,
+        outcome: hit.map_or(CombatOutcome::Miss, CombatOutcome::Hit),
+        time: time.world_time,
+    }
+}
+
+/// Seconds until `entity` can attack again
+pub fn attack_interval(world: &World, entity: Entity) -> f64 {
+    ATTACK_INTERVAL / attribute(world, entity, |a| a.agility).max(0.25)
+}
+
+/// Whether `entity` would fight `other`. Pawns fight hostile creatures, hostile creatures fight
+/// everything not of their kind, and anyone fights back against whoever recently attacked them.
+pub fn is_enemy(
+    world: &World,
+    creatures: &DefinitionStorage<CreatureDefinition>,
+    now: f64,
+    entity: Entity,
+    other: Entity,
+) -> bool {
+    if entity == other || world.get_tag::<DeadTag>(other).is_some() {
+        return false;
+    }
+
+    let attacked_by = world
+        .get_component::<CombatComponent>(entity)
+        .map_or(false, |combat| {
+            combat.last_attacker == Some(other) && combat.recently_hit(now) > 0.0
+        });
+    if attacked_by {
+        return true;
+    }
+
+    let creature = |e| {
+        world
+            .get_component::<CreatureComponent>(e)
+            .map(|creature| creature.fetch(creatures))
+    };
+
+    if world.get_tag::<PawnTag>(entity).is_some() {
+        return creature(other).map_or(false, |theirs| theirs.hostile);
+    }
+
+    match creature(entity) {
+        Some(ours) if ours.hostile => {
+            world.get_tag::<PawnTag>(other).is_some()
+                || (world.get_tag::<CreatureTag>(other).is_some()
+                    && creature(other).map_or(false, |theirs| theirs.id() != ours.id()))
+        }
+        _ => false,
+    }
+}
+
+/// Whether `entity` should avoid `other`: anyone who would fight it, and pawns for peaceful creatures
+pub fn is_threat(
+    world: &World,
+    creatures: &DefinitionStorage<CreatureDefinition>,
+    now: f64,
+    entity: Entity,
+    other: Entity,
+) -> bool {
+    if is_enemy(world, creatures, now, other, entity) {
+        return true;
+    }
+
+    world.get_tag::<PawnTag>(other).is_some()
+        && world
+            .get_component::<CreatureComponent>(entity)
+            .map_or(false, |ours| !ours.fetch(creatures).hostile)
+}
+
+/// Nearest perceived enemy of `entity`
+pub fn nearest_enemy(
+    world: &World,
+    creatures: &DefinitionStorage<CreatureDefinition>,
+    now: f64,
+    entity: Entity,
+    senses: &SensesComponent,
+) -> Option<(Entity, Vec3i)> {
+    senses
+        .nearest(|percept| is_enemy(world, creatures, now, entity, percept.entity))
+        .map(|percept| (percept.entity, percept.position))
+}
+
+/// Whether two positions are close enough to strike across
+pub fn in_reach(a: Vec3i, b: Vec3i) -> bool {
+    a.z == b.z && (a.x - b.x).abs() <= 1 && (a.y - b.y).abs() <= 1
+}
+
+/// Records combat events in the log and remembers who attacked whom
+pub fn build_combat_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<CombatLog>() {
+        resources.insert(CombatLog::default());
+    }
+    if !resources.contains::<Channel<CombatEvent>>() {
+        resources.insert(Channel::<CombatEvent>::default());
+    }
+    if !resources.contains::<Channel<NoiseEvent>>() {
+        resources.insert(Channel::<NoiseEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<CombatEvent>>()
+        .unwrap()
+        .bind_listener(256);
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("combat_system");
+
+        let (channel, noise, mut log) = <(
+            Read<Channel<CombatEvent>>,
+            Read<Channel<NoiseEvent>>,
+            Write<CombatLog>,
+        )>::fetch_mut(resources);
+
+        while let Some(event) = channel.read(listener_id) {
+            // Anyone in earshot learns there is danger where the blow landed
+            if let Some(position) = world.get_component::<PositionComponent>(event.defender) {
+                noise
+                    .write(NoiseEvent::new(
+                        Some(event.attacker),
+                        **position,
+                        NoiseKind::Combat,
+                        COMBAT_NOISE,
+                    ))
+                    .unwrap();
+            }
+
+            if let Some(mut combat) =
+                unsafe { world.get_component_mut_unchecked::<CombatComponent>(event.defender) }
+            {
+                combat.last_attacker = Some(event.attacker);
+                combat.last_hit = event.time;
+            }
+
+            log.push(event);
+        }
+    })
+}
@@ -0,0 +1,97 @@
+use rl_core::{
+    components::{CorpseComponent, DeadTag, Destroy, PositionComponent},
+    defs::thought::{MoodComponent, ThoughtEvent, ThoughtTrigger},
+    event::Channel,
+    legion::prelude::*,
+    math::Vec3i,
+    smallvec::SmallVec,
+    time::Time,
+};
+
+/// World seconds for a corpse to rot away completely
+pub const ROT_TIME: f64 = 3.0 * 24.0 * 3600.0;
+
+/// Rot at which a corpse starts giving off miasma
+pub const MIASMA_ROT: f32 = 0.25;
+
+/// Tiles the miasma of a corpse on the verge of rotting away carries
+pub const MIASMA_RANGE: i32 = 6;
+
+/// Tiles the miasma of a corpse carries, none until it starts to rot and further as it rots
+#[allow(clippy::cast_possible_truncation)]
+pub fn miasma_range(corpse: &CorpseComponent) -> i32 {
+    if corpse.rot < MIASMA_ROT {
+        return 0;
+    }
+
+    (MIASMA_RANGE as f32 * corpse.rot).ceil() as i32
+}
+
+fn smells(source: Vec3i, range: i32, position: Vec3i) -> bool {
+    source.z == position.z
+        && (source.x - position.x).abs() <= range
+        && (source.y - position.y).abs() <= range
+}
+
+/// Rots corpses, gives anyone near a rotting one a bad thought and removes those which have
+/// rotted away
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub fn build_corpse_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<Channel<ThoughtEvent>>() {
+        resources.insert(Channel::<ThoughtEvent>::default());
+    }
+
+    let corpse_query = <(Write<CorpseComponent>, Read<PositionComponent>)>::query()
+        .filter(!component::<Destroy>());
+    let smeller_query =
+        <(Read<MoodComponent>, Read<PositionComponent>)>::query().filter(!tag::<DeadTag>());
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("corpse_system");
+
+        let (time, thoughts) = <(Read<Time>, Read<Channel<ThoughtEvent>>)>::fetch(resources);
+        let rot = (time.world_delta.as_secs_f64() / ROT_TIME) as f32;
+
+        let mut miasma = SmallVec::<[(Vec3i, i32); 16]>::new();
+        let mut rotted = Vec::new();
+
+        for (entity, (mut corpse, position)) in corpse_query.iter_entities_mut(world) {
+            corpse.rot = (corpse.rot + rot).min(1.0);
+            if corpse.rot >= 1.0 {
+                rotted.push(entity);
+                continue;
+            }
+
+            let range = miasma_range(&corpse);
+            if range > 0 {
+                miasma.push((**position, range));
+            }
+        }
+
+        if !miasma.is_empty() {
+            for (entity, (_, position)) in smeller_query.iter_entities(world) {
+                let count = miasma
+                    .iter()
+                    .filter(|(source, range)| smells(*source, *range, **position))
+                    .count();
+
+                if count > 0 {
+                    thoughts
+                        .write(ThoughtEvent::new(
+                            entity,
+                            ThoughtTrigger::SmelledMiasma,
+                            count as i32,
+                        ))
+                        .unwrap();
+                }
+            }
+        }
+
+        for entity in rotted {
+            world.add_component(entity, Destroy::default()).unwrap();
+        }
+    })
+}
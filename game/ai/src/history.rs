@@ -0,0 +1,302 @@
+use crate::{
+    bt::{BehaviorHandle, BehaviorStatus, BehaviorStorage},
+    iaus::{
+        decisions::{DecisionHandle, DecisionStorage},
+        Decision,
+    },
+};
+use rl_core::failure;
+use std::{collections::VecDeque, fmt::Write};
+
+/// Scoring rounds kept per entity
+pub const HISTORY_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConsiderationRecord {
+    /// The value fed into the curve, for considerations which have one
+    pub input: Option<f64>,
+    pub output: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionRecord {
+    pub decision: DecisionHandle,
+    pub score: f64,
+    /// In the same order as `Decision::considerations`
+    pub considerations: Vec<ConsiderationRecord>,
+}
+
+/// One pass of the scoring system over an entity
+#[derive(Debug, Clone, Default)]
+pub struct ScoringRound {
+    pub time: f64,
+    /// Only the decisions which were rescored this round
+    pub decisions: Vec<DecisionRecord>,
+    pub chosen: Option<DecisionHandle>,
+    pub behavior: Option<BehaviorHandle>,
+    pub status: BehaviorStatus,
+}
+impl ScoringRound {
+    pub fn new(time: f64) -> Self {
+        Self {
+            time,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ConsiderationExport<'a> {
+    name: &'a str,
+    input: Option<f64>,
+    output: f64,
+}
+
+#[derive(serde::Serialize)]
+struct DecisionExport<'a> {
+    name: &'a str,
+    score: f64,
+    considerations: Vec<ConsiderationExport<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct RoundExport<'a> {
+    time: f64,
+    chosen: Option<&'a str>,
+    behavior: Option<&'a str>,
+    status: BehaviorStatus,
+    decisions: Vec<DecisionExport<'a>>,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// The most recent scoring rounds of an entity, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct DecisionHistoryComponent {
+    pub rounds: VecDeque<ScoringRound>,
+}
+impl DecisionHistoryComponent {
+    pub fn push(&mut self, round: ScoringRound) {
+        if self.rounds.len() >= HISTORY_CAPACITY {
+            self.rounds.pop_front();
+        }
+        self.rounds.push_back(round);
+    }
+
+    fn export<'a>(
+        &'a self,
+        decisions: &'a DecisionStorage,
+        behaviors: &'a BehaviorStorage,
+    ) -> Vec<RoundExport<'a>> {
+        self.rounds
+            .iter()
+            .map(|round| RoundExport {
+                time: round.time,
+                chosen: round.chosen.and_then(|handle| decisions.get_name(handle)),
+                behavior: round.behavior.and_then(|handle| behaviors.get_name(handle)),
+                status: round.status,
+                decisions: round
+                    .decisions
+                    .iter()
+                    .map(|record| {
+                        let decision = decisions.get(record.decision);
+                        DecisionExport {
+                            name: decision.map_or("", |decision| decision.name()),
+                            score: record.score,
+                            considerations: record
+                                .considerations
+                                .iter()
+                                .enumerate()
+                                .map(|(n, consideration)| ConsiderationExport {
+                                    name: decision
+                                        .and_then(|decision| decision.considerations().get(n))
+                                        .map_or("", |consideration| consideration.name()),
+                                    input: consideration.input,
+                                    output: consideration.output,
+                                })
+                                .collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn to_json(
+        &self,
+        decisions: &DecisionStorage,
+        behaviors: &BehaviorStorage,
+    ) -> Result<String, failure::Error> {
+        Ok(serde_json::to_string_pretty(
+            &self.export(decisions, behaviors),
+        )?)
+    }
+
+    /// One row per consideration, or per decision for decisions without any
+    pub fn to_csv(&self, decisions: &DecisionStorage, behaviors: &BehaviorStorage) -> String {
+        let mut csv =
+            String::from("time,chosen,behavior,status,decision,score,consideration,input,output\n");
+
+        for round in self.export(decisions, behaviors) {
+            let prefix = format!(
+                "{},{},{},{:?}",
+                round.time,
+                csv_field(round.chosen.unwrap_or("")),
+                csv_field(round.behavior.unwrap_or("")),
+                round.status.result()
+            );
+
+            for decision in &round.decisions {
+                if decision.considerations.is_empty() {
+                    writeln!(
+                        csv,
+                        "{},{},{},,,",
+                        prefix,
+                        csv_field(decision.name),
+                        decision.score
+                    )
+                    .unwrap();
+                }
+
+                for consideration in &decision.considerations {
+                    writeln!(
+                        csv,
+                        "{},{},{},{},{},{}",
+                        prefix,
+                        csv_field(decision.name),
+                        decision.score,
+                        csv_field(consideration.name),
+                        consideration
+                            .input
+                            .map_or_else(String::new, |input| input.to_string()),
+                        consideration.output
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bt::make,
+        iaus::{simple::ClosureConsideration, DecisionSet},
+        utility::UtilityState,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn history_capacity_test() {
+        let mut history = DecisionHistoryComponent::default();
+        for n in 0..HISTORY_CAPACITY + 10 {
+            history.push(ScoringRound::new(n as f64));
+        }
+
+        assert_eq!(history.rounds.len(), HISTORY_CAPACITY);
+        assert_eq!(history.rounds.front().unwrap().time, 10.0);
+    }
+
+    fn sample() -> (DecisionHistoryComponent, DecisionStorage, BehaviorStorage) {
+        let mut decisions = DecisionStorage::default();
+        let eat = decisions.insert(
+            "Eat",
+            DecisionSet::new(
+                "Eat",
+                vec![Arc::new(ClosureConsideration::new(
+                    "Hunger",
+                    Box::new(|_: &UtilityState| 0.5),
+                ))],
+            ),
+        );
+        let idle = decisions.insert("Idle", DecisionSet::new("Idle", Vec::new()));
+
+        let mut behaviors = BehaviorStorage::default();
+        let try_eat = behaviors.insert(
+            "try_eat",
+            make::closure(None, |_, _| BehaviorStatus::success()),
+        );
+
+        let mut history = DecisionHistoryComponent::default();
+        history.push(ScoringRound {
+            time: 1.5,
+            decisions: vec![
+                DecisionRecord {
+                    decision: eat,
+                    score: 0.25,
+                    considerations: vec![ConsiderationRecord {
+                        input: Some(3.0),
+                        output: 0.5,
+                    }],
+                },
+                DecisionRecord {
+                    decision: idle,
+                    score: 1.0,
+                    considerations: Vec::new(),
+                },
+            ],
+            chosen: Some(eat),
+            behavior: Some(try_eat),
+            status: BehaviorStatus::success(),
+        });
+
+        (history, decisions, behaviors)
+    }
+
+    #[test]
+    fn to_csv_test() {
+        let (history, decisions, behaviors) = sample();
+
+        assert_eq!(
+            history.to_csv(&decisions, &behaviors),
+            "time,chosen,behavior,status,decision,score,consideration,input,output\n\
+             1.5,Eat,try_eat,Success,Eat,0.25,Hunger,3,0.5\n\
+             1.5,Eat,try_eat,Success,Idle,1,,,\n"
+        );
+    }
+
+    #[test]
+    fn to_json_test() -> Result<(), failure::Error> {
+        let (history, decisions, behaviors) = sample();
+
+        let json =
+            serde_json::from_str::<serde_json::Value>(&history.to_json(&decisions, &behaviors)?)?;
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "time": 1.5,
+                "chosen": "Eat",
+                "behavior": "try_eat",
+                "status": { "result": "Success", "cancellable": true, "bail": false },
+                "decisions": [
+                    {
+                        "name": "Eat",
+                        "score": 0.25,
+                        "considerations": [{ "name": "Hunger", "input": 3.0, "output": 0.5 }]
+                    },
+                    { "name": "Idle", "score": 1.0, "considerations": [] }
+                ]
+            }])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_field_test() {
+        assert_eq!(csv_field("eat"), "eat");
+        assert_eq!(csv_field("eat, drink"), "\"eat, drink\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -1,6 +1,7 @@
+use rl_core::defs::decision::ConsiderationCurve;
 use std::{
     fmt::Debug,
-    ops::{Bound, RangeBounds, RangeInclusive},
+    ops::{Bound, Range, RangeBounds, RangeInclusive},
 };
 
 pub trait Curve<R>: Debug + Send + Sync
@@ -285,3 +286,54 @@ where
         t / (4.0 * std::f64::consts::E)
     }
 }
+
+pub fn from_definition(def: &ConsiderationCurve) -> Box<dyn Curve<Range<f64>>> {
+    match def.clone() {
+        ConsiderationCurve::Linear {
+            range,
+            slope,
+            intercept,
+        } => Box::new(Linear {
+            range,
+            slope,
+            intercept,
+        }),
+        ConsiderationCurve::Exponential {
+            range,
+            power,
+            intercept,
+        } => Box::new(Exponential {
+            range,
+            power,
+            intercept,
+        }),
+        ConsiderationCurve::Sine {
+            range,
+            magnitude,
+            intercept,
+        } => Box::new(Sine {
+            range,
+            magnitude,
+            intercept,
+        }),
+        ConsiderationCurve::Cosine {
+            range,
+            magnitude,
+            intercept,
+        } => Box::new(Cosine {
+            range,
+            magnitude,
+            intercept,
+        }),
+        ConsiderationCurve::Logistic {
+            range,
+            steepness,
+            midpoint,
+        } => Box::new(Logistic {
+            range,
+            steepness,
+            midpoint,
+        }),
+        ConsiderationCurve::Logit { range, base } => Box::new(Logit { range, base }),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::utility::UtilityState;
+use crate::{history::ConsiderationRecord, utility::UtilityState};
 use std::{fmt::Debug, hash::Hash, sync::Arc};
 
 pub mod curves;
@@ -11,6 +11,14 @@ pub use simple::*;
 pub trait Consideration: Send + Sync {
     fn name(&self) -> &str;
     fn score(&self, state: &UtilityState) -> f64;
+
+    /// Scores the consideration, keeping the input if it has one
+    fn evaluate(&self, state: &UtilityState) -> ConsiderationRecord {
+        ConsiderationRecord {
+            input: None,
+            output: self.score(state),
+        }
+    }
 }
 
 impl<F> Consideration for F
@@ -35,28 +43,43 @@ pub trait Decision: Send + Sync {
         1.0
     }
 
-    #[allow(clippy::cast_precision_loss)]
     fn score(&self, state: &UtilityState) -> f64 {
-        let scores = self
+        combine(
+            self.base(),
+            self.considerations().len(),
+            self.considerations().iter().map(|consider| consider.score(state)),
+        )
+    }
+
+    /// Scores the decision, keeping each consideration's result
+    fn evaluate(&self, state: &UtilityState) -> (f64, Vec<ConsiderationRecord>) {
+        let records = self
             .considerations()
             .iter()
-            .filter_map(|consider| {
-                let score = consider.score(state);
-                if score > 0.0 {
-                    Some(score.min(1.0).max(0.0))
-                } else {
-                    None
-                }
-            })
+            .map(|consider| consider.evaluate(state))
             .collect::<Vec<_>>();
 
-        let base = self.base();
-        let modifier = 1.0 - (1.0 / self.considerations().len() as f64);
-        scores.iter().fold(base, |mut acc, score| {
-            acc *= *score;
+        (
+            combine(
+                self.base(),
+                records.len(),
+                records.iter().map(|record| record.output),
+            ),
+            records,
+        )
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn combine(base: f64, count: usize, scores: impl Iterator<Item = f64>) -> f64 {
+    let modifier = 1.0 - (1.0 / count as f64);
+    scores
+        .filter(|score| *score > 0.0)
+        .map(|score| score.min(1.0).max(0.0))
+        .fold(base, |mut acc, score| {
+            acc *= score;
             acc + ((1.0 - acc) * modifier * acc)
         })
-    }
 }
 
 #[derive(Default, Clone)]
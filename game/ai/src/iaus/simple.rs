@@ -1,9 +1,28 @@
 use crate::{
-    iaus::{Consideration, Curve},
+    combat::{self, CombatComponent},
+    history::ConsiderationRecord,
+    iaus::{curves, Consideration, Curve},
+    social::{RelationshipGraph, SocialComponent},
     utility::UtilityState,
 };
 use derivative::Derivative;
-use std::ops::RangeBounds;
+use rl_core::{
+    components::{CreatureTag, PawnTag, PositionComponent},
+    defs::{
+        body::{BodyComponent, BodyDefinition},
+        creature::{self, CreatureComponent, CreatureDefinition},
+        decision::{ConsiderationDefinition, ConsiderationInput},
+        item::{ItemComponent, ItemDefinition},
+        thought::MoodComponent,
+        DefinitionComponent, DefinitionStorage,
+    },
+    inventory,
+    legion::prelude::*,
+    map::spatial::SpatialMap,
+    schedule::{self, ScheduleBlock},
+    Distance,
+};
+use std::ops::{Range, RangeBounds};
 
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
@@ -71,3 +90,247 @@ impl Consideration for ClosureConsideration {
         }
     }
 }
+
+//////
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct DefinitionConsideration {
+    pub name: String,
+    pub input: ConsiderationInput,
+    pub curve: Option<Box<dyn Curve<Range<f64>>>>,
+    pub weight: f64,
+}
+impl DefinitionConsideration {
+    pub fn new(def: &ConsiderationDefinition) -> Self {
+        Self {
+            name: def.name.clone(),
+            input: def.input,
+            curve: def.curve.as_ref().map(curves::from_definition),
+            weight: def.weight,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn input(&self, state: &UtilityState) -> f64 {
+        match self.input {
+            ConsiderationInput::Constant(value) => value,
+            ConsiderationInput::Need(kind) => f64::from(state.needs.get(kind).value),
+            ConsiderationInput::NeedDeficit(kind) => 1.0 - f64::from(state.needs.get(kind).value),
+            ConsiderationInput::TimeOfDay => state.time.hour_of_day(),
+            ConsiderationInput::DistanceToItem(kind) => {
+                let (spatial_map, items) = <(
+                    Read<SpatialMap>,
+                    Read<DefinitionStorage<ItemDefinition>>,
+                )>::fetch(state.state.resources);
+
+                let position = match state
+                    .state
+                    .world
+                    .get_component::<PositionComponent>(state.entity)
+                {
+                    Some(position) => *position,
+                    None => return std::f64::INFINITY,
+                };
+
+                spatial_map
+                    .nearest_neighbor_iter(&position)
+                    .find(|entry| {
+                        state
+                            .state
+                            .world
+                            .get_component::<ItemComponent>(entry.entity)
+                            .map_or(false, |item| item.fetch(&items).kind == kind)
+                    })
+                    .map_or(std::f64::INFINITY, |entry| {
+                        f64::from(entry.position().distance(&*position))
+                    })
+            }
+            ConsiderationInput::ItemCount(kind) => {
+                let items = state
+                    .state
+                    .resources
+                    .get::<DefinitionStorage<ItemDefinition>>()
+                    .unwrap();
+
+                let mut count = 0_u32;
+                inventory::for_all_items_recursive(
+                    state.entity,
+                    state.state.world,
+                    |_, (_, item)| {
+                        if item.fetch(&items).kind == kind {
+                            count += 1;
+                        }
+                    },
+                );
+                f64::from(count)
+            }
+            ConsiderationInput::PerceivedCreatures => {
+                let world = state.state.world;
+                state
+                    .senses
+                    .perceived
+                    .iter()
+                    .filter(|percept| {
+                        world.get_tag::<CreatureTag>(percept.entity).is_some()
+                            || world.get_tag::<PawnTag>(percept.entity).is_some()
+                    })
+                    .count() as f64
+            }
+            ConsiderationInput::HeardNoises => state.senses.heard.len() as f64,
+            ConsiderationInput::PerceivedPawns | ConsiderationInput::PerceivedRivals => {
+                let world = state.state.world;
+                let graph = state.state.resources.get::<RelationshipGraph>();
+                let rivals = self.input == ConsiderationInput::PerceivedRivals;
+
+                state
+                    .senses
+                    .perceived
+                    .iter()
+                    .filter(|percept| {
+                        world.get_tag::<PawnTag>(percept.entity).is_some()
+                            && graph
+                                .as_ref()
+                                .map_or(false, |graph| graph.is_rival(state.entity, percept.entity))
+                                == rivals
+                    })
+                    .count() as f64
+            }
+            ConsiderationInput::TimeSinceSocial => state
+                .state
+                .world
+                .get_component::<SocialComponent>(state.entity)
+                .map_or(0.0, |social| {
+                    (state.time.world_time - social.last_conversation) / 3600.0
+                }),
+            ConsiderationInput::Mood => state
+                .state
+                .world
+                .get_component::<MoodComponent>(state.entity)
+                .map_or(0.0, |mood| f64::from(mood.mood)),
+            ConsiderationInput::Schedule(block) => {
+                match schedule::current_block(
+                    state.state.world,
+                    state.state.resources,
+                    state.entity,
+                ) {
+                    current if current == block => 1.0,
+                    ScheduleBlock::Anything => 0.5,
+                    _ => 0.0,
+                }
+            }
+            ConsiderationInput::PerceivedThreats => {
+                let world = state.state.world;
+                let creatures = state
+                    .state
+                    .resources
+                    .get::<DefinitionStorage<CreatureDefinition>>()
+                    .unwrap();
+
+                state
+                    .senses
+                    .perceived
+                    .iter()
+                    .filter(|percept| {
+                        combat::is_threat(
+                            world,
+                            &creatures,
+                            state.time.world_time,
+                            state.entity,
+                            percept.entity,
+                        )
+                    })
+                    .count() as f64
+            }
+            ConsiderationInput::DistanceToHerd => {
+                let world = state.state.world;
+                let creatures = state
+                    .state
+                    .resources
+                    .get::<DefinitionStorage<CreatureDefinition>>()
+                    .unwrap();
+
+                let range = match world
+                    .get_component::<CreatureComponent>(state.entity)
+                    .and_then(|creature| creature.fetch(&creatures).behavior.herd_range)
+                {
+                    Some(range) => range.max(1),
+                    None => return 0.0,
+                };
+                let position = match world.get_component::<PositionComponent>(state.entity) {
+                    Some(position) => **position,
+                    None => return 0.0,
+                };
+
+                creature::nearest_herd_mate(world, state.entity).map_or(0.0, |(_, mate)| {
+                    let d = mate - position;
+                    f64::from(d.x * d.x + d.y * d.y + d.z * d.z).sqrt() / f64::from(range)
+                })
+            }
+            ConsiderationInput::PerceivedEnemies => {
+                let world = state.state.world;
+                let creatures = state
+                    .state
+                    .resources
+                    .get::<DefinitionStorage<CreatureDefinition>>()
+                    .unwrap();
+
+                state
+                    .senses
+                    .perceived
+                    .iter()
+                    .filter(|percept| {
+                        combat::is_enemy(
+                            world,
+                            &creatures,
+                            state.time.world_time,
+                            state.entity,
+                            percept.entity,
+                        )
+                    })
+                    .count() as f64
+            }
+            ConsiderationInput::RecentlyHit => state
+                .state
+                .world
+                .get_component::<CombatComponent>(state.entity)
+                .map_or(0.0, |combat| combat.recently_hit(state.time.world_time)),
+            ConsiderationInput::Injury => {
+                let bodies = state
+                    .state
+                    .resources
+                    .get::<DefinitionStorage<BodyDefinition>>()
+                    .unwrap();
+
+                state
+                    .state
+                    .world
+                    .get_component::<BodyComponent>(state.entity)
+                    .map_or(0.0, |body| {
+                        1.0 - f64::from(body.health(body.fetch(&bodies)))
+                    })
+            }
+        }
+    }
+}
+impl Consideration for DefinitionConsideration {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn score(&self, state: &UtilityState) -> f64 {
+        self.evaluate(state).output
+    }
+
+    fn evaluate(&self, state: &UtilityState) -> ConsiderationRecord {
+        let input = self.input(state);
+
+        ConsiderationRecord {
+            input: Some(input),
+            output: self
+                .curve
+                .as_ref()
+                .map_or(input, |curve| curve.transform(input))
+                * self.weight,
+        }
+    }
+}
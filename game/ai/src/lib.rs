@@ -21,23 +21,26 @@ use rl_core::{
 pub mod action;
 pub mod body;
 pub mod bt;
+pub mod combat;
+pub mod corpse;
+pub mod history;
 pub mod iaus;
+pub mod memory;
+pub mod mood;
 pub mod movement;
 pub mod needs;
+pub mod orders;
 pub mod pathfinding;
+pub mod perception;
+pub mod social;
 pub mod task;
 pub mod utility;
 
+pub use memory::MemoryComponent;
+pub use perception::SensesComponent;
+pub use social::SocialComponent;
 pub use task::*;
 
-#[derive(Debug, Clone, Copy, Hash)]
-pub struct SensesComponent {}
-impl Default for SensesComponent {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash, serde::Serialize, serde::Deserialize,
 )]
@@ -59,9 +62,20 @@ pub fn bundle(
     resources: &mut Resources,
     builder: &mut DispatcherBuilder,
 ) -> Result<(), failure::Error> {
+    builder.add_thread_local_fn(AIStage::Setup, task::build_reclaim_tasks_system);
+    builder.add_thread_local_fn(AIStage::Setup, orders::build_workshop_orders_system);
     builder.add_system(AIStage::Setup, task::build_update_task_cache_system);
+    builder.add_system(AIStage::Setup, perception::build_perception_system);
+    builder.add_thread_local_fn(AIStage::Setup, memory::build_memory_system);
+    builder.add_thread_local_fn(AIStage::Setup, social::build_social_system);
+    builder.add_thread_local_fn(AIStage::Setup, mood::build_mood_system);
+    builder.add_thread_local_fn(AIStage::Setup, combat::build_combat_system);
+    builder.add_thread_local_fn(AIStage::Setup, body::build_health_system);
+    builder.add_thread_local_fn(AIStage::Setup, corpse::build_corpse_system);
     builder.add_thread_local_fn(AIStage::Planning, utility::build_scoring_system);
+    builder.add_thread_local_fn(AIStage::ActionPlanning, action::build_planning_system);
     builder.add_thread_local_fn(AIStage::Execution, bt::system);
+    builder.add_thread_local_fn(Stage::Begin, bt::definition::build_reload_system);
     builder.add_system(AIStage::Execution, movement::build_process_movement_system);
 
     builder.add_system(Stage::End, task::build_cleanup_virtual_tasks);
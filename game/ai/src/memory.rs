@@ -0,0 +1,267 @@
+use crate::perception::{line_of_sight, SensesComponent};
+use rl_core::{
+    components::{ItemContainerChildComponent, PositionComponent},
+    data::NoiseKind,
+    defs::{
+        creature::{CreatureComponent, CreatureDefinition},
+        item::{ItemComponent, ItemDefinition},
+        needs::{get_nutrition_value, NeedKind},
+        DefinitionComponent, DefinitionStorage,
+    },
+    legion::prelude::*,
+    map::Map,
+    math::Vec3i,
+    smallvec::SmallVec,
+    time::Time,
+    GameStateRef,
+};
+
+/// Maximum number of entries a single memory holds, the stalest entry is replaced when full
+pub const MEMORY_CAPACITY: usize = 64;
+
+const HOUR: f64 = 3600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryKind {
+    Food,
+    Water,
+    Tool,
+    Hostile,
+    Danger,
+}
+impl MemoryKind {
+    /// World seconds an entry lasts without being seen again
+    pub fn lifetime(self) -> f64 {
+        match self {
+            Self::Food | Self::Water | Self::Tool => 48.0 * HOUR,
+            Self::Hostile => 4.0 * HOUR,
+            Self::Danger => HOUR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryEntry {
+    pub kind: MemoryKind,
+    /// Memories of a place, such as danger, have no entity
+    pub entity: Option<Entity>,
+    pub position: Vec3i,
+    pub last_seen: f64,
+}
+impl MemoryEntry {
+    /// 1.0 when just seen, falling to 0.0 when the entry is forgotten
+    pub fn strength(&self, now: f64) -> f64 {
+        (1.0 - (now - self.last_seen) / self.kind.lifetime()).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryComponent {
+    entries: Vec<MemoryEntry>,
+}
+impl MemoryComponent {
+    pub fn remember(
+        &mut self,
+        kind: MemoryKind,
+        entity: Option<Entity>,
+        position: Vec3i,
+        now: f64,
+    ) {
+        let existing = self.entries.iter_mut().find(|entry| {
+            entry.kind == kind
+                && match entity {
+                    Some(_) => entry.entity == entity,
+                    None => entry.entity.is_none() && entry.position == position,
+                }
+        });
+
+        if let Some(entry) = existing {
+            entry.position = position;
+            entry.last_seen = now;
+            return;
+        }
+
+        if self.entries.len() >= MEMORY_CAPACITY {
+            if let Some(stalest) = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.last_seen.partial_cmp(&b.last_seen).unwrap())
+                .map(|(i, _)| i)
+            {
+                self.entries.swap_remove(stalest);
+            }
+        }
+
+        self.entries.push(MemoryEntry {
+            kind,
+            entity,
+            position,
+            last_seen: now,
+        });
+    }
+
+    pub fn forget(&mut self, entity: Entity) {
+        self.entries.retain(|entry| entry.entity != Some(entity));
+    }
+
+    pub fn decay(&mut self, now: f64) {
+        self.entries.retain(|entry| entry.strength(now) > 0.0);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MemoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn has(&self, kind: MemoryKind) -> bool {
+        self.entries.iter().any(|entry| entry.kind == kind)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries of the kind, nearest to `from` first
+    pub fn nearest(&self, kind: MemoryKind, from: Vec3i) -> Vec<&MemoryEntry> {
+        let mut found = self
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .collect::<Vec<_>>();
+        found.sort_by_key(|entry| distance_2(from, entry.position));
+        found
+    }
+}
+
+fn distance_2(a: Vec3i, b: Vec3i) -> i32 {
+    let d = a - b;
+    d.x * d.x + d.y * d.y + d.z * d.z
+}
+
+/// What an entity is worth remembering as, if anything
+pub fn classify(state: GameStateRef, entity: Entity) -> SmallVec<[MemoryKind; 2]> {
+    let mut kinds = SmallVec::new();
+
+    if let Some(creature) = state.world.get_component::<CreatureComponent>(entity) {
+        let creatures = state
+            .resources
+            .get::<DefinitionStorage<CreatureDefinition>>()
+            .unwrap();
+        if creature.fetch(&creatures).hostile {
+            kinds.push(MemoryKind::Hostile);
+        }
+        return kinds;
+    }
+
+    if state
+        .world
+        .has_component::<ItemContainerChildComponent>(entity)
+    {
+        return kinds;
+    }
+
+    if get_nutrition_value(state, entity, NeedKind::Calories).map_or(false, |n| n.start > 0) {
+        kinds.push(MemoryKind::Food);
+    }
+    if get_nutrition_value(state, entity, NeedKind::Hydration).map_or(false, |n| n.start > 0) {
+        kinds.push(MemoryKind::Water);
+    }
+
+    if let Some(item) = state.world.get_component::<ItemComponent>(entity) {
+        let items = state
+            .resources
+            .get::<DefinitionStorage<ItemDefinition>>()
+            .unwrap();
+        if !item.fetch(&items).abilities.is_empty() {
+            kinds.push(MemoryKind::Tool);
+        }
+    }
+
+    kinds
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn in_view(map: &Map, senses: &SensesComponent, from: Vec3i, to: Vec3i) -> bool {
+    let range = senses.sight_range as i32;
+    distance_2(from, to) <= range * range && line_of_sight(map, from, to)
+}
+
+enum Observation {
+    Remember(MemoryKind, Option<Entity>, Vec3i),
+    Forget(Entity),
+}
+
+/// Fills memories from what was perceived this tick. Remembered entities which are gone, or are no
+/// longer where they were last seen while that spot is in view, are forgotten.
+pub fn build_memory_system(
+    _: &mut World,
+    _: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    let query = <(
+        Read<SensesComponent>,
+        Read<MemoryComponent>,
+        Read<PositionComponent>,
+    )>::query();
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("memory_system");
+
+        let now = resources.get::<Time>().unwrap().world_time;
+
+        let updates = {
+            let map = resources.get::<Map>().unwrap();
+            let state = GameStateRef { world, resources };
+
+            query
+                .iter_entities(world)
+                .map(|(entity, (senses, memory, position))| {
+                    let mut observations = Vec::new();
+
+                    for percept in &senses.perceived {
+                        observations.extend(classify(state, percept.entity).into_iter().map(
+                            |kind| {
+                                Observation::Remember(kind, Some(percept.entity), percept.position)
+                            },
+                        ));
+                    }
+
+                    for noise in &senses.heard {
+                        if noise.kind == NoiseKind::Combat {
+                            observations.push(Observation::Remember(
+                                MemoryKind::Danger,
+                                None,
+                                noise.position,
+                            ));
+                        }
+                    }
+
+                    for entry in memory.iter() {
+                        if let Some(target) = entry.entity {
+                            if !world.is_alive(target)
+                                || (!senses.can_see(target)
+                                    && in_view(&map, &senses, **position, entry.position))
+                            {
+                                observations.push(Observation::Forget(target));
+                            }
+                        }
+                    }
+
+                    (entity, observations)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (entity, observations) in updates {
+            let mut memory = world.get_component_mut::<MemoryComponent>(entity).unwrap();
+            for observation in observations {
+                match observation {
+                    Observation::Remember(kind, target, position) => {
+                        memory.remember(kind, target, position, now);
+                    }
+                    Observation::Forget(target) => memory.forget(target),
+                }
+            }
+            memory.decay(now);
+        }
+    })
+}
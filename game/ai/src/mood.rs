@@ -0,0 +1,146 @@
+use crate::{
+    bt::{BehaviorRoot, BehaviorStorage, BehaviorTreeComponent},
+    social::{SocialEvent, SocialEventKind},
+    SensesComponent,
+};
+use rl_core::{
+    components::{BlackboardComponent, CorpseComponent, DeadTag, MovementComponent},
+    defs::{
+        thought::{MoodComponent, ThoughtDefinition, ThoughtEvent, ThoughtTrigger},
+        DefinitionStorage,
+    },
+    event::Channel,
+    legion::prelude::*,
+    time::Time,
+};
+
+/// Pawns at or below this mood break down
+pub const BREAKDOWN_MOOD: i32 = -60;
+
+fn thought_for_social(event: &SocialEvent) -> ThoughtEvent {
+    let trigger = match event.kind {
+        SocialEventKind::Insult => ThoughtTrigger::Insulted,
+        SocialEventKind::Compliment => ThoughtTrigger::Complimented,
+        _ => ThoughtTrigger::Conversation,
+    };
+
+    ThoughtEvent::new(event.to, trigger, event.kind.opinion())
+}
+
+/// Turns thought events into thoughts, recalculates every mood and forces a breakdown on anyone
+/// who is miserable enough.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub fn build_mood_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<Channel<ThoughtEvent>>() {
+        resources.insert(Channel::<ThoughtEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<ThoughtEvent>>()
+        .unwrap()
+        .bind_listener(256);
+    if !resources.contains::<Channel<SocialEvent>>() {
+        resources.insert(Channel::<SocialEvent>::default());
+    }
+    let social_listener_id = resources
+        .get_mut::<Channel<SocialEvent>>()
+        .unwrap()
+        .bind_listener(256);
+
+    let query = <(Read<MoodComponent>, Read<SensesComponent>)>::query();
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("mood_system");
+
+        let now = resources.get::<Time>().unwrap().world_time;
+
+        let mut events = Vec::new();
+        {
+            let (thought_channel, social_channel) =
+                <(Read<Channel<ThoughtEvent>>, Read<Channel<SocialEvent>>)>::fetch(resources);
+
+            while let Some(event) = thought_channel.read(listener_id) {
+                events.push(event);
+            }
+            while let Some(event) = social_channel.read(social_listener_id) {
+                if event.kind != SocialEventKind::SharedWork {
+                    events.push(thought_for_social(&event));
+                }
+            }
+        }
+
+        for (entity, (_, senses)) in query.iter_entities(world) {
+            let corpses = senses
+                .perceived
+                .iter()
+                .filter(|percept| {
+                    world.get_tag::<DeadTag>(percept.entity).is_some()
+                        || world.has_component::<CorpseComponent>(percept.entity)
+                })
+                .count();
+
+            if corpses > 0 {
+                events.push(ThoughtEvent::new(
+                    entity,
+                    ThoughtTrigger::SawCorpse,
+                    corpses as i32,
+                ));
+            }
+        }
+
+        let defs = resources
+            .get::<DefinitionStorage<ThoughtDefinition>>()
+            .unwrap();
+
+        for event in &events {
+            if let Some(mut mood) = world.get_component_mut::<MoodComponent>(event.entity) {
+                defs.iter()
+                    .filter(|def| def.matches(event))
+                    .for_each(|def| mood.add(def, now));
+            }
+        }
+
+        let entities = query
+            .iter_entities(world)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        let breakdown = resources
+            .get::<BehaviorStorage>()
+            .unwrap()
+            .get_handle("breakdown");
+
+        for entity in entities {
+            let mood = {
+                let mut mood = world.get_component_mut::<MoodComponent>(entity).unwrap();
+                mood.update(&defs, now);
+                mood.mood
+            };
+
+            if mood > BREAKDOWN_MOOD {
+                continue;
+            }
+
+            if let (Some(handle), Some(mut tree)) = (
+                breakdown,
+                world.get_component_mut::<BehaviorTreeComponent>(entity),
+            ) {
+                if tree.root.is_forced() {
+                    continue;
+                }
+                tree.root = BehaviorRoot::Forced(handle);
+            } else {
+                continue;
+            }
+
+            if let Some(mut blackboard) = world.get_component_mut::<BlackboardComponent>(entity) {
+                blackboard.clear();
+            }
+            if let Some(mut movement) = world.get_component_mut::<MovementComponent>(entity) {
+                movement.current = None;
+            }
+        }
+    })
+}
@@ -1,6 +1,10 @@
 use rl_core::{
     components::{MovementComponent, MovementError, MovementResult, PositionComponent},
     debug::DebugLines,
+    defs::{
+        body::{BodyComponent, BodyDefinition, PartFlag},
+        DefinitionComponent, DefinitionStorage,
+    },
     event::Channel,
     legion::prelude::*,
     map::{
@@ -23,17 +27,46 @@ pub fn build_process_movement_system(
         .read_resource::<Channel<MovementResult>>()
         .read_resource::<SpatialMap>()
         .read_resource::<StaticSpatialMap>()
-        .with_query(<(Write<PositionComponent>, Write<MovementComponent>)>::query())
+        .read_resource::<DefinitionStorage<BodyDefinition>>()
+        .with_query(<(
+            Write<PositionComponent>,
+            Write<MovementComponent>,
+            TryRead<BodyComponent>,
+        )>::query())
         .build(
             move |command_buffer,
                   world,
-                  (time, _debug_lines, map, result_channel, spatial_map, static_spatial_map),
+                  (
+                time,
+                _debug_lines,
+                map,
+                result_channel,
+                spatial_map,
+                static_spatial_map,
+                bodies,
+            ),
                   query| {
-                for (entity, (mut position, mut movecomp)) in query.iter_entities_mut(world) {
+                for (entity, (mut position, mut movecomp, body)) in query.iter_entities_mut(world) {
                     game_metrics::scope!("process_movement_system");
                     if let Some(current) = movecomp.current {
+                        // Losing legs slows us down, losing all of them stops us
+                        let speed = body.map_or(1.0, |body| {
+                            body.functional_fraction(body.fetch(&bodies), PartFlag::STANCE)
+                        });
+                        if speed <= 0.0 {
+                            movecomp.current = None;
+
+                            let result = MovementResult {
+                                request: current,
+                                result: Err(MovementError::Immobile),
+                            };
+                            command_buffer.add_component(entity, result);
+                            result_channel.write(result).unwrap();
+                            continue;
+                        }
+
                         // TODO: for now, just allow movement of 1 tile per "world 1 seconds"
-                        movecomp.acc += time.world_delta.as_secs_f64();
+                        movecomp.acc += time.world_delta.as_secs_f64() * f64::from(speed);
 
                         if movecomp.acc >= 1.0 {
                             movecomp.acc = 0.0;
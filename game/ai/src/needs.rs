@@ -1,7 +1,8 @@
 use rl_core::{
-    components::NeedsComponent,
+    components::{BlackboardComponent, NeedsComponent, PositionComponent, SleepingComponent},
+    defs::needs::NeedKind,
     dispatcher::{DispatcherBuilder, Stage},
-    failure,
+    failure, fnv,
     legion::prelude::*,
     smallvec::SmallVec,
     time::Time,
@@ -13,10 +14,16 @@ pub fn bundle(
     builder: &mut DispatcherBuilder,
 ) -> Result<(), failure::Error> {
     builder.add_system(Stage::Logic, build_apply_decay_system);
+    builder.add_system(Stage::Logic, build_sleep_system);
 
     Ok(())
 }
 
+/// Sleep restored per world second on the floor
+pub const SLEEP_RECOVERY: f64 = 2.0;
+/// Multiplier on `SLEEP_RECOVERY` when sleeping in a bed
+pub const BED_RECOVERY_MULTIPLIER: f64 = 2.0;
+
 pub fn build_apply_decay_system(_: &mut World, _: &mut Resources) -> Box<dyn Schedulable> {
     SystemBuilder::<()>::new("apply_decay_system")
         .read_resource::<Time>()
@@ -52,3 +59,52 @@ pub fn build_apply_decay_system(_: &mut World, _: &mut Resources) -> Box<dyn Sch
             }
         })
 }
+
+/// Restores sleep for sleeping entities, waking anything which was moved from where it lay down
+/// or whose sleeping behavior failed or was replaced
+#[allow(clippy::cast_possible_truncation)]
+pub fn build_sleep_system(_: &mut World, _: &mut Resources) -> Box<dyn Schedulable> {
+    SystemBuilder::<()>::new("sleep_system")
+        .read_resource::<Time>()
+        .write_component::<BlackboardComponent>()
+        .with_query(<(
+            Write<NeedsComponent>,
+            Write<SleepingComponent>,
+            Read<PositionComponent>,
+        )>::query())
+        .build(move |command_buffer, world, time, query| {
+            game_metrics::scope!("sleep_system");
+
+            let mut woken = SmallVec::<[Entity; 8]>::new();
+
+            for (entity, (mut needs, mut sleeping, position)) in query.iter_entities_mut(world) {
+                if **position != sleeping.position
+                    || time.frame > sleeping.renewed + SleepingComponent::TIMEOUT
+                {
+                    command_buffer.remove_component::<SleepingComponent>(entity);
+                    woken.push(entity);
+                    continue;
+                }
+
+                let rate = if sleeping.bed.is_some() {
+                    SLEEP_RECOVERY * BED_RECOVERY_MULTIPLIER
+                } else {
+                    SLEEP_RECOVERY
+                };
+
+                sleeping.acc += time.world_delta.as_secs_f64() * rate;
+                let restored = sleeping.acc.floor();
+                sleeping.acc -= restored;
+
+                needs.add(NeedKind::Sleep, restored as i32);
+            }
+
+            for entity in woken {
+                if let Some(mut blackboard) =
+                    unsafe { world.get_component_mut_unchecked::<BlackboardComponent>(entity) }
+                {
+                    blackboard.remove(fnv!("Bed"));
+                }
+            }
+        })
+}
@@ -0,0 +1,265 @@
+use crate::task::{
+    HasTasksComponent, Task, TaskHandle, TaskReservationChange, TaskReservationEvent,
+};
+use rl_core::{
+    components::{Destroy, PositionComponent},
+    condition::{self, CheckConditionParameters},
+    defs::{
+        item::{ItemComponent, ItemDefinitionId},
+        labor::LaborDefinition,
+        reaction::{ProductKind, ReactionDefinition, ReactionDefinitionId, Reagent},
+        workshop::WorkshopComponent,
+        Definition, DefinitionComponent, DefinitionStorage,
+    },
+    event::Channel,
+    fxhash::{FxHashMap, FxHashSet},
+    legion::prelude::*,
+    GameStateRef,
+};
+
+/// Priority of the tasks orders create
+const ORDER_TASK_PRIORITY: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderMode {
+    /// Run the reaction this many times
+    Count(u32),
+    Forever,
+    /// Run the reaction while there are fewer than this many of its product
+    KeepStock(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderBlocked {
+    MissingReagent(Reagent),
+    NoLabor,
+    Suspended,
+    StockReached(u32),
+}
+impl std::fmt::Display for OrderBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingReagent(reagent) => write!(f, "Missing reagent: {:?}", reagent.conditions),
+            Self::NoLabor => write!(f, "No labor for this reaction"),
+            Self::Suspended => write!(f, "Suspended"),
+            Self::StockReached(count) => write!(f, "Stock reached ({})", count),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProductionOrder {
+    pub reaction: ReactionDefinitionId,
+    pub mode: OrderMode,
+    pub completed: u32,
+    pub suspended: bool,
+    /// Why the order could not queue its next task
+    pub blocked: Option<OrderBlocked>,
+    task: Option<TaskHandle>,
+}
+impl ProductionOrder {
+    pub fn new(reaction: ReactionDefinitionId, mode: OrderMode) -> Self {
+        Self {
+            reaction,
+            mode,
+            completed: 0,
+            suspended: false,
+            blocked: None,
+            task: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.mode {
+            OrderMode::Count(count) => self.completed >= count,
+            _ => false,
+        }
+    }
+
+    /// True while a task for this order is queued or being worked on
+    pub fn is_queued(&self) -> bool {
+        self.task.is_some()
+    }
+}
+
+/// Production orders for a workshop, each keeps at most one task in the workshop's queue
+#[derive(Debug, Clone, Default)]
+pub struct OrderQueueComponent {
+    pub orders: Vec<ProductionOrder>,
+}
+impl OrderQueueComponent {
+    pub fn push(&mut self, order: ProductionOrder) {
+        self.orders.push(order);
+    }
+
+    /// Removes an order, along with its task if nobody has taken it yet
+    pub fn remove(&mut self, index: usize, tasks: &HasTasksComponent) -> ProductionOrder {
+        let order = self.orders.remove(index);
+        if let Some(handle) = order.task {
+            let mut queue = tasks.storage.get_mut();
+            if queue.is_available(handle) {
+                queue.remove(handle);
+            }
+        }
+
+        order
+    }
+}
+
+fn count_items(world: &World) -> FxHashMap<ItemDefinitionId, u32> {
+    let mut counts = FxHashMap::default();
+    for item in <Read<ItemComponent>>::query()
+        .filter(!component::<Destroy>())
+        .iter(world)
+    {
+        *counts.entry(item.id()).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/// The first reagent which is neither at the workshop nor lying loose for a worker to bring or
+/// carry over. Who works the order isn't known yet, so conditions on them are assumed to hold.
+fn missing_reagent(
+    state: GameStateRef,
+    reaction: &ReactionDefinition,
+    workshop: Entity,
+) -> Option<Reagent> {
+    let GameStateRef { world, resources } = state;
+    let parameters = CheckConditionParameters::new(world, resources, None, Some(workshop));
+    let tile = **world.get_component::<PositionComponent>(workshop)?;
+
+    reaction
+        .reagents
+        .iter()
+        .find(|reagent| {
+            condition::check(&parameters, &reagent.conditions).is_err()
+                && [true, false].iter().all(|target| {
+                    let wanted = condition::wanted_items(&reagent.conditions, *target);
+                    wanted.is_empty()
+                        || condition::find_loose_item(world, resources, tile, &wanted, Some(tile))
+                            .is_none()
+                })
+        })
+        .cloned()
+}
+
+/// Tracks finished order tasks and queues the next task for every order which can run
+pub fn build_workshop_orders_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<Channel<TaskReservationEvent>>() {
+        resources.insert(Channel::<TaskReservationEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<TaskReservationEvent>>()
+        .unwrap()
+        .bind_listener(256);
+
+    let query = <(
+        Read<WorkshopComponent>,
+        Read<OrderQueueComponent>,
+        Read<HasTasksComponent>,
+    )>::query()
+    .filter(!component::<Destroy>());
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("workshop_orders_system");
+
+        // Tasks are only completed once their reaction has run
+        let mut completed = FxHashSet::default();
+        {
+            let channel = resources.get::<Channel<TaskReservationEvent>>().unwrap();
+            while let Some(event) = channel.read(listener_id) {
+                if event.change == TaskReservationChange::Completed {
+                    completed.insert((event.entity, event.handle));
+                }
+            }
+        }
+
+        let workshops = query
+            .iter_entities(world)
+            .filter(|(_, (_, orders, _))| !orders.orders.is_empty())
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        if workshops.is_empty() {
+            return;
+        }
+
+        let (reactions, labors) = <(
+            Read<DefinitionStorage<ReactionDefinition>>,
+            Read<DefinitionStorage<LaborDefinition>>,
+        )>::fetch(resources);
+
+        let mut stock = None;
+        let state = GameStateRef { world, resources };
+
+        for entity in workshops {
+            let tasks = world.get_component::<HasTasksComponent>(entity).unwrap();
+            let orders =
+                unsafe { world.get_component_mut_unchecked::<OrderQueueComponent>(entity) }
+                    .unwrap();
+
+            for order in &mut orders.orders {
+                // A task which left the queue without being completed failed, and is queued again
+                if let Some(handle) = order.task {
+                    if tasks.storage.get().get(handle).is_none() {
+                        order.task = None;
+                        if completed.contains(&(entity, handle)) {
+                            order.completed += 1;
+                        }
+                    }
+                }
+
+                if order.is_queued() || order.is_finished() {
+                    continue;
+                }
+
+                let reaction = order.reaction.fetch(&reactions);
+
+                order.blocked = if order.suspended {
+                    Some(OrderBlocked::Suspended)
+                } else if let OrderMode::KeepStock(target) = order.mode {
+                    let count = match &reaction.product {
+                        Some(product) => match &product.kind {
+                            ProductKind::Item(item) => stock
+                                .get_or_insert_with(|| count_items(world))
+                                .get(&item.id())
+                                .copied()
+                                .unwrap_or(0),
+                        },
+                        None => 0,
+                    };
+
+                    if count >= target {
+                        Some(OrderBlocked::StockReached(count))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                };
+                if order.blocked.is_some() {
+                    continue;
+                }
+
+                if let Some(reagent) = missing_reagent(state, reaction, entity) {
+                    order.blocked = Some(OrderBlocked::MissingReagent(reagent));
+                    continue;
+                }
+
+                match labors.for_reaction(reaction) {
+                    Some(labor) => {
+                        order.task = Some(tasks.storage.get_mut().insert(Task::new(
+                            ORDER_TASK_PRIORITY,
+                            labor,
+                            reaction.id(),
+                        )));
+                    }
+                    None => order.blocked = Some(OrderBlocked::NoLabor),
+                }
+            }
+        }
+    })
+}
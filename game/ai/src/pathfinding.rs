@@ -110,6 +110,30 @@ pub fn neighbors(map: &Map, coord: &Vec3i) -> SmallVec<[(Vec3i, f32); 16]> {
     res
 }
 
+/// The furthest walkable tile up to `distance` steps directly away from `from`, stepping back
+/// towards `position` until there is somewhere to stand
+pub fn step_away(map: &Map, position: Vec3i, from: Vec3i, distance: i32) -> Option<Vec3i> {
+    let dimensions = map.dimensions();
+
+    let away = position - from;
+    let length = away.x.abs().max(away.y.abs()).max(1);
+
+    (1..=distance)
+        .rev()
+        .map(|step| {
+            Vec3i::new(
+                (position.x + away.x * step / length)
+                    .max(0)
+                    .min(dimensions.x - 1),
+                (position.y + away.y * step / length)
+                    .max(0)
+                    .min(dimensions.y - 1),
+                position.z,
+            )
+        })
+        .find(|target| *target != position && map.get(*target).is_walkable())
+}
+
 const MAX_ASTAR_STEPS: u32 = 65536;
 
 pub fn a_star_search<S>(start: u32, end: u32, map: &Map, spatial_set: &S) -> NavigationPath
@@ -319,4 +343,34 @@ mod tests {
         let cost = (src - dst).mag() as u32;
         println!("cost = {}", cost);
     }
+
+    #[test]
+    fn step_away_test() -> Result<(), rl_core::failure::Error> {
+        use rl_core::map::tile::{Tile, TileKind};
+
+        let mut map = Map::with_default(Vec3i::new(8, 8, 1), || Tile {
+            kind: TileKind::Floor,
+            ..Tile::default()
+        })?;
+
+        let position = Vec3i::new(4, 4, 0);
+        assert_eq!(
+            step_away(&map, position, Vec3i::new(1, 4, 0), 2),
+            Some(Vec3i::new(6, 4, 0))
+        );
+        // Clamped to the edge of the map
+        assert_eq!(
+            step_away(&map, position, Vec3i::new(3, 3, 0), 8),
+            Some(Vec3i::new(7, 7, 0))
+        );
+
+        map.get_mut_untracked(Vec3i::new(7, 7, 0)).kind = TileKind::Solid;
+        assert_eq!(
+            step_away(&map, position, Vec3i::new(3, 3, 0), 8),
+            Some(Vec3i::new(6, 6, 0))
+        );
+        assert_eq!(step_away(&map, position, Vec3i::new(3, 3, 0), 0), None);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,174 @@
+use rl_core::{
+    components::PositionComponent,
+    data::NoiseEvent,
+    defs::{
+        body::{BodyComponent, BodyDefinition, PartFlag},
+        DefinitionComponent, DefinitionStorage,
+    },
+    event::Channel,
+    legion::prelude::*,
+    map::{
+        spatial::{SpatialMap, StaticSpatialMap},
+        Map,
+    },
+    math::Vec3i,
+};
+
+/// Sight range in tiles with all eyes working
+pub const BASE_SIGHT_RANGE: u32 = 16;
+/// Hearing range in tiles with all ears working, scaled by the volume of a noise
+pub const BASE_HEARING_RANGE: u32 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Percept {
+    pub entity: Entity,
+    pub position: Vec3i,
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SensesComponent {
+    pub sight_range: u32,
+    pub hearing_range: u32,
+
+    /// Entities seen this tick, nearest first
+    pub perceived: Vec<Percept>,
+    /// Noises heard this tick
+    pub heard: Vec<NoiseEvent>,
+}
+impl SensesComponent {
+    pub fn can_see(&self, entity: Entity) -> bool {
+        self.perceived.iter().any(|percept| percept.entity == entity)
+    }
+
+    pub fn nearest<F>(&self, mut f: F) -> Option<&Percept>
+    where
+        F: FnMut(&Percept) -> bool,
+    {
+        self.perceived.iter().find(|percept| f(percept))
+    }
+}
+
+fn scaled_range(base: u32, parts: usize) -> u32 {
+    match parts {
+        0 => 0,
+        1 => base * 3 / 4,
+        _ => base,
+    }
+}
+
+/// Walks a 3d bresenham line between the two tiles, failing on any solid tile in between
+#[allow(clippy::cast_possible_truncation)]
+pub fn line_of_sight(map: &Map, from: Vec3i, to: Vec3i) -> bool {
+    let delta = to - from;
+    let steps = delta.x.abs().max(delta.y.abs()).max(delta.z.abs());
+
+    for step in 1..steps {
+        let t = step as f32 / steps as f32;
+        let coord = Vec3i::new(
+            from.x + (delta.x as f32 * t).round() as i32,
+            from.y + (delta.y as f32 * t).round() as i32,
+            from.z + (delta.z as f32 * t).round() as i32,
+        );
+
+        if map.get(coord).is_solid() {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn distance(a: Vec3i, b: Vec3i) -> f32 {
+    let d = a - b;
+    ((d.x * d.x + d.y * d.y + d.z * d.z) as f32).sqrt()
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub fn build_perception_system(_: &mut World, resources: &mut Resources) -> Box<dyn Schedulable> {
+    if resources.get::<Channel<NoiseEvent>>().is_none() {
+        resources.insert(Channel::<NoiseEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<NoiseEvent>>()
+        .unwrap()
+        .bind_listener(1024);
+
+    let mut noises = Vec::with_capacity(64);
+
+    SystemBuilder::<()>::new("perception_system")
+        .read_resource::<Map>()
+        .read_resource::<SpatialMap>()
+        .read_resource::<StaticSpatialMap>()
+        .read_resource::<Channel<NoiseEvent>>()
+        .read_resource::<DefinitionStorage<BodyDefinition>>()
+        .with_query(<(
+            Write<SensesComponent>,
+            Read<PositionComponent>,
+            TryRead<BodyComponent>,
+        )>::query())
+        .build(
+            move |_, world, (map, spatial_map, static_spatial_map, channel, bodies), query| {
+                game_metrics::scope!("perception_system");
+
+                noises.clear();
+                while let Some(noise) = channel.read(listener_id) {
+                    noises.push(noise);
+                }
+
+                for (entity, (mut senses, position, body)) in query.iter_entities_mut(world) {
+                    if let Some(body) = body {
+                        let def = body.fetch(&bodies);
+                        senses.sight_range = scaled_range(
+                            BASE_SIGHT_RANGE,
+                            body.count_parts(def, PartFlag::SIGHT),
+                        );
+                        senses.hearing_range = scaled_range(
+                            BASE_HEARING_RANGE,
+                            body.count_parts(def, PartFlag::HEAR),
+                        );
+                    }
+
+                    let origin = **position;
+
+                    senses.perceived.clear();
+                    if senses.sight_range > 0 {
+                        let range_2 = (senses.sight_range * senses.sight_range) as i32;
+
+                        let mut perceived = spatial_map
+                            .locate_within_distance(*position, range_2)
+                            .chain(static_spatial_map.locate_within_distance(*position, range_2))
+                            .filter(|entry| entry.entity != entity)
+                            .filter_map(|entry| {
+                                let target = entry.position();
+                                if line_of_sight(&map, origin, target) {
+                                    Some(Percept {
+                                        entity: entry.entity,
+                                        position: target,
+                                        distance: distance(origin, target),
+                                    })
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect::<Vec<_>>();
+
+                        perceived.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+                        senses.perceived = perceived;
+                    }
+
+                    senses.heard.clear();
+                    if senses.hearing_range > 0 {
+                        let hearing_range = senses.hearing_range as f32;
+                        let heard = noises.iter().filter(|noise| {
+                            noise.source != Some(entity)
+                                && distance(origin, noise.position)
+                                    <= hearing_range * noise.volume as f32
+                                        / BASE_HEARING_RANGE as f32
+                        });
+                        senses.heard.extend(heard);
+                    }
+                }
+            },
+        )
+}
@@ -0,0 +1,366 @@
+use crate::{
+    bt::{BehaviorStorage, BehaviorTreeComponent},
+    perception::SensesComponent,
+};
+use rl_core::{
+    components::{AttributesComponent, PawnTag, PositionComponent},
+    defs::race::Attributes,
+    event::Channel,
+    fxhash::FxHashMap,
+    legion::prelude::*,
+    petgraph::{
+        stable_graph::{NodeIndex, StableDiGraph},
+        visit::EdgeRef,
+    },
+    rand::Rng,
+    time::Time,
+    Random,
+};
+
+pub const MAX_OPINION: i32 = 100;
+pub const MAX_FAMILIARITY: u32 = 100;
+/// Pawns at or below this opinion of another consider them a rival
+pub const RIVAL_OPINION: i32 = -30;
+/// Pawns at or above this opinion of another consider them a friend
+pub const FRIEND_OPINION: i32 = 30;
+
+/// Distance in tiles two pawns can talk over
+pub const CONVERSATION_RANGE: f32 = 3.0;
+/// World seconds before an idle pawn will start another conversation on its own
+pub const CONVERSATION_COOLDOWN: f64 = 3600.0;
+
+const INSULT_CHANCE: f64 = 0.1;
+const COMPLIMENT_CHANCE: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kinship {
+    Parent,
+    Child,
+    Sibling,
+    Spouse,
+}
+impl Kinship {
+    pub fn reciprocal(self) -> Self {
+        match self {
+            Self::Parent => Self::Child,
+            Self::Child => Self::Parent,
+            Self::Sibling => Self::Sibling,
+            Self::Spouse => Self::Spouse,
+        }
+    }
+
+    pub fn opinion_bonus(self) -> i32 {
+        match self {
+            Self::Spouse => 40,
+            Self::Parent | Self::Child | Self::Sibling => 25,
+        }
+    }
+}
+
+/// How one pawn feels about another. Relationships are directional.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Relationship {
+    pub opinion: i32,
+    pub familiarity: u32,
+    pub kinship: Option<Kinship>,
+}
+impl Relationship {
+    pub fn is_rival(&self) -> bool {
+        self.opinion <= RIVAL_OPINION
+    }
+
+    pub fn is_friend(&self) -> bool {
+        self.opinion >= FRIEND_OPINION
+    }
+}
+
+#[derive(Default)]
+pub struct RelationshipGraph {
+    graph: StableDiGraph<Entity, Relationship>,
+    nodes: FxHashMap<Entity, NodeIndex>,
+}
+impl RelationshipGraph {
+    fn node(&mut self, entity: Entity) -> NodeIndex {
+        let graph = &mut self.graph;
+        *self
+            .nodes
+            .entry(entity)
+            .or_insert_with(|| graph.add_node(entity))
+    }
+
+    pub fn get(&self, from: Entity, to: Entity) -> Option<&Relationship> {
+        let a = self.nodes.get(&from)?;
+        let b = self.nodes.get(&to)?;
+
+        self.graph.find_edge(*a, *b).map(|edge| &self.graph[edge])
+    }
+
+    /// The relationship of `from` towards `to`, created as strangers if they have never met
+    pub fn get_mut(&mut self, from: Entity, to: Entity) -> &mut Relationship {
+        let a = self.node(from);
+        let b = self.node(to);
+
+        let edge = match self.graph.find_edge(a, b) {
+            Some(edge) => edge,
+            None => self.graph.add_edge(a, b, Relationship::default()),
+        };
+
+        &mut self.graph[edge]
+    }
+
+    pub fn opinion(&self, from: Entity, to: Entity) -> i32 {
+        self.get(from, to).map_or(0, |relationship| relationship.opinion)
+    }
+
+    pub fn is_rival(&self, from: Entity, to: Entity) -> bool {
+        self.get(from, to).map_or(false, Relationship::is_rival)
+    }
+
+    pub fn adjust_opinion(&mut self, from: Entity, to: Entity, delta: i32) {
+        let relationship = self.get_mut(from, to);
+        relationship.opinion = (relationship.opinion + delta)
+            .max(-MAX_OPINION)
+            .min(MAX_OPINION);
+    }
+
+    pub fn add_familiarity(&mut self, from: Entity, to: Entity, amount: u32) {
+        let relationship = self.get_mut(from, to);
+        relationship.familiarity = (relationship.familiarity + amount).min(MAX_FAMILIARITY);
+    }
+
+    /// Records `a` as `kinship` of `b`, and the reciprocal from `b` to `a`
+    pub fn set_kinship(&mut self, a: Entity, b: Entity, kinship: Kinship) {
+        for (from, to, kinship) in &[(b, a, kinship), (a, b, kinship.reciprocal())] {
+            let relationship = self.get_mut(*from, *to);
+            if relationship.kinship.is_none() {
+                relationship.opinion = (relationship.opinion + kinship.opinion_bonus())
+                    .max(-MAX_OPINION)
+                    .min(MAX_OPINION);
+            }
+            relationship.kinship = Some(*kinship);
+        }
+    }
+
+    /// Every relationship `entity` holds towards others
+    pub fn relationships(&self, entity: Entity) -> impl Iterator<Item = (Entity, &Relationship)> {
+        self.nodes.get(&entity).into_iter().flat_map(move |node| {
+            self.graph
+                .edges(*node)
+                .map(move |edge| (self.graph[edge.target()], edge.weight()))
+        })
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(node) = self.nodes.remove(&entity) {
+            self.graph.remove_node(node);
+        }
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.nodes.keys().copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocialEventKind {
+    Conversation,
+    Compliment,
+    Insult,
+    SharedWork,
+    Gift,
+}
+impl SocialEventKind {
+    pub fn opinion(self) -> i32 {
+        match self {
+            Self::Conversation => 2,
+            Self::Compliment => 5,
+            Self::Insult => -10,
+            Self::SharedWork => 1,
+            Self::Gift => 10,
+        }
+    }
+
+    pub fn familiarity(self) -> u32 {
+        match self {
+            Self::Conversation | Self::Compliment | Self::Insult => 2,
+            Self::SharedWork | Self::Gift => 1,
+        }
+    }
+}
+
+/// `from` did something to `to`, shifting how `to` feels about `from`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocialEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub kind: SocialEventKind,
+}
+impl SocialEvent {
+    pub fn new(from: Entity, to: Entity, kind: SocialEventKind) -> Self {
+        Self { from, to, kind }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SocialComponent {
+    /// World time of the last conversation this pawn took part in
+    pub last_conversation: f64,
+}
+
+/// Pick what `speaker` says to `listener`. Social pawns compliment more, and everyone is ruder to rivals.
+pub fn converse<R: Rng>(
+    rng: &mut R,
+    graph: &RelationshipGraph,
+    speaker: Entity,
+    attributes: Option<&Attributes>,
+    listener: Entity,
+) -> SocialEventKind {
+    let social = f64::from(attributes.map_or(1000, |attributes| attributes.social).max(1));
+
+    let mut insult = INSULT_CHANCE * 1000.0 / social;
+    if graph.is_rival(speaker, listener) {
+        insult *= 2.0;
+    }
+    let compliment = COMPLIMENT_CHANCE * social / 1000.0;
+
+    let roll = rng.gen_range(0.0, 1.0);
+    if roll < insult {
+        SocialEventKind::Insult
+    } else if roll < insult + compliment {
+        SocialEventKind::Compliment
+    } else {
+        SocialEventKind::Conversation
+    }
+}
+
+/// Both sides of a conversation between two pawns
+pub fn conversation_events<R: Rng>(
+    rng: &mut R,
+    world: &World,
+    graph: &RelationshipGraph,
+    a: Entity,
+    b: Entity,
+) -> [SocialEvent; 2] {
+    let attributes = |entity| world.get_component::<AttributesComponent>(entity).map(|c| c.0);
+
+    [
+        SocialEvent::new(a, b, converse(rng, graph, a, attributes(a).as_ref(), b)),
+        SocialEvent::new(b, a, converse(rng, graph, b, attributes(b).as_ref(), a)),
+    ]
+}
+
+/// Empathic pawns are moved more by what others do
+#[allow(clippy::cast_possible_truncation)]
+fn apply_event(graph: &mut RelationshipGraph, event: &SocialEvent, empathy: u16) {
+    let delta = (f64::from(event.kind.opinion()) * f64::from(empathy) / 1000.0).round() as i32;
+
+    graph.adjust_opinion(event.to, event.from, delta);
+    graph.add_familiarity(event.to, event.from, event.kind.familiarity());
+    graph.add_familiarity(event.from, event.to, event.kind.familiarity());
+}
+
+fn distance(a: &PositionComponent, b: &PositionComponent) -> f32 {
+    let d = **a - **b;
+    ((d.x * d.x + d.y * d.y + d.z * d.z) as f32).sqrt()
+}
+
+/// Starts conversations between idle pawns near each other and applies all social events to the
+/// relationship graph.
+pub fn build_social_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<RelationshipGraph>() {
+        resources.insert(RelationshipGraph::default());
+    }
+    if !resources.contains::<Channel<SocialEvent>>() {
+        resources.insert(Channel::<SocialEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<SocialEvent>>()
+        .unwrap()
+        .bind_listener(256);
+
+    let idle_query = <(
+        Read<PositionComponent>,
+        Read<SocialComponent>,
+        Read<BehaviorTreeComponent>,
+    )>::query()
+    .filter(tag::<PawnTag>());
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("social_system");
+
+        let now = resources.get::<Time>().unwrap().world_time;
+
+        let idle = resources
+            .get::<BehaviorStorage>()
+            .unwrap()
+            .get_handle("idle");
+
+        let talkers = idle_query
+            .iter_entities(world)
+            .filter(|(_, (_, social, tree))| {
+                tree.root.handle().is_some()
+                    && tree.root.handle() == idle
+                    && now - social.last_conversation >= CONVERSATION_COOLDOWN
+            })
+            .map(|(entity, (position, _, _))| (entity, *position))
+            .collect::<Vec<_>>();
+
+        let mut talked = Vec::new();
+        {
+            let (channel, graph, random) = <(
+                Read<Channel<SocialEvent>>,
+                Read<RelationshipGraph>,
+                Read<Random>,
+            )>::fetch(resources);
+            let mut rng = random.make();
+
+            for (i, (a, a_position)) in talkers.iter().enumerate() {
+                if talked.contains(a) {
+                    continue;
+                }
+
+                let partner = talkers[i + 1..].iter().find(|(b, b_position)| {
+                    !talked.contains(b)
+                        && distance(a_position, b_position) <= CONVERSATION_RANGE
+                        && world
+                            .get_component::<SensesComponent>(*a)
+                            .map_or(false, |senses| senses.can_see(*b))
+                });
+
+                if let Some((b, _)) = partner {
+                    let events = conversation_events(&mut rng, world, &graph, *a, *b);
+                    channel.write_iter(events.iter().copied()).unwrap();
+
+                    talked.push(*a);
+                    talked.push(*b);
+                }
+            }
+        }
+
+        for entity in talked {
+            if let Some(mut social) = world.get_component_mut::<SocialComponent>(entity) {
+                social.last_conversation = now;
+            }
+        }
+
+        let (channel, mut graph) =
+            <(Read<Channel<SocialEvent>>, Write<RelationshipGraph>)>::fetch_mut(resources);
+
+        while let Some(event) = channel.read(listener_id) {
+            let empathy = world
+                .get_component::<AttributesComponent>(event.to)
+                .map_or(1000, |attributes| attributes.empathy);
+
+            apply_event(&mut graph, &event, empathy);
+        }
+
+        let dead = graph
+            .entities()
+            .filter(|entity| !world.is_alive(*entity))
+            .collect::<Vec<_>>();
+        dead.into_iter().for_each(|entity| graph.remove(entity));
+    })
+}
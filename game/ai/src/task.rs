@@ -1,105 +1,82 @@
 use rl_core::defs::{
+    labor::{LaborDefinition, LaborDefinitionId},
     reaction::{ReactionDefinition, ReactionDefinitionId},
-    DefinitionStorage,
+    Definition, DefinitionStorage,
 };
 use rl_core::{
-    bitflags::*,
-    bitflags_serial,
-    components::{Destroy, PositionComponent},
-    fxhash::{FxHashMap, FxHashSet},
+    components::{BlackboardComponent, DeadTag, Destroy, PositionComponent},
+    event::Channel,
+    fnv,
+    fxhash::FxHashMap,
     legion::{borrow::AtomicRefCell, entity::Entity},
     map::Map,
     rstar, slotmap,
     smallvec::SmallVec,
     strum_macros::EnumDiscriminants,
+    time::Time,
     uuid::Uuid,
     GameStateRef,
 };
 use rl_reaction::{ReactionEntity, ReactionExecution};
 use std::{convert::TryInto, hash::Hash, iter::FromIterator, sync::Arc};
 
-pub const TASKKIND_COUNT: usize = 3;
-
-bitflags_serial! {
-    pub struct TaskKind: u32 {
-        const MINING           =  0b1000_0000_0000_0000_0000_0000_0000_0000;
-        const CLEANING         =  0b0100_0000_0000_0000_0000_0000_0000_0000;
-        const WOODCUTTING      =  0b0010_0000_0000_0000_0000_0000_0000_0000;
-    }
-}
+/// Labors a pawn will usually have
+const LABOR_COUNT: usize = 16;
 
+/// Priority of every labor for a pawn, lower priorities are worked first
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct TaskPrioritiesComponent {
-    priorities: [u8; TASKKIND_COUNT],
+    priorities: SmallVec<[u8; LABOR_COUNT]>,
 }
 impl TaskPrioritiesComponent {
-    pub fn get(&self, kind: TaskKind) -> u8 {
-        self.priorities
-            .get(Self::get_bit_index(kind.bits()).unwrap())
-            .copied()
-            .unwrap()
+    pub fn new(labors: &DefinitionStorage<LaborDefinition>) -> Self {
+        let mut priorities = Self::default();
+        for labor in labors.iter() {
+            priorities.set(labor.id(), labor.default_priority);
+        }
+
+        priorities
     }
 
-    pub fn get_mut(&mut self, kind: TaskKind) -> &mut u8 {
-        self.priorities
-            .get_mut(Self::get_bit_index(kind.bits()).unwrap())
-            .unwrap()
+    pub fn get(&self, labor: LaborDefinitionId) -> u8 {
+        self.priorities.get(*labor).copied().unwrap_or_default()
     }
 
-    pub fn set(&mut self, kind: TaskKind, priority: u8) {
-        self.priorities[Self::get_bit_index(kind.bits()).unwrap()] = priority;
+    pub fn get_mut(&mut self, labor: LaborDefinitionId) -> &mut u8 {
+        if *labor >= self.priorities.len() {
+            self.priorities.resize(*labor + 1, 0);
+        }
+
+        &mut self.priorities[*labor]
     }
 
-    pub fn sorted(&self) -> SmallVec<[(TaskKind, u8); TASKKIND_COUNT]> {
-        let mut sorted: SmallVec<[(TaskKind, u8); TASKKIND_COUNT]> = SmallVec::default();
+    pub fn set(&mut self, labor: LaborDefinitionId, priority: u8) {
+        *self.get_mut(labor) = priority;
+    }
+
+    pub fn sorted(&self) -> SmallVec<[(LaborDefinitionId, u8); LABOR_COUNT]> {
+        let mut sorted: SmallVec<[(LaborDefinitionId, u8); LABOR_COUNT]> = SmallVec::default();
 
         self.iter()
-            .for_each(|(kind, priority)| sorted.push((kind, *priority)));
+            .for_each(|(labor, priority)| sorted.push((labor, *priority)));
 
         sorted.sort_by(|left, right| left.1.cmp(&right.1));
 
         sorted
     }
 
-    #[allow(clippy::cast_possible_truncation)]
-    pub fn iter(&self) -> impl Iterator<Item = (TaskKind, &u8)> {
-        self.priorities.iter().enumerate().map(|(n, priority)| {
-            (
-                TaskKind::from_bits(Self::set_bit(n as u32)).unwrap(),
-                priority,
-            )
-        })
-    }
-
-    #[allow(clippy::cast_possible_truncation)]
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (TaskKind, &mut u8)> {
-        self.priorities.iter_mut().enumerate().map(|(n, priority)| {
-            (
-                TaskKind::from_bits(Self::set_bit(n as u32)).unwrap(),
-                priority,
-            )
-        })
-    }
-
-    #[inline]
-    fn set_bit(p: u32) -> u32 {
-        1 << (31 - p)
+    pub fn iter(&self) -> impl Iterator<Item = (LaborDefinitionId, &u8)> {
+        self.priorities
+            .iter()
+            .enumerate()
+            .map(|(n, priority)| (LaborDefinitionId(n), priority))
     }
 
-    #[inline]
-    fn get_bit_index(mut n: u32) -> Option<usize> {
-        if !(n > 0 && ((n & (n - 1)) == 0)) {
-            return None;
-        }
-
-        let mut count: u32 = 0;
-
-        while n != 0 {
-            n >>= 1;
-            count += 1;
-        }
-
-        Some(32 - count as usize)
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (LaborDefinitionId, &mut u8)> {
+        self.priorities
+            .iter_mut()
+            .enumerate()
+            .map(|(n, priority)| (LaborDefinitionId(n), priority))
     }
 }
 
@@ -131,6 +108,13 @@ impl HasTasksComponent {
         self.storage.get().len()
     }
 }
+impl From<TaskQueue> for HasTasksComponent {
+    fn from(queue: TaskQueue) -> Self {
+        Self {
+            storage: Arc::new(AtomicRefCell::new(queue)),
+        }
+    }
+}
 impl FromIterator<Task> for HasTasksComponent {
     fn from_iter<I: IntoIterator<Item = Task>>(iter: I) -> Self {
         Self {
@@ -143,7 +127,7 @@ impl FromIterator<Task> for HasTasksComponent {
 pub struct Task {
     pub id: u128,
     pub priority: u8,
-    pub kind: TaskKind,
+    pub labor: LaborDefinitionId,
     pub reaction: ReactionDefinitionId,
 }
 impl PartialEq for Task {
@@ -153,12 +137,12 @@ impl PartialEq for Task {
     }
 }
 impl Task {
-    pub fn new(priority: u8, kind: TaskKind, reaction: ReactionDefinitionId) -> Self {
+    pub fn new(priority: u8, labor: LaborDefinitionId, reaction: ReactionDefinitionId) -> Self {
         let id = Uuid::new_v4().as_u128();
         Self {
             id,
             priority,
-            kind,
+            labor,
             reaction,
         }
     }
@@ -166,15 +150,27 @@ impl Task {
 
 use rl_core::{
     legion::{borrow, prelude::*},
-    math::Vec3i,
+    math::{Vec3i, Vec3iProxy},
     Logging,
 };
 
+/// Something which has to finish before a task becomes available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum TaskDependency {
+    /// Another task in the same queue
+    Task(TaskHandle),
+    /// Every task queued at a location
+    Location(#[serde(with = "Vec3iProxy")] Vec3i),
+}
+
 #[derive(Clone, Debug, PartialEq, EnumDiscriminants)]
 #[strum_discriminants(name(FindBestTaskErrorKind))]
 pub enum FindBestTaskError {
-    MissingReagent(rl_core::defs::reaction::Reagent),
+    /// A reagent of the task held by the entity, such as a workshop, is missing
+    MissingReagent(rl_core::defs::reaction::Reagent, Entity),
     NoPath(Vec3i),
+    /// Lost the parts needed to work
+    Impaired,
     Empty,
     Other,
 }
@@ -229,6 +225,13 @@ impl TaskCache {
         );
     }
 
+    /// True while there are unfinished tasks at `location`
+    pub fn has_tasks_at(&self, location: Vec3i) -> bool {
+        self.tasks
+            .get(&location)
+            .map_or(false, |entry| !entry.queue.get().is_empty())
+    }
+
     pub fn len(&self) -> usize {
         self.tasks
             .values()
@@ -254,6 +257,14 @@ impl TaskCache {
     ) -> Result<(Vec3i, TaskCacheEntry, TaskHandle), FindBestTaskError> {
         game_metrics::scope!("find_best_task");
 
+        if !rl_reaction::can_work(
+            state.world,
+            state.resources,
+            Some(ReactionEntity::Pawn(source_entity)),
+        ) {
+            return Err(FindBestTaskError::Impaired);
+        }
+
         let priorities = source_priorities.sorted();
 
         let mut err = FindBestTaskError::Empty;
@@ -261,7 +272,7 @@ impl TaskCache {
         let (map, reactions) =
             <(Read<Map>, Read<DefinitionStorage<ReactionDefinition>>)>::fetch(state.resources);
 
-        for (task_kind, _) in &priorities {
+        for (labor, _) in &priorities {
             for task_location in self.tree.nearest_neighbor_iter(
                 source_location
                     .as_slice()
@@ -272,7 +283,7 @@ impl TaskCache {
                 if let Some(queue_ptr) = self.tasks.get(&task_location.into()) {
                     let queue = queue_ptr.queue.get();
 
-                    let iter = queue.iter_available(*task_kind);
+                    let iter = queue.iter_available(*labor, self);
 
                     if let Some(iter) = iter {
                         let mut sorted = iter.collect::<Vec<_>>();
@@ -282,11 +293,11 @@ impl TaskCache {
                             // Before expensive pathfinding, check the tasks reaction
                             let task = queue.get(entry.handle).unwrap();
 
-                            match task
-                                .reaction
-                                .fetch(&reactions)
-                                .can_initiate(state, ReactionEntity::Pawn(source_entity))
-                            {
+                            match task.reaction.fetch(&reactions).can_initiate(
+                                state,
+                                ReactionEntity::Pawn(source_entity),
+                                Some(ReactionEntity::Task(queue_ptr.entity)),
+                            ) {
                                 Ok(_) => {
                                     if let Some(dst) =
                                         crate::pathfinding::neighbors(&map, &task_location.into())
@@ -300,7 +311,8 @@ impl TaskCache {
                                 }
                                 Err(e) => {
                                     if FindBestTaskErrorKind::MissingReagent != err.clone().into() {
-                                        err = FindBestTaskError::MissingReagent(e);
+                                        err =
+                                            FindBestTaskError::MissingReagent(e, queue_ptr.entity);
                                     }
                                 }
                             };
@@ -364,7 +376,10 @@ pub fn build_cleanup_virtual_tasks(_: &mut World, _: &mut Resources) -> Box<dyn
                 .filter(!component::<Destroy>()),
         )
         .build(
-            move |command_buffer, world, _cache, (virtual_task_query, _, _task_query)| {
+            move |command_buffer,
+                  world,
+                  _cache,
+                  (virtual_task_query, destroyed_query, task_query)| {
                 game_metrics::scope!("cleanup_virtual_tasks");
 
                 for (entity, tasks) in virtual_task_query.iter_entities(world) {
@@ -373,6 +388,28 @@ pub fn build_cleanup_virtual_tasks(_: &mut World, _: &mut Resources) -> Box<dyn
                     }
                 }
 
+                // Designations destroyed with tasks still queued were cancelled, so fail
+                // everything waiting on their locations, and anything waiting on those in turn.
+                let mut cancelled = destroyed_query
+                    .iter_entities(world)
+                    .filter(|(_, tasks)| !tasks.storage.get().is_empty())
+                    .filter_map(|(entity, _)| {
+                        world
+                            .get_component::<PositionComponent>(entity)
+                            .map(|position| **position)
+                    })
+                    .collect::<Vec<_>>();
+
+                while let Some(location) = cancelled.pop() {
+                    for (tasks, position) in task_query.iter(world) {
+                        let mut queue = tasks.storage.get_mut();
+                        let failed = queue.remove_dependents(TaskDependency::Location(location));
+                        if !failed.is_empty() && queue.is_empty() {
+                            cancelled.push(**position);
+                        }
+                    }
+                }
+
                 //if flag {
                 //    cache.update(
                 //        task_query
@@ -384,10 +421,139 @@ pub fn build_cleanup_virtual_tasks(_: &mut World, _: &mut Resources) -> Box<dyn
         )
 }
 
+/// A holder keeps the task it works on in its blackboard until it completes or cancels it, and
+/// loses it when its behavior ends
+fn is_working_on(world: &World, holder: Entity, queue: Entity, handle: TaskHandle) -> bool {
+    world
+        .get_component::<BlackboardComponent>(holder)
+        .map_or(false, |blackboard| {
+            blackboard
+                .get::<(Entity, TaskHandle, Task)>(fnv!("current_task"))
+                .map_or(false, |(entity, current, _)| {
+                    *entity == queue && *current == handle
+                })
+        })
+}
+
+pub fn build_reclaim_tasks_system(
+    _: &mut World,
+    resources: &mut Resources,
+) -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    if !resources.contains::<Channel<TaskReservationEvent>>() {
+        resources.insert(Channel::<TaskReservationEvent>::default());
+    }
+
+    let query = <(Read<HasTasksComponent>,)>::query();
+
+    Box::new(move |world, resources| {
+        game_metrics::scope!("reclaim_tasks_system");
+
+        let (time, channel) = <(Read<Time>, Read<Channel<TaskReservationEvent>>)>::fetch(resources);
+
+        let mut stale = Vec::new();
+        for (entity, (tasks,)) in query.iter_entities(world) {
+            // Return tasks whose holder died, was destroyed, moved on without finishing them or
+            // held on to them for too long, such as when stuck on the way there
+            let is_abandoned = |handle: TaskHandle, reservation: &TaskReservation| {
+                !world.is_alive(reservation.holder)
+                    || world.get_tag::<DeadTag>(reservation.holder).is_some()
+                    || world.get_component::<Destroy>(reservation.holder).is_some()
+                    || !is_working_on(world, reservation.holder, entity, handle)
+                    || time.world_time - reservation.taken > TaskReservation::TIMEOUT
+            };
+
+            for (handle, reservation) in tasks.storage.get_mut().reclaim(is_abandoned) {
+                if is_working_on(world, reservation.holder, entity, handle) {
+                    stale.push(reservation.holder);
+                }
+
+                channel
+                    .write(TaskReservationEvent::new(
+                        entity,
+                        handle,
+                        reservation.holder,
+                        TaskReservationChange::Reclaimed,
+                    ))
+                    .unwrap();
+            }
+        }
+
+        // Holders which timed out lose the task so their behavior gives up on it
+        for holder in stale {
+            if let Some(mut blackboard) = world.get_component_mut::<BlackboardComponent>(holder) {
+                blackboard.remove(fnv!("current_task"));
+            }
+        }
+    })
+}
+
+/// Returns every task `holder` has taken to its queue, such as when the holder dies
+pub fn release_held_tasks(world: &World, channel: &Channel<TaskReservationEvent>, holder: Entity) {
+    for (entity, tasks) in <Read<HasTasksComponent>>::query().iter_entities(world) {
+        for (handle, _) in tasks
+            .storage
+            .get_mut()
+            .reclaim(|_, reservation| reservation.holder == holder)
+        {
+            channel
+                .write(TaskReservationEvent::new(
+                    entity,
+                    handle,
+                    holder,
+                    TaskReservationChange::Reclaimed,
+                ))
+                .unwrap();
+        }
+    }
+}
+
 use std::collections::BTreeSet;
 
 slotmap::new_key_type! { pub struct TaskHandle; }
 
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaskReservation {
+    #[serde(with = "rl_core::saveload::entity")]
+    pub holder: Entity,
+    pub taken: f64,
+}
+impl TaskReservation {
+    /// World seconds a task can be held before it is returned to the queue
+    pub const TIMEOUT: f64 = 7200.0;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskReservationChange {
+    Taken,
+    Released,
+    Completed,
+    Reclaimed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskReservationEvent {
+    /// Entity owning the task queue
+    pub entity: Entity,
+    pub handle: TaskHandle,
+    pub holder: Entity,
+    pub change: TaskReservationChange,
+}
+impl TaskReservationEvent {
+    pub fn new(
+        entity: Entity,
+        handle: TaskHandle,
+        holder: Entity,
+        change: TaskReservationChange,
+    ) -> Self {
+        Self {
+            entity,
+            handle,
+            holder,
+            change,
+        }
+    }
+}
+
 #[derive(
     Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
 )]
@@ -401,8 +567,9 @@ pub type TaskQueuePtr = Arc<AtomicRefCell<TaskQueue>>;
 #[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TaskQueue {
     storage: slotmap::SlotMap<TaskHandle, Task>,
-    available: FxHashMap<TaskKind, BTreeSet<TaskQueueEntry>>,
-    taken: FxHashSet<TaskQueueEntry>,
+    available: FxHashMap<LaborDefinitionId, BTreeSet<TaskQueueEntry>>,
+    taken: FxHashMap<TaskQueueEntry, TaskReservation>,
+    dependencies: FxHashMap<TaskHandle, SmallVec<[TaskDependency; 2]>>,
 }
 impl FromIterator<Task> for TaskQueue {
     fn from_iter<I: IntoIterator<Item = Task>>(iter: I) -> Self {
@@ -415,7 +582,7 @@ impl FromIterator<Task> for TaskQueue {
             let handle = storage.insert(task);
 
             available
-                .entry(task.kind)
+                .entry(task.labor)
                 .or_insert_with(BTreeSet::<TaskQueueEntry>::default)
                 .insert(TaskQueueEntry { priority, handle });
         }
@@ -431,7 +598,7 @@ impl TaskQueue {
     pub fn insert(&mut self, task: Task) -> TaskHandle {
         let handle = self.storage.insert(task);
         self.available
-            .entry(task.kind)
+            .entry(task.labor)
             .or_insert_with(Default::default)
             .insert(TaskQueueEntry {
                 priority: task.priority,
@@ -441,6 +608,44 @@ impl TaskQueue {
         handle
     }
 
+    pub fn insert_with_dependencies(
+        &mut self,
+        task: Task,
+        dependencies: impl IntoIterator<Item = TaskDependency>,
+    ) -> TaskHandle {
+        let handle = self.insert(task);
+        for dependency in dependencies {
+            self.add_dependency(handle, dependency);
+        }
+
+        handle
+    }
+
+    pub fn add_dependency(&mut self, handle: TaskHandle, dependency: TaskDependency) {
+        if self.storage.contains_key(handle) {
+            self.dependencies
+                .entry(handle)
+                .or_insert_with(Default::default)
+                .push(dependency);
+        }
+    }
+
+    pub fn dependencies(&self, handle: TaskHandle) -> &[TaskDependency] {
+        self.dependencies
+            .get(&handle)
+            .map_or(&[][..], SmallVec::as_slice)
+    }
+
+    /// A task is blocked while any task it depends on is still queued
+    pub fn is_blocked(&self, handle: TaskHandle, cache: &TaskCache) -> bool {
+        self.dependencies(handle)
+            .iter()
+            .any(|dependency| match dependency {
+                TaskDependency::Task(other) => self.storage.contains_key(*other),
+                TaskDependency::Location(location) => cache.has_tasks_at(*location),
+            })
+    }
+
     pub fn get(&self, handle: TaskHandle) -> Option<&Task> {
         self.storage.get(handle)
     }
@@ -449,8 +654,18 @@ impl TaskQueue {
         self.storage.get_mut(handle)
     }
 
-    pub fn iter_available(&self, kind: TaskKind) -> Option<impl Iterator<Item = &TaskQueueEntry>> {
-        Some(self.available.get(&kind)?.iter())
+    /// Available tasks of a labor, skipping those still waiting on their dependencies
+    pub fn iter_available<'a>(
+        &'a self,
+        labor: LaborDefinitionId,
+        cache: &'a TaskCache,
+    ) -> Option<impl Iterator<Item = &'a TaskQueueEntry>> {
+        Some(
+            self.available
+                .get(&labor)?
+                .iter()
+                .filter(move |entry| !self.is_blocked(entry.handle, cache)),
+        )
     }
 
     pub fn iter_all(&self) -> impl Iterator<Item = (TaskHandle, &Task)> {
@@ -458,14 +673,22 @@ impl TaskQueue {
     }
 
     pub fn is_available(&self, handle: TaskHandle) -> bool {
-        if let Some(task) = self.storage.get(handle) {
-            return !self.taken.contains(&TaskQueueEntry {
-                priority: task.priority,
-                handle,
-            });
-        }
+        self.reservation(handle).is_none() && self.storage.contains_key(handle)
+    }
 
-        false
+    pub fn reservation(&self, handle: TaskHandle) -> Option<&TaskReservation> {
+        let task = self.storage.get(handle)?;
+
+        self.taken.get(&TaskQueueEntry {
+            priority: task.priority,
+            handle,
+        })
+    }
+
+    pub fn iter_taken(&self) -> impl Iterator<Item = (TaskHandle, &TaskReservation)> {
+        self.taken
+            .iter()
+            .map(|(entry, reservation)| (entry.handle, reservation))
     }
 
     pub fn top_any(&self) -> Option<TaskHandle> {
@@ -477,16 +700,16 @@ impl TaskQueue {
         None
     }
 
-    pub fn top(&self, kind: TaskKind) -> Option<TaskHandle> {
+    pub fn top(&self, labor: LaborDefinitionId) -> Option<TaskHandle> {
         self.available
-            .get(&kind)?
+            .get(&labor)?
             .iter()
             .rev()
             .nth(0)
             .map(|e| e.handle)
     }
 
-    pub fn take(&mut self, handle: TaskHandle) -> Option<Task> {
+    pub fn take(&mut self, handle: TaskHandle, holder: Entity, now: f64) -> Option<Task> {
         let task = self.storage.get(handle)?;
 
         let entry = TaskQueueEntry {
@@ -494,25 +717,32 @@ impl TaskQueue {
             handle,
         };
 
-        if !self.available.get_mut(&task.kind)?.remove(&entry) {
+        if !self.available.get_mut(&task.labor)?.remove(&entry) {
             return None;
         }
 
-        self.taken.insert(entry);
+        self.taken
+            .insert(entry, TaskReservation { holder, taken: now });
 
         Some(*task)
     }
 
-    pub fn cancel(&mut self, handle: TaskHandle) -> bool {
+    /// Returns a task taken by `holder` to the queue
+    pub fn cancel(&mut self, handle: TaskHandle, holder: Entity) -> bool {
         if let Some(task) = self.storage.get(handle) {
             let entry = TaskQueueEntry {
                 priority: task.priority,
                 handle,
             };
-            self.taken.remove(&entry);
+            match self.taken.get(&entry) {
+                Some(reservation) if reservation.holder == holder => {
+                    self.taken.remove(&entry);
+                }
+                _ => return false,
+            }
 
             self.available
-                .entry(task.kind)
+                .entry(task.labor)
                 .or_insert_with(Default::default)
                 .insert(entry);
 
@@ -525,15 +755,97 @@ impl TaskQueue {
     pub fn complete(&mut self, handle: TaskHandle) -> bool {
         let mut res = false;
         if let Some(task) = self.storage.get(handle) {
-            res = self.taken.remove(&TaskQueueEntry {
+            let entry = TaskQueueEntry {
                 priority: task.priority,
                 handle,
-            });
+            };
+            res = self.taken.remove(&entry).is_some();
+
+            // A reclaimed task can still be finished by its old holder
+            if let Some(available) = self.available.get_mut(&task.labor) {
+                available.remove(&entry);
+            }
         }
         self.storage.remove(handle);
+        self.dependencies.remove(&handle);
         res
     }
 
+    /// Returns every taken task whose reservation matches `f` to the queue
+    pub fn reclaim(
+        &mut self,
+        f: impl Fn(TaskHandle, &TaskReservation) -> bool,
+    ) -> SmallVec<[(TaskHandle, TaskReservation); 4]> {
+        let reclaimed = self
+            .taken
+            .iter()
+            .filter(|(entry, reservation)| f(entry.handle, reservation))
+            .map(|(entry, reservation)| (*entry, *reservation))
+            .collect::<SmallVec<[_; 4]>>();
+
+        for (entry, _) in &reclaimed {
+            self.taken.remove(entry);
+            if let Some(task) = self.storage.get(entry.handle) {
+                self.available
+                    .entry(task.labor)
+                    .or_insert_with(Default::default)
+                    .insert(*entry);
+            }
+        }
+
+        reclaimed
+            .into_iter()
+            .map(|(entry, reservation)| (entry.handle, reservation))
+            .collect()
+    }
+
+    /// Removes a task which will never finish, failing every task depending on it. Returns all the
+    /// removed handles.
+    pub fn remove(&mut self, handle: TaskHandle) -> SmallVec<[TaskHandle; 4]> {
+        let mut removed = SmallVec::new();
+        let mut pending: SmallVec<[TaskHandle; 4]> = SmallVec::new();
+        pending.push(handle);
+
+        while let Some(handle) = pending.pop() {
+            let task = match self.storage.remove(handle) {
+                Some(task) => task,
+                None => continue,
+            };
+            let entry = TaskQueueEntry {
+                priority: task.priority,
+                handle,
+            };
+            self.taken.remove(&entry);
+            if let Some(available) = self.available.get_mut(&task.labor) {
+                available.remove(&entry);
+            }
+            self.dependencies.remove(&handle);
+            removed.push(handle);
+
+            pending.extend(self.dependents(TaskDependency::Task(handle)));
+        }
+
+        removed
+    }
+
+    /// Removes every task depending on `dependency`, along with their own dependents
+    pub fn remove_dependents(&mut self, dependency: TaskDependency) -> SmallVec<[TaskHandle; 4]> {
+        let mut removed = SmallVec::new();
+        for handle in self.dependents(dependency) {
+            removed.extend(self.remove(handle));
+        }
+
+        removed
+    }
+
+    fn dependents(&self, dependency: TaskDependency) -> SmallVec<[TaskHandle; 4]> {
+        self.dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.contains(&dependency))
+            .map(|(handle, _)| *handle)
+            .collect()
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.storage.len()
@@ -553,16 +865,142 @@ mod tests {
     fn task_priorities_storage() {
         let mut p = TaskPrioritiesComponent::default();
 
-        p.set(TaskKind::MINING, 55);
-        p.set(TaskKind::CLEANING, 123);
-        p.set(TaskKind::WOODCUTTING, 222);
-
-        assert_eq!(55, p.get(TaskKind::MINING));
-        assert_eq!(123, p.get(TaskKind::CLEANING));
-        assert_eq!(222, p.get(TaskKind::WOODCUTTING));
+        p.set(LaborDefinitionId(0), 55);
+        p.set(LaborDefinitionId(1), 123);
+        p.set(LaborDefinitionId(2), 222);
+
+        assert_eq!(55, p.get(LaborDefinitionId(0)));
+        assert_eq!(123, p.get(LaborDefinitionId(1)));
+        assert_eq!(222, p.get(LaborDefinitionId(2)));
+        assert_eq!(0, p.get(LaborDefinitionId(7)));
+
+        p.set(LaborDefinitionId(4), 1);
+        assert_eq!(
+            p.sorted().as_slice(),
+            &[
+                (LaborDefinitionId(3), 0),
+                (LaborDefinitionId(4), 1),
+                (LaborDefinitionId(0), 55),
+                (LaborDefinitionId(1), 123),
+                (LaborDefinitionId(2), 222),
+            ]
+        );
 
         for task in p.iter() {
             println!("itered: {:?}", task);
         }
     }
+
+    #[test]
+    fn task_dependencies() {
+        let mut world = Universe::new().create_world();
+        let holder = world.insert((), vec![(0_u32,)])[0];
+
+        let cache = TaskCache::default();
+        let mut queue = TaskQueue::default();
+
+        let dig = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+        let channel = queue.insert_with_dependencies(
+            Task::new(5, LaborDefinitionId(0), Default::default()),
+            vec![TaskDependency::Task(dig)],
+        );
+        let ramp = queue.insert_with_dependencies(
+            Task::new(5, LaborDefinitionId(0), Default::default()),
+            vec![TaskDependency::Task(channel)],
+        );
+
+        let available = |queue: &TaskQueue| {
+            queue
+                .iter_available(LaborDefinitionId(0), &cache)
+                .unwrap()
+                .map(|entry| entry.handle)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(queue.is_blocked(channel, &cache));
+        assert_eq!(available(&queue), vec![dig]);
+
+        queue.take(dig, holder, 0.0);
+        assert!(queue.complete(dig));
+        assert_eq!(available(&queue), vec![channel]);
+
+        let removed = queue.remove(channel);
+        assert_eq!(removed.as_slice(), &[channel, ramp]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn task_reservations() {
+        let mut world = Universe::new().create_world();
+        let holder = world.insert((), vec![(0_u32,)])[0];
+
+        let mut queue = TaskQueue::default();
+        let handle = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+
+        queue.take(handle, holder, 0.0).unwrap();
+        assert!(!queue.is_available(handle));
+        assert_eq!(queue.reservation(handle).unwrap().holder, holder);
+
+        assert!(queue.reclaim(|other, _| other != handle).is_empty());
+        assert!(queue
+            .reclaim(|_, reservation| 100.0 - reservation.taken > TaskReservation::TIMEOUT)
+            .is_empty());
+
+        let reclaimed = queue.reclaim(|_, reservation| reservation.holder == holder);
+        assert_eq!(reclaimed.len(), 1);
+        assert!(queue.is_available(handle));
+        assert!(!queue.cancel(handle, holder));
+    }
+
+    #[test]
+    fn is_working_on_test() {
+        let mut world = Universe::new().create_world();
+        let owner = world.insert((), vec![(0_u32,)])[0];
+        let holder = world.insert((), vec![(BlackboardComponent::default(),)])[0];
+
+        let mut queue = TaskQueue::default();
+        let handle = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+        let other = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+        let task = queue.take(handle, holder, 0.0).unwrap();
+
+        // Holding a task lasts as long as the holder's behavior keeps it
+        assert!(!is_working_on(&world, holder, owner, handle));
+        world
+            .get_component_mut::<BlackboardComponent>(holder)
+            .unwrap()
+            .insert(fnv!("current_task"), (owner, handle, task));
+        assert!(is_working_on(&world, holder, owner, handle));
+        assert!(!is_working_on(&world, holder, owner, other));
+        assert!(!is_working_on(&world, holder, holder, handle));
+        assert!(!is_working_on(&world, owner, owner, handle));
+    }
+
+    #[test]
+    fn release_held_tasks_test() {
+        let mut world = Universe::new().create_world();
+        let holder = world.insert((), vec![(0_u32,)])[0];
+        let other = world.insert((), vec![(0_u32,)])[0];
+
+        let mut queue = TaskQueue::default();
+        let held = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+        let kept = queue.insert(Task::new(5, LaborDefinitionId(0), Default::default()));
+        queue.take(held, holder, 0.0).unwrap();
+        queue.take(kept, other, 0.0).unwrap();
+
+        let tasks = HasTasksComponent::from(queue);
+        let owner = world.insert((), vec![(tasks.clone(),)])[0];
+
+        let mut channel = Channel::<TaskReservationEvent>::default();
+        let listener = channel.bind_listener(8);
+        release_held_tasks(&world, &channel, holder);
+
+        assert!(tasks.storage.get().is_available(held));
+        assert!(!tasks.storage.get().is_available(kept));
+
+        let event = channel.read(listener).unwrap();
+        assert_eq!(event.entity, owner);
+        assert_eq!(event.handle, held);
+        assert_eq!(event.change, TaskReservationChange::Reclaimed);
+        assert!(channel.read(listener).is_none());
+    }
 }
@@ -3,6 +3,7 @@ use crate::{
         BehaviorArgs, BehaviorHandle, BehaviorRoot, BehaviorStatus, BehaviorStorage,
         BehaviorTreeComponent,
     },
+    history::{DecisionHistoryComponent, DecisionRecord, ScoringRound},
     iaus::{
         decisions::{DecisionHandle, DecisionStorage},
         Decision,
@@ -52,13 +53,30 @@ impl UtilityStateComponent {
 }
 
 pub struct UtilityState<'a> {
+    pub entity: Entity,
+    pub state: GameStateRef<'a>,
+    pub time: &'a Time,
     pub map: &'a Map,
     pub senses: &'a SensesComponent,
     pub needs: &'a NeedsComponent,
 }
 impl<'a> UtilityState<'a> {
-    pub fn new(map: &'a Map, senses: &'a SensesComponent, needs: &'a NeedsComponent) -> Self {
-        Self { map, senses, needs }
+    pub fn new(
+        entity: Entity,
+        state: GameStateRef<'a>,
+        time: &'a Time,
+        map: &'a Map,
+        senses: &'a SensesComponent,
+        needs: &'a NeedsComponent,
+    ) -> Self {
+        Self {
+            entity,
+            state,
+            time,
+            map,
+            senses,
+            needs,
+        }
     }
 }
 
@@ -147,16 +165,41 @@ pub fn build_scoring_system(
                     unsafe { world.get_component_mut_unchecked::<BlackboardComponent>(entity) }
                         .unwrap();
 
+                let history = unsafe {
+                    world.get_component_mut_unchecked::<DecisionHistoryComponent>(entity)
+                };
+                let mut round = history.as_ref().map(|_| ScoringRound::new(time.world_time));
+
                 utility.available.iter_mut().for_each(|entry| {
                     if time.world_time - entry.last_tick > entry.frequency {
                         entry.last_tick = time.world_time;
-                        entry.last_score = decision_storage
-                            .get(entry.decision)
-                            .unwrap()
-                            .score(&UtilityState::new(&map, &senses, &needs));
+
+                        let decision = decision_storage.get(entry.decision).unwrap();
+                        let state = UtilityState::new(
+                            entity,
+                            GameStateRef { world, resources },
+                            &time,
+                            &map,
+                            &senses,
+                            &needs,
+                        );
+
+                        entry.last_score = if let Some(round) = round.as_mut() {
+                            let (score, considerations) = decision.evaluate(&state);
+                            round.decisions.push(DecisionRecord {
+                                decision: entry.decision,
+                                score,
+                                considerations,
+                            });
+                            score
+                        } else {
+                            decision.score(&state)
+                        };
                     }
                 });
 
+                let mut status = behavior_tree.last_status;
+
                 // If a behavior is currently running and not cancellable, we should continue
                 if (!behavior_tree.root.is_none()
                     && behavior_tree.last_status.is_cancellable()
@@ -198,6 +241,7 @@ pub fn build_scoring_system(
 
                                 if result == BehaviorStatus::running(false) {
                                     current = n.0;
+                                    status = result;
                                     behavior_tree.root = choice
                                         .behavior
                                         .map_or(BehaviorRoot::None, BehaviorRoot::Decision);
@@ -213,6 +257,16 @@ pub fn build_scoring_system(
 
                     sorted_cache.clear();
                 }
+
+                // Only keep rounds where something was rescored
+                if let (Some(mut history), Some(mut round)) = (history, round) {
+                    if !round.decisions.is_empty() {
+                        round.chosen = Some(utility.current().decision);
+                        round.behavior = behavior_tree.root.handle();
+                        round.status = status;
+                        history.push(round);
+                    }
+                }
             }
             command_buffer.write(world)
         }
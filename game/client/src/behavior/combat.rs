@@ -0,0 +1,157 @@
+use rl_ai::bt::{definition::BehaviorNodeRegistry, make};
+
+/// Distance in tiles a retreating entity tries to put between itself and its enemy
+pub const RETREAT_DISTANCE: i32 = 10;
+
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register("find_enemy", make::closure(None, nodes::find_enemy));
+    registry.register("melee_attack", make::closure(None, nodes::melee_attack));
+    registry.register("find_retreat", make::closure(None, nodes::find_retreat));
+}
+
+pub mod nodes {
+    use super::*;
+    use rl_ai::{
+        bt::*,
+        combat::{self, CombatComponent, CombatEvent},
+    };
+    use rl_core::{
+        components::{DeadTag, PositionComponent},
+        data::bt::*,
+        defs::{creature::CreatureDefinition, DefinitionStorage},
+        event::Channel,
+        fnv,
+        legion::prelude::*,
+        map::Map,
+        rand::Rng,
+        time::Time,
+        GameStateRef, Random,
+    };
+
+    /// Target the nearest perceived enemy, chasing it if it is a new target
+    pub fn find_enemy(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (creatures, time) =
+            <(Read<DefinitionStorage<CreatureDefinition>>, Read<Time>)>::fetch(state.resources);
+
+        let enemy = match combat::nearest_enemy(
+            state.world,
+            &creatures,
+            time.world_time,
+            args.entity,
+            args.senses,
+        ) {
+            Some((enemy, _)) => enemy,
+            None => return BehaviorStatus::failure(),
+        };
+
+        if args.blackboard.get::<Entity>(fnv!("CombatTarget")) != Some(&enemy) {
+            args.blackboard.insert(fnv!("CombatTarget"), enemy);
+            args.blackboard
+                .insert(fnv!("MoveParameters"), MoveParameters::new_entity(enemy));
+        }
+
+        BehaviorStatus::success()
+    }
+
+    /// Strike the target whenever we are ready while it stays in reach, failing once it moves
+    /// away so we chase it again.
+    pub fn melee_attack(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let target = match args.blackboard.get::<Entity>(fnv!("CombatTarget")).copied() {
+            Some(target) => target,
+            None => return BehaviorStatus::failure(),
+        };
+
+        if !state.world.is_alive(target) || state.world.get_tag::<DeadTag>(target).is_some() {
+            args.blackboard.remove(fnv!("CombatTarget"));
+            return BehaviorStatus::success();
+        }
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+        let in_reach = state
+            .world
+            .get_component::<PositionComponent>(target)
+            .map_or(false, |target_position| {
+                combat::in_reach(position, **target_position)
+            });
+        if !in_reach {
+            return BehaviorStatus::failure();
+        }
+
+        let now = state.resources.get::<Time>().unwrap().world_time;
+        let mut component = match unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<CombatComponent>(args.entity)
+        } {
+            Some(component) => component,
+            None => return BehaviorStatus::failure(),
+        };
+        if now < component.next_attack {
+            return BehaviorStatus::running(true);
+        }
+
+        let attacks = combat::available_attacks(state.world, state.resources, args.entity);
+        if attacks.is_empty() {
+            return BehaviorStatus::failure();
+        }
+
+        let mut rng = state.resources.get::<Random>().unwrap().make();
+        let attack = attacks[rng.gen_range(0, attacks.len())];
+        let event = combat::roll_attack(
+            &mut rng,
+            state.world,
+            state.resources,
+            args.entity,
+            target,
+            attack,
+        );
+
+        state
+            .resources
+            .get::<Channel<CombatEvent>>()
+            .unwrap()
+            .write(event)
+            .unwrap();
+
+        component.next_attack = now + combat::attack_interval(state.world, args.entity);
+
+        BehaviorStatus::running(true)
+    }
+
+    /// Move directly away from the nearest enemy
+    pub fn find_retreat(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (creatures, time, map) = <(
+            Read<DefinitionStorage<CreatureDefinition>>,
+            Read<Time>,
+            Read<Map>,
+        )>::fetch(state.resources);
+
+        let enemy = match combat::nearest_enemy(
+            state.world,
+            &creatures,
+            time.world_time,
+            args.entity,
+            args.senses,
+        ) {
+            Some((_, position)) => position,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        match rl_ai::pathfinding::step_away(&map, position, enemy, RETREAT_DISTANCE) {
+            Some(target) => {
+                args.blackboard
+                    .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+                BehaviorStatus::success()
+            }
+            None => BehaviorStatus::failure(),
+        }
+    }
+}
@@ -1,36 +1,55 @@
-use super::nodes as general_nodes;
 use crate::behavior::ExecuteReactionParameters;
-use rl_ai::bt::{self, make, BehaviorNode, BehaviorStatus};
+use rl_ai::{
+    bt::{definition::BehaviorNodeRegistry, make, BehaviorNode, BehaviorStatus},
+    combat,
+};
 use rl_core::{
-    components::PositionComponent,
+    components::{NeedsComponent, PositionComponent},
     data::bt::*,
     defs::{
+        creature::{self, CreatureComponent, CreatureDefinition, HomeComponent},
         foliage::{FoliageComponent, FoliageDefinition},
-        needs::NeedKind,
+        material::MaterialDefinition,
+        needs::{get_tile_nutrition_value, NeedKind},
         reaction::ReactionDefinition,
         DefinitionComponent, DefinitionStorage,
     },
-    failure, fnv,
+    fnv,
     legion::prelude::*,
-    map::spatial::StaticSpatialMap,
-    GameStateRef,
+    map::{spatial::StaticSpatialMap, Map},
+    math::Vec3i,
+    rand::Rng,
+    time::Time,
+    GameStateRef, Random,
 };
 use rl_reaction::ReactionEntity;
 use std::sync::Arc;
 
-pub fn build(storage: &mut bt::BehaviorStorage) -> Result<(), failure::Error> {
-    let try_graze = make::sequence(&[
-        make::selector(&[
-            nodes::make_try_find_nearest_consumable_foliage(NeedKind::Calories),
-            nodes::make_try_find_nearest_consumable_tile(NeedKind::Calories),
-        ]),
-        make::closure(None, general_nodes::move_to),
-        nodes::make_consume_target(),
-        make::closure(None, general_nodes::execute_reaction),
-    ]);
-    storage.insert("try_graze", try_graze);
-
-    Ok(())
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register(
+        "find_foliage_food",
+        nodes::make_try_find_nearest_consumable_foliage(NeedKind::Calories),
+    );
+    registry.register(
+        "find_food_tile",
+        nodes::make_try_find_nearest_consumable_tile(NeedKind::Calories),
+    );
+    registry.register(
+        "find_water_tile",
+        nodes::make_try_find_nearest_consumable_tile(NeedKind::Hydration),
+    );
+    registry.register("consume_target", nodes::make_consume_target());
+    registry.register("consume_tile", make::closure(None, nodes::consume_tile));
+
+    registry.register(
+        "find_wander_target",
+        make::closure(None, nodes::find_wander_target),
+    );
+    registry.register("find_herd", make::closure(None, nodes::find_herd));
+    registry.register(
+        "find_flee_target",
+        make::closure(None, nodes::find_flee_target),
+    );
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -39,8 +58,31 @@ struct CreatureConsumeParameters {
     kind: NeedKind,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct CreatureConsumeTileParameters {
+    tile: Vec3i,
+    kind: NeedKind,
+}
+
+fn distance_2(a: Vec3i, b: Vec3i) -> i32 {
+    let d = a - b;
+    d.x * d.x + d.y * d.y + d.z * d.z
+}
+
+fn behavior_of<'a>(
+    state: GameStateRef,
+    creatures: &'a DefinitionStorage<CreatureDefinition>,
+    entity: Entity,
+) -> Option<&'a creature::CreatureBehavior> {
+    state
+        .world
+        .get_component::<CreatureComponent>(entity)
+        .map(|creature| &creature.fetch(creatures).behavior)
+}
+
 pub mod nodes {
     use super::*;
+    use rl_ai::bt::BehaviorArgs;
 
     pub fn make_consume_target() -> Arc<dyn BehaviorNode> {
         make::closure(None, |state, args| {
@@ -73,8 +115,215 @@ pub mod nodes {
         })
     }
 
-    pub fn make_try_find_nearest_consumable_tile(_: NeedKind) -> Arc<dyn BehaviorNode> {
-        make::closure(None, |_, _| BehaviorStatus::failure())
+    /// Nearest liquid tile with nutrition of `kind`, moving next to it if it can't be stood in
+    pub fn make_try_find_nearest_consumable_tile(kind: NeedKind) -> Arc<dyn BehaviorNode> {
+        const RANGE: i32 = 32;
+
+        make::closure(None, move |state, args| {
+            // A tile found for another need, such as water while grazing, is stale
+            match args
+                .blackboard
+                .get::<CreatureConsumeTileParameters>(fnv!("CreatureConsumeTileParameters"))
+                .map(|parameters| parameters.kind)
+            {
+                Some(stored) if stored == kind => return BehaviorStatus::success(),
+                Some(_) => {
+                    args.blackboard
+                        .remove(fnv!("CreatureConsumeTileParameters"));
+                }
+                None => {}
+            }
+
+            let (map, materials) =
+                <(Read<Map>, Read<DefinitionStorage<MaterialDefinition>>)>::fetch(&state.resources);
+
+            let position = **state
+                .world
+                .get_component::<PositionComponent>(args.entity)
+                .unwrap();
+
+            let mut candidates = map
+                .has_liquid
+                .read()
+                .iter()
+                .map(|index| map.encoder().decode(*index))
+                .filter(|tile| distance_2(*tile, position) <= RANGE * RANGE)
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|tile| distance_2(*tile, position));
+
+            for tile in candidates {
+                let provides = get_tile_nutrition_value(&map, &materials, tile, kind)
+                    .map_or(false, |nutrition| nutrition.start > 0);
+                if !provides {
+                    continue;
+                }
+
+                let stand = if map.get(tile).is_walkable() {
+                    Some(tile)
+                } else {
+                    map.neighbors(&tile)
+                        .into_iter()
+                        .filter(|neighbor| map.get(*neighbor).is_walkable())
+                        .min_by_key(|neighbor| distance_2(*neighbor, position))
+                };
+
+                if let Some(stand) = stand {
+                    args.blackboard.insert(
+                        fnv!("CreatureConsumeTileParameters"),
+                        CreatureConsumeTileParameters { tile, kind },
+                    );
+                    args.blackboard
+                        .insert(fnv!("MoveParameters"), MoveParameters::new_tile(stand));
+
+                    return BehaviorStatus::success();
+                }
+            }
+
+            BehaviorStatus::failure()
+        })
+    }
+
+    /// Consume from the tile we found, which may have dried up on the way
+    pub fn consume_tile(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let parameters = match args
+            .blackboard
+            .remove_get::<CreatureConsumeTileParameters>(fnv!("CreatureConsumeTileParameters"))
+        {
+            Some(parameters) => parameters,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let (map, materials) =
+            <(Read<Map>, Read<DefinitionStorage<MaterialDefinition>>)>::fetch(&state.resources);
+
+        let value =
+            match get_tile_nutrition_value(&map, &materials, parameters.tile, parameters.kind) {
+                Some(nutrition) if nutrition.start > 0 => nutrition.start,
+                _ => return BehaviorStatus::failure(),
+            };
+
+        let mut needs = unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<NeedsComponent>(args.entity)
+        }
+        .unwrap();
+        needs.add(parameters.kind, value);
+
+        BehaviorStatus::success()
+    }
+
+    /// A random walkable tile within our home range, or near where we are without a home
+    pub fn find_wander_target(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        const ATTEMPTS: usize = 8;
+
+        let (map, creatures) =
+            <(Read<Map>, Read<DefinitionStorage<CreatureDefinition>>)>::fetch(&state.resources);
+        let mut rng = state.resources.get::<Random>().unwrap().make();
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+        let home = state
+            .world
+            .get_component::<HomeComponent>(args.entity)
+            .map_or(position, |home| home.position);
+        let radius = behavior_of(state, &creatures, args.entity)
+            .and_then(|behavior| behavior.home_range)
+            .unwrap_or(crate::behavior::needs::EXPLORE_RADIUS)
+            .max(1);
+        let dimensions = map.dimensions();
+
+        for _ in 0..ATTEMPTS {
+            let target = Vec3i::new(
+                (home.x + rng.gen_range(-radius, radius + 1))
+                    .max(0)
+                    .min(dimensions.x - 1),
+                (home.y + rng.gen_range(-radius, radius + 1))
+                    .max(0)
+                    .min(dimensions.y - 1),
+                home.z,
+            );
+
+            if target != position && map.get(target).is_walkable() {
+                args.blackboard
+                    .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+                return BehaviorStatus::success();
+            }
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    /// Head for the nearest herd mate when we have strayed further than our herd range
+    pub fn find_herd(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (map, creatures) =
+            <(Read<Map>, Read<DefinitionStorage<CreatureDefinition>>)>::fetch(&state.resources);
+
+        let range = match behavior_of(state, &creatures, args.entity)
+            .and_then(|behavior| behavior.herd_range)
+        {
+            Some(range) => range,
+            None => return BehaviorStatus::failure(),
+        };
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let mate = match creature::nearest_herd_mate(state.world, args.entity) {
+            Some((_, mate)) if distance_2(mate, position) > range * range => mate,
+            _ => return BehaviorStatus::failure(),
+        };
+
+        // The mate itself is in the way, stand beside it
+        let target = map
+            .neighbors(&mate)
+            .into_iter()
+            .filter(|neighbor| map.get(*neighbor).is_walkable())
+            .min_by_key(|neighbor| distance_2(*neighbor, position))
+            .unwrap_or(mate);
+
+        args.blackboard
+            .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+
+        BehaviorStatus::success()
+    }
+
+    /// Run directly away from the nearest perceived threat
+    pub fn find_flee_target(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (map, creatures) =
+            <(Read<Map>, Read<DefinitionStorage<CreatureDefinition>>)>::fetch(&state.resources);
+
+        let distance = match behavior_of(state, &creatures, args.entity)
+            .and_then(|behavior| behavior.flee_distance)
+        {
+            Some(distance) => distance,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let now = state.resources.get::<Time>().unwrap().world_time;
+        let threat = match args.senses.nearest(|percept| {
+            combat::is_threat(state.world, &creatures, now, args.entity, percept.entity)
+        }) {
+            Some(threat) => threat.position,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        match rl_ai::pathfinding::step_away(&map, position, threat, distance) {
+            Some(target) => {
+                args.blackboard
+                    .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+                BehaviorStatus::success()
+            }
+            None => BehaviorStatus::failure(),
+        }
     }
 
     pub fn make_try_find_nearest_consumable_foliage(kind: NeedKind) -> Arc<dyn BehaviorNode> {
@@ -1,65 +1,336 @@
-use super::nodes as general_nodes;
-use rl_ai::bt::{self, make, BehaviorStatus};
-use rl_core::failure;
-
-pub fn build(storage: &mut bt::BehaviorStorage) -> Result<(), failure::Error> {
-    storage.insert(
-        "pickup_item",
-        make::selector(&[
-            make::closure(None, general_nodes::has_item),
-            make::sequence(&[
-                make::closure(None, nodes::can_pickup),
-                make::closure(None, nodes::designate_pickup),
-                make::if_else(
-                    make::closure(None, general_nodes::move_to),
-                    make::closure(None, nodes::do_pickup),
-                    make::closure(None, nodes::cancel_pickup),
-                ),
-            ]),
-        ]),
-    );
+use rl_ai::bt::{definition::BehaviorNodeRegistry, make, BehaviorStatus};
 
-    let haul_item_to_target = make::sequence(&[
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register("can_pickup", make::closure(None, nodes::can_pickup));
+    registry.register(
+        "designate_pickup",
+        make::closure(None, nodes::designate_pickup),
+    );
+    registry.register("do_pickup", make::closure(None, nodes::do_pickup));
+    registry.register("cancel_pickup", make::closure(None, nodes::cancel_pickup));
+    registry.register(
+        "prepare_haul_parameters",
         make::closure(None, nodes::prepare_haul_parameters),
-        make::sub("pickup_item", &storage),
+    );
+    registry.register(
+        "prepare_haul_movement_target",
         make::closure(None, nodes::prepare_haul_movement_target),
-        make::closure(None, general_nodes::move_to),
-        make::closure(None, nodes::do_drop),
+    );
+    registry.register("do_drop", make::closure(None, nodes::do_drop));
+    registry.register(
+        "make_item_stockpile_child",
         make::closure(None, nodes::make_item_stockpile_child),
-    ]);
-    storage.insert("haul_item_to_target", haul_item_to_target);
-
-    let do_haul = make::sequence(&[
+    );
+    registry.register(
+        "find_item_for_stockpile",
         make::closure(None, nodes::find_item_for_stockpile),
-        make::sub("haul_item_to_target", &storage),
-    ]);
-    storage.insert("do_haul", do_haul);
-
-    Ok(())
+    );
+    registry.register(
+        "find_haul_batch",
+        make::closure(None, nodes::find_haul_batch),
+    );
+    registry.register(
+        "prepare_haul_container",
+        make::closure(None, nodes::prepare_haul_container),
+    );
+    registry.register(
+        "prepare_batch_pickup",
+        make::closure(None, nodes::prepare_batch_pickup),
+    );
+    registry.register(
+        "abandon_batch_pickup",
+        make::closure(None, nodes::abandon_batch_pickup),
+    );
+    registry.register(
+        "prepare_batch_drop",
+        make::closure(None, nodes::prepare_batch_drop),
+    );
+    registry.register(
+        "prepare_drop_haul_container",
+        make::closure(None, nodes::prepare_drop_haul_container),
+    );
+    registry.register(
+        "clear_haul_batch",
+        make::closure(None, nodes::clear_haul_batch),
+    );
 }
 
 pub mod nodes {
     use super::*;
-    use rl_ai::bt::*;
+    use rl_ai::{bt::*, task::TaskCache};
     use rl_core::defs::{
+        body::{BodyComponent, BodyDefinition},
         item::{
-            ItemComponent, ItemDefinition, StockpileComponent, StockpileItemChildComponent,
-            StockpileSpatialMap,
+            ItemComponent, ItemDefinition, ItemExtensionKind, StockpileComponent,
+            StockpileItemChildComponent, StockpilePriority,
         },
+        material::MaterialDefinition,
         DefinitionComponent, DefinitionStorage,
     };
     use rl_core::{
         components::{
-            ActivePickupComponent, CarryComponent, ItemContainerChildComponent,
+            ActivePickupComponent, CarryComponent, Destroy, ItemContainerChildComponent,
             ItemContainerComponent, PositionComponent,
         },
-        data::bt::*,
+        data::{bt::*, DimensionsVec, PartGraphId},
         fnv,
+        inventory::{self, compare_volumes},
         legion::prelude::*,
-        map::spatial::SpatialMap,
+        map::spatial::{SpatialMap, SpatialMapEntry},
+        math::Vec3i,
+        smallvec::SmallVec,
         time::Time,
         GameStateRef,
     };
+    use std::cmp::Ordering;
+
+    /// How many of the nearest loose or stockpiled items are weighed against every stockpile
+    const HAUL_CANDIDATES: usize = 32;
+
+    fn distance_squared(a: Vec3i, b: Vec3i) -> i32 {
+        let d = b - a;
+        d.x * d.x + d.y * d.y + d.z * d.z
+    }
+
+    /// An empty limb `entity` can still pick something up with
+    fn free_limb(state: GameStateRef, entity: Entity) -> Option<PartGraphId> {
+        let carry = state.world.get_component::<CarryComponent>(entity)?;
+        let body = state.world.get_component::<BodyComponent>(entity);
+        let bodies = state
+            .resources
+            .get::<DefinitionStorage<BodyDefinition>>()
+            .unwrap();
+
+        inventory::free_limb(
+            &carry,
+            body.as_ref().map(|body| (&**body, body.fetch(&bodies))),
+        )
+    }
+
+    /// Whether `entity` has a working hand left to pick anything up with, even into its bag.
+    /// Bodiless carriers always do.
+    fn has_hands(state: GameStateRef, entity: Entity) -> bool {
+        let body = match state.world.get_component::<BodyComponent>(entity) {
+            Some(body) => body,
+            None => return true,
+        };
+        let bodies = state
+            .resources
+            .get::<DefinitionStorage<BodyDefinition>>()
+            .unwrap();
+
+        inventory::can_carry(&body, body.fetch(&bodies))
+    }
+
+    /// Items nobody is holding or picking up, leaving those brought to a workshop or other task
+    /// location for its reaction where they are
+    fn is_haulable(state: GameStateRef, entry: &SpatialMapEntry) -> bool {
+        let world = state.world;
+
+        world.has_component::<ItemComponent>(entry.entity)
+            && !world.has_component::<ItemContainerChildComponent>(entry.entity)
+            && !world.has_component::<ActivePickupComponent>(entry.entity)
+            && !state
+                .resources
+                .get::<TaskCache>()
+                .map_or(false, |tasks| tasks.has_tasks_at(entry.position()))
+    }
+
+    /// Picks the item and stockpile pair to haul. The highest priority stockpile wins, then the
+    /// shortest trip from `source_position` to the item and on to the stockpile. Stockpiled items
+    /// are only moved into a stockpile of a higher priority, or out of one which no longer accepts them.
+    #[allow(clippy::too_many_lines)]
+    fn find_best_haul(
+        state: GameStateRef,
+        source_position: Vec3i,
+    ) -> Option<(Entity, Entity, Vec3i)> {
+        let (spatial_map, items, materials, time) = <(
+            Read<SpatialMap>,
+            Read<DefinitionStorage<ItemDefinition>>,
+            Read<DefinitionStorage<MaterialDefinition>>,
+            Read<Time>,
+        )>::fetch(state.resources);
+
+        let stockpiles = <Read<StockpileComponent>>::query()
+            .filter(!component::<Destroy>())
+            .iter_entities(state.world)
+            .filter(|(_, stockpile)| stockpile.tiles.has_free())
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        let top_priority = match stockpiles
+            .iter()
+            .filter_map(|entity| state.world.get_component::<StockpileComponent>(*entity))
+            .map(|stockpile| stockpile.priority)
+            .max()
+        {
+            Some(priority) => priority,
+            None => return None,
+        };
+
+        // The stockpile an item sits in, if it still accepts the item
+        let stockpiled_in = |entity: Entity| {
+            let child = state
+                .world
+                .get_component::<StockpileItemChildComponent>(entity)?;
+            let item = state
+                .world
+                .get_component::<ItemComponent>(entity)?
+                .fetch(&items);
+            state
+                .world
+                .get_component::<StockpileComponent>(child.parent)
+                .filter(|parent| {
+                    parent
+                        .filter
+                        .accepts(state.world, entity, item, &materials, time.world_time)
+                })
+                .map(|parent| (child.parent, parent.priority))
+        };
+
+        let mut best: Option<(StockpilePriority, f32, Entity, Entity, Vec3i)> = None;
+
+        // Items already in a stockpile nothing free outranks are settled, and left out before
+        // they can use up the candidates
+        for item_entry in spatial_map
+            .nearest_neighbor_iter(&PositionComponent::new(source_position))
+            .filter(|entry| {
+                is_haulable(state, entry)
+                    && stockpiled_in(entry.entity)
+                        .map_or(true, |(_, priority)| priority < top_priority)
+            })
+            .take(HAUL_CANDIDATES)
+        {
+            let item = state
+                .world
+                .get_component::<ItemComponent>(item_entry.entity)
+                .unwrap()
+                .fetch(&items);
+            let item_position = **state
+                .world
+                .get_component::<PositionComponent>(item_entry.entity)
+                .unwrap();
+            let accepts = |stockpile: &StockpileComponent| {
+                stockpile.filter.accepts(
+                    state.world,
+                    item_entry.entity,
+                    item,
+                    &materials,
+                    time.world_time,
+                )
+            };
+
+            // An item stays put unless a better stockpile wants it, or its own no longer does
+            let current = stockpiled_in(item_entry.entity);
+
+            for stockpile_entity in &stockpiles {
+                let stockpile = state
+                    .world
+                    .get_component::<StockpileComponent>(*stockpile_entity)
+                    .unwrap();
+
+                if let Some((parent, priority)) = current {
+                    if parent == *stockpile_entity || stockpile.priority <= priority {
+                        continue;
+                    }
+                }
+                if !accepts(&*stockpile) {
+                    continue;
+                }
+                if let Some((best_priority, ..)) = best {
+                    if stockpile.priority < best_priority {
+                        continue;
+                    }
+                }
+
+                let tile = stockpile
+                    .tiles
+                    .iter_free()
+                    .min_by_key(|tile| distance_squared(item_position, **tile));
+                if let Some(tile) = tile {
+                    let distance = (distance_squared(source_position, item_position) as f32).sqrt()
+                        + (distance_squared(item_position, *tile) as f32).sqrt();
+
+                    let better = best.map_or(true, |(best_priority, best_distance, ..)| {
+                        stockpile.priority > best_priority || distance < best_distance
+                    });
+                    if better {
+                        best = Some((
+                            stockpile.priority,
+                            distance,
+                            item_entry.entity,
+                            *stockpile_entity,
+                            *tile,
+                        ));
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, _, item, stockpile, tile)| (item, stockpile, tile))
+    }
+
+    /// Frees the stockpile tile `item` sits on, if it is stockpiled
+    fn leave_stockpile(state: GameStateRef, command_buffer: &mut CommandBuffer, item: Entity) {
+        if let Some(child) = state
+            .world
+            .get_component::<StockpileItemChildComponent>(item)
+            .map(|child| *child)
+        {
+            let item_position = **state
+                .world
+                .get_component::<PositionComponent>(item)
+                .unwrap();
+            if let Some(mut parent) = unsafe {
+                state
+                    .world
+                    .get_component_mut_unchecked::<StockpileComponent>(child.parent)
+            } {
+                parent.tiles.push(item_position).ok();
+            }
+            command_buffer.remove_component::<StockpileItemChildComponent>(item);
+        }
+    }
+
+    /// Reserves `tile` in the stockpile for an item on its way. A stockpiled item keeps its own
+    /// tile until it is picked up.
+    fn reserve_haul(state: GameStateRef, stockpile: Entity, tile: Vec3i) {
+        unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<StockpileComponent>(stockpile)
+                .unwrap()
+        }
+        .tiles
+        .take(&tile);
+    }
+
+    /// Returns a reserved tile which will not be used after all
+    fn release_haul(state: GameStateRef, stockpile: Entity, tile: Vec3i) {
+        if let Some(mut stockpile) = unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<StockpileComponent>(stockpile)
+        } {
+            stockpile.tiles.push(tile).ok();
+        }
+    }
+
+    /// Returns the tiles of batch items which never made it onto them
+    fn release_unplaced(state: GameStateRef, batch: &HaulBatch) {
+        for (item, tile) in &batch.items {
+            let placed = state
+                .world
+                .get_component::<StockpileItemChildComponent>(*item)
+                .map_or(false, |child| child.parent == batch.stockpile)
+                || state
+                    .world
+                    .get_component::<PositionComponent>(*item)
+                    .map_or(false, |position| **position == *tile);
+            if !placed {
+                release_haul(state, batch.stockpile, *tile);
+            }
+        }
+    }
 
     pub fn find_item_for_stockpile(
         state: GameStateRef,
@@ -67,89 +338,424 @@ pub mod nodes {
     ) -> BehaviorStatus {
         if args.blackboard.contains(fnv!("HaulParameters")) {
             return BehaviorStatus::success();
-        } else {
-            let (spatial_map, stockpile_map, items) = <(
+        }
+
+        let source_position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        if let Some((item, stockpile, target_tile)) = find_best_haul(state, source_position) {
+            reserve_haul(state, stockpile, target_tile);
+
+            args.blackboard.insert(
+                fnv!("HaulParameters"),
+                HaulParameters {
+                    stockpile,
+                    item,
+                    target_tile,
+                },
+            );
+            args.blackboard.insert(
+                fnv!("PickupParameters"),
+                PickupParameters {
+                    target: item,
+                    destination: None,
+                },
+            );
+
+            return BehaviorStatus::success();
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    /// The nearest empty container we could haul with, and its capacity
+    fn find_haul_container(
+        state: GameStateRef,
+        source_position: Vec3i,
+    ) -> Option<(Entity, DimensionsVec)> {
+        let (spatial_map, items) =
+            <(Read<SpatialMap>, Read<DefinitionStorage<ItemDefinition>>)>::fetch(state.resources);
+
+        spatial_map
+            .nearest_neighbor_iter(&PositionComponent::new(source_position))
+            .filter(|entry| is_haulable(state, entry))
+            .find_map(|entry| {
+                let item = state
+                    .world
+                    .get_component::<ItemComponent>(entry.entity)?
+                    .fetch(&items);
+                if !item.has_extension(ItemExtensionKind::Container) {
+                    return None;
+                }
+
+                state
+                    .world
+                    .get_component::<ItemContainerComponent>(entry.entity)
+                    .filter(|container| {
+                        container.inside.is_empty() && container.queued_inside.is_empty()
+                    })
+                    .map(|container| (entry.entity, container.capacity))
+            })
+    }
+
+    /// Plans a batch: the best haul, plus loose items near it which the same stockpile accepts,
+    /// for as much as the nearest free container holds. Fails unless at least two items fit.
+    #[allow(clippy::too_many_lines)]
+    pub fn find_haul_batch(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        if args.blackboard.contains(fnv!("HaulBatch")) {
+            return BehaviorStatus::success();
+        }
+        // A single item haul is already under way
+        if args.blackboard.contains(fnv!("HaulParameters")) {
+            return BehaviorStatus::failure();
+        }
+
+        // A free hand to carry the container in
+        if free_limb(state, args.entity).is_none() {
+            return BehaviorStatus::failure();
+        }
+
+        let source_position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let (container, capacity) = match find_haul_container(state, source_position) {
+            Some(found) => found,
+            None => return BehaviorStatus::failure(),
+        };
+        // The container itself is left to be hauled on its own
+        let (first, stockpile_entity, first_tile) = match find_best_haul(state, source_position) {
+            Some(found) if found.0 != container => found,
+            _ => return BehaviorStatus::failure(),
+        };
+
+        let batch = {
+            let (spatial_map, items, materials, time) = <(
                 Read<SpatialMap>,
-                Read<StockpileSpatialMap>,
                 Read<DefinitionStorage<ItemDefinition>>,
+                Read<DefinitionStorage<MaterialDefinition>>,
+                Read<Time>,
             )>::fetch(state.resources);
-            // Find the nearest item which also has an open stockpile slot
-            let source_position = state
+
+            let dimensions = |item: Entity| {
+                state
+                    .world
+                    .get_component::<ItemComponent>(item)
+                    .unwrap()
+                    .fetch(&items)
+                    .dimensions
+            };
+
+            let mut consumed = dimensions(first);
+            if compare_volumes(consumed, capacity) != Ordering::Less {
+                return BehaviorStatus::failure();
+            }
+
+            let stockpile = state
                 .world
-                .get_component::<PositionComponent>(args.entity)
+                .get_component::<StockpileComponent>(stockpile_entity)
                 .unwrap();
+            let mut tiles = stockpile
+                .tiles
+                .iter_free()
+                .filter(|tile| **tile != first_tile)
+                .copied()
+                .collect::<Vec<_>>();
 
-            // TODO: with distance somehow, find the bets mix?
-            for item_entry in spatial_map.nearest_neighbor_iter(&source_position) {
-                if let Some(item_component) = state
-                    .world
-                    .get_component::<ItemComponent>(item_entry.entity)
-                {
-                    // skip if its already in a stockpile, or is a child of someone else
-                    if state
-                        .world
-                        .has_component::<StockpileItemChildComponent>(item_entry.entity)
-                        || state
-                            .world
-                            .has_component::<ItemContainerChildComponent>(item_entry.entity)
-                        || state
-                            .world
-                            .has_component::<ActivePickupComponent>(item_entry.entity)
-                    {
-                        continue;
-                    }
+            let first_position = **state
+                .world
+                .get_component::<PositionComponent>(first)
+                .unwrap();
 
-                    let item = item_component.fetch(&items);
+            let mut batch: SmallVec<[(Entity, Vec3i); 8]> = SmallVec::new();
+            batch.push((first, first_tile));
 
-                    for stockpile_entry in stockpile_map.nearest_neighbor_iter(&source_position) {
-                        let mut stockpile = unsafe {
+            for entry in spatial_map
+                .nearest_neighbor_iter(&PositionComponent::new(first_position))
+                .filter(|entry| {
+                    entry.entity != first
+                        && entry.entity != container
+                        && is_haulable(state, entry)
+                        && !state
+                            .world
+                            .has_component::<StockpileItemChildComponent>(entry.entity)
+                        && stockpile.filter.accepts(
+                            state.world,
+                            entry.entity,
                             state
                                 .world
-                                .get_component_mut_unchecked::<StockpileComponent>(
-                                    stockpile_entry.entity,
-                                )
+                                .get_component::<ItemComponent>(entry.entity)
                                 .unwrap()
-                        };
-                        if stockpile.tiles.is_empty() {
-                            continue;
-                        }
+                                .fetch(&items),
+                            &materials,
+                            time.world_time,
+                        )
+                })
+                .take(HAUL_CANDIDATES)
+            {
+                if tiles.is_empty() {
+                    break;
+                }
 
-                        if stockpile.stores.contains(item.kind) {
-                            let item_position = state
-                                .world
-                                .get_component::<PositionComponent>(item_entry.entity)
-                                .unwrap();
-
-                            if let Some(target_tile) = stockpile.tiles.pop_nearest(&**item_position)
-                            {
-                                // Found a stockpile to store the item
-                                args.blackboard.insert(
-                                    fnv!("HaulParameters"),
-                                    HaulParameters {
-                                        stockpile: stockpile_entry.entity,
-                                        item: item_entry.entity,
-                                        target_tile,
-                                    },
-                                );
-                                args.blackboard.insert(
-                                    fnv!("PickupParameters"),
-                                    PickupParameters {
-                                        target: item_entry.entity,
-                                        destination: None,
-                                    },
-                                );
-
-                                return BehaviorStatus::success();
-                            }
-                        }
-                    }
+                let item = state
+                    .world
+                    .get_component::<ItemComponent>(entry.entity)
+                    .unwrap()
+                    .fetch(&items);
+                if compare_volumes(consumed + item.dimensions, capacity) != Ordering::Less {
+                    continue;
                 }
+
+                let item_position = **state
+                    .world
+                    .get_component::<PositionComponent>(entry.entity)
+                    .unwrap();
+                let (index, tile) = tiles
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, tile)| distance_squared(item_position, **tile))
+                    .map(|(index, tile)| (index, *tile))
+                    .unwrap();
+                tiles.swap_remove(index);
+
+                consumed += item.dimensions;
+                batch.push((entry.entity, tile));
+            }
+
+            batch
+        };
+
+        if batch.len() < 2 {
+            return BehaviorStatus::failure();
+        }
+
+        for (_, tile) in &batch {
+            reserve_haul(state, stockpile_entity, *tile);
+        }
+
+        args.blackboard.insert(
+            fnv!("HaulBatch"),
+            HaulBatch {
+                container,
+                stockpile: stockpile_entity,
+                items: batch,
+                unloading: false,
+            },
+        );
+
+        BehaviorStatus::success()
+    }
+
+    pub fn prepare_haul_container(
+        _state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        if let Some(container) = args
+            .blackboard
+            .get::<HaulBatch>(fnv!("HaulBatch"))
+            .map(|batch| batch.container)
+        {
+            args.blackboard.insert(fnv!("HasItem"), container);
+            args.blackboard
+                .insert(fnv!("PickupParameters"), PickupParameters::new(container));
+
+            return BehaviorStatus::success();
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    /// Picks the next batch item to load into the container, failing once everything is loaded.
+    /// Items someone else took in the meantime are dropped from the batch.
+    pub fn prepare_batch_pickup(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        let entity = args.entity;
+        let batch = match args.blackboard.get_mut::<HaulBatch>(fnv!("HaulBatch")) {
+            Some(batch) if !batch.unloading => batch,
+            _ => return BehaviorStatus::failure(),
+        };
+
+        let container = state
+            .world
+            .get_component::<ItemContainerComponent>(batch.container)
+            .unwrap();
+        let loaded = |item: Entity| {
+            container.inside.contains(&item) || container.queued_inside.contains(&item)
+        };
+        let stockpile = batch.stockpile;
+
+        batch.items.retain(|(item, tile)| {
+            let available = loaded(*item)
+                || (state.world.is_alive(*item)
+                    && !state
+                        .world
+                        .has_component::<ItemContainerChildComponent>(*item)
+                    && state
+                        .world
+                        .get_component::<ActivePickupComponent>(*item)
+                        .map_or(true, |pickup| pickup.initiator == entity));
+            if !available {
+                release_haul(state, stockpile, *tile);
+            }
+            available
+        });
+
+        let next = batch.items.iter().find(|(item, _)| !loaded(*item)).copied();
+        let (item, _) = match next {
+            Some(next) => next,
+            None => {
+                batch.unloading = true;
+                return BehaviorStatus::failure();
             }
+        };
+        let destination = PickupDestination::Container(batch.container);
+
+        args.blackboard.insert(fnv!("HasItem"), item);
+        args.blackboard.insert(
+            fnv!("PickupParameters"),
+            PickupParameters {
+                target: item,
+                destination: Some(destination),
+            },
+        );
+
+        BehaviorStatus::success()
+    }
+
+    /// Gives up on the batch item we failed to pick up, so loading moves on to the next one
+    pub fn abandon_batch_pickup(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        let target = match args
+            .blackboard
+            .get::<PickupParameters>(fnv!("PickupParameters"))
+        {
+            Some(parameters) => parameters.target,
+            None => return BehaviorStatus::failure(),
+        };
+
+        if let Some(batch) = args.blackboard.get_mut::<HaulBatch>(fnv!("HaulBatch")) {
+            if let Some(index) = batch.items.iter().position(|(item, _)| *item == target) {
+                let (_, tile) = batch.items.remove(index);
+                release_haul(state, batch.stockpile, tile);
+
+                return BehaviorStatus::success();
+            }
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    /// Picks the next batch item still in the container to unload on its stockpile tile
+    pub fn prepare_batch_drop(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let batch = match args.blackboard.get::<HaulBatch>(fnv!("HaulBatch")) {
+            Some(batch) => batch.clone(),
+            None => return BehaviorStatus::failure(),
+        };
+
+        let container = state
+            .world
+            .get_component::<ItemContainerComponent>(batch.container)
+            .unwrap();
+
+        // Wait for the container to take in the last items we put in it
+        if batch
+            .items
+            .iter()
+            .any(|(item, _)| container.queued_inside.contains(item))
+        {
+            return BehaviorStatus::running(false);
+        }
+
+        if let Some((item, target_tile)) = batch
+            .items
+            .iter()
+            .find(|(item, _)| container.inside.contains(item))
+            .copied()
+        {
+            args.blackboard.insert(
+                fnv!("HaulParameters"),
+                HaulParameters {
+                    stockpile: batch.stockpile,
+                    item,
+                    target_tile,
+                },
+            );
+            args.blackboard.insert(
+                fnv!("MoveParameters"),
+                MoveParameters::new_tile(target_tile),
+            );
+            args.blackboard.insert(
+                fnv!("DropParameters"),
+                DropParameters::with_target(item, Target::Tile(target_tile)),
+            );
+
+            return BehaviorStatus::success();
         }
 
         BehaviorStatus::failure()
     }
 
+    /// Sets the emptied container down where we stand, returning tiles of anything never loaded
+    pub fn prepare_drop_haul_container(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        let batch = match args.blackboard.get_mut::<HaulBatch>(fnv!("HaulBatch")) {
+            Some(batch) => batch,
+            None => return BehaviorStatus::failure(),
+        };
+
+        release_unplaced(state, batch);
+        batch.items.clear();
+        let container = batch.container;
+
+        args.blackboard
+            .insert(fnv!("DropParameters"), DropParameters::new(container));
+
+        BehaviorStatus::success()
+    }
+
+    /// Ends a batch, finished or not: returns the tiles of everything not yet stockpiled, sets
+    /// the container down if we still hold it and clears the batch. Always succeeds.
+    pub fn clear_haul_batch(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        if let Some(batch) = args.blackboard.remove_get::<HaulBatch>(fnv!("HaulBatch")) {
+            args.blackboard.remove(fnv!("HaulParameters"));
+            release_unplaced(state, &batch);
+
+            if inventory::remove_item(state.world, args.entity, batch.container) {
+                args.command_buffer
+                    .remove_component::<ItemContainerChildComponent>(batch.container);
+            }
+        }
+
+        BehaviorStatus::success()
+    }
+
+    /// Gives back the tile of a single haul which never made it to the stockpile. Always succeeds.
+    pub fn abandon_haul(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        if let Some(parameters) = args
+            .blackboard
+            .remove_get::<HaulParameters>(fnv!("HaulParameters"))
+        {
+            let placed = state
+                .world
+                .get_component::<StockpileItemChildComponent>(parameters.item)
+                .map_or(false, |child| child.parent == parameters.stockpile);
+            if !placed {
+                release_haul(state, parameters.stockpile, parameters.target_tile);
+            }
+        }
+
+        BehaviorStatus::success()
+    }
+
     pub fn make_item_stockpile_child(
         _state: GameStateRef,
         args: &mut BehaviorArgs<'_>,
@@ -236,6 +842,10 @@ pub mod nodes {
     }
 
     pub fn can_pickup(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        if !has_hands(state, args.entity) {
+            return BehaviorStatus::failure();
+        }
+
         if let Some(parameters) = args
             .blackboard
             .get_mut::<PickupParameters>(fnv!("PickupParameters"))
@@ -266,19 +876,30 @@ pub mod nodes {
                     }
                 }
 
+                // A container chosen up front, such as a hauling bin, only needs room for the item
+                if let Some(PickupDestination::Container(container)) = parameters.destination {
+                    if container != args.entity {
+                        return if state
+                            .world
+                            .get_component::<ItemContainerComponent>(container)
+                            .map_or(false, |container| {
+                                rl_core::inventory::can_contain_item(&container, item)
+                            }) {
+                            BehaviorStatus::success()
+                        } else {
+                            BehaviorStatus::failure()
+                        };
+                    }
+                }
+
                 // Do we have either the capacity in our bag, or a limb to pick it up?
                 // TODO: How do we pick which?
                 // Default bag for non weapon/tool unless no choice
                 // TODO: for now, we just carry everything first if we can
-                if let Some(carrying) = state.world.get_component::<CarryComponent>(args.entity) {
-                    // TODO: can carry check. For now we just check an emtpy limb
-                    for limb in &carrying.limbs {
-                        if limb.1.is_none() {
-                            parameters.destination = Some(PickupDestination::Carry(limb.0));
+                if let Some(limb) = free_limb(state, args.entity) {
+                    parameters.destination = Some(PickupDestination::Carry(limb));
 
-                            return BehaviorStatus::success();
-                        }
-                    }
+                    return BehaviorStatus::success();
                 }
 
                 if let Some(container) = state
@@ -366,23 +987,20 @@ pub mod nodes {
                     }
                 }
 
+                // Items put in a container belong to it, anything else to us
+                let parent = match pickup_dest {
+                    PickupDestination::Container(entity) => entity,
+                    PickupDestination::Carry(_) => args.entity,
+                };
+
+                // Taking it out of a stockpile frees its tile
+                leave_stockpile(state, args.command_buffer, parameters.target);
+
                 // Remove the sprite, add our child.
                 args.command_buffer
                     .remove_component::<rl_render_pod::sprite::Sprite>(parameters.target);
                 args.command_buffer
-                    .add_component::<ItemContainerChildComponent>(
-                        parameters.target,
-                        ItemContainerChildComponent {
-                            parent: args.entity,
-                        },
-                    );
-
-                args.command_buffer.add_component(
-                    parameters.target,
-                    ItemContainerChildComponent {
-                        parent: args.entity,
-                    },
-                );
+                    .add_component(parameters.target, ItemContainerChildComponent { parent });
             }
 
             // Make the target a child of the caller
@@ -421,20 +1039,32 @@ pub mod nodes {
             .blackboard
             .get::<DropParameters>(fnv!("DropParameters"))
         {
+            // Work out where a handed over item goes before letting go of it
+            let receiver = match parameters.target {
+                Some(Target::Entity(receiver)) => {
+                    match hand_over_destination(state, receiver, parameters.item) {
+                        Some(destination) => Some((receiver, destination)),
+                        None => return BehaviorStatus::failure(),
+                    }
+                }
+                _ => None,
+            };
+
             if rl_core::inventory::remove_item(state.world, args.entity, parameters.item) {
-                if let Some(target) = parameters.target {
-                    match target {
-                        Target::Tile(tile) => {
-                            let mut position = unsafe {
-                                state
-                                    .world
-                                    .get_component_mut_unchecked::<PositionComponent>(args.entity)
-                                    .unwrap()
-                            };
-                            **position = tile;
-                        }
-                        Target::Entity(_) => unimplemented!(),
+                if let Some((receiver, destination)) = receiver {
+                    hand_over(state, args, parameters.item, receiver, destination);
+
+                    return BehaviorStatus::success();
+                }
+
+                if let Some(Target::Tile(tile)) = parameters.target {
+                    let mut position = unsafe {
+                        state
+                            .world
+                            .get_component_mut_unchecked::<PositionComponent>(parameters.item)
+                            .unwrap()
                     };
+                    **position = tile;
                 }
 
                 args.command_buffer
@@ -446,4 +1076,261 @@ pub mod nodes {
 
         BehaviorStatus::failure()
     }
+
+    /// A free limb or room in the bag of `receiver` to put `item` in
+    fn hand_over_destination(
+        state: GameStateRef,
+        receiver: Entity,
+        item: Entity,
+    ) -> Option<PickupDestination> {
+        if !has_hands(state, receiver) {
+            return None;
+        }
+
+        if let Some(limb) = free_limb(state, receiver) {
+            return Some(PickupDestination::Carry(limb));
+        }
+
+        let items = state
+            .resources
+            .get::<DefinitionStorage<ItemDefinition>>()
+            .unwrap();
+        let definition = state.world.get_component::<ItemComponent>(item)?;
+        let container = state
+            .world
+            .get_component::<ItemContainerComponent>(receiver)?;
+
+        if inventory::can_contain_item(&container, definition.fetch(&items)) {
+            Some(PickupDestination::Container(receiver))
+        } else {
+            None
+        }
+    }
+
+    /// Puts `item` straight into the inventory of `receiver`, which another pawn takes as a gift
+    fn hand_over(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+        item: Entity,
+        receiver: Entity,
+        destination: PickupDestination,
+    ) {
+        use rl_ai::social::{SocialEvent, SocialEventKind};
+        use rl_core::{components::PawnTag, event::Channel};
+
+        match destination {
+            PickupDestination::Container(container) => {
+                unsafe {
+                    state
+                        .world
+                        .get_component_mut_unchecked::<ItemContainerComponent>(container)
+                }
+                .unwrap()
+                .push(item);
+            }
+            PickupDestination::Carry(part) => {
+                let mut carrying = unsafe {
+                    state
+                        .world
+                        .get_component_mut_unchecked::<CarryComponent>(receiver)
+                        .unwrap()
+                };
+                carrying.limbs.iter_mut().find(|i| i.0 == part).unwrap().1 = Some(item);
+            }
+        }
+
+        args.command_buffer
+            .add_component(item, ItemContainerChildComponent { parent: receiver });
+
+        if receiver != args.entity
+            && state.world.get_tag::<PawnTag>(args.entity).is_some()
+            && state.world.get_tag::<PawnTag>(receiver).is_some()
+        {
+            if let Some(channel) = state.resources.get::<Channel<SocialEvent>>() {
+                channel
+                    .write(SocialEvent::new(
+                        args.entity,
+                        receiver,
+                        SocialEventKind::Gift,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use rl_ai::perception::SensesComponent;
+        use rl_core::{blackboard::BlackboardComponent, defs::item::StockpileFilter, GameState};
+
+        fn setup() -> (GameState, Entity, [Vec3i; 3]) {
+            let mut state = GameState::default();
+            let tiles = [
+                Vec3i::new(0, 0, 0),
+                Vec3i::new(1, 0, 0),
+                Vec3i::new(2, 0, 0),
+            ];
+            let stockpile = state.world.insert(
+                (),
+                vec![(StockpileComponent::new(
+                    StockpileFilter::default(),
+                    StockpilePriority::Normal,
+                    tiles.to_vec(),
+                ),)],
+            )[0];
+
+            (state, stockpile, tiles)
+        }
+
+        fn spawn_item(state: &mut GameState, position: Vec3i) -> Entity {
+            state
+                .world
+                .insert((), vec![(PositionComponent::new(position),)])[0]
+        }
+
+        fn is_free(state: &GameState, stockpile: Entity, tile: Vec3i) -> bool {
+            state
+                .world
+                .get_component::<StockpileComponent>(stockpile)
+                .unwrap()
+                .tiles
+                .iter_free()
+                .any(|free| *free == tile)
+        }
+
+        #[test]
+        fn reserve_release_test() {
+            let (mut state, stockpile, tiles) = setup();
+            let mut command_buffer = CommandBuffer::new(&state.world);
+
+            let item = spawn_item(&mut state, tiles[0]);
+            state
+                .world
+                .get_component_mut::<StockpileComponent>(stockpile)
+                .unwrap()
+                .tiles
+                .take(&tiles[0]);
+            state
+                .world
+                .add_component(item, StockpileItemChildComponent { parent: stockpile })
+                .unwrap();
+
+            // The item keeps its tile until it is picked up
+            let game_state = GameStateRef {
+                world: &state.world,
+                resources: &state.resources,
+            };
+            reserve_haul(game_state, stockpile, tiles[1]);
+            assert!(!is_free(&state, stockpile, tiles[0]));
+            assert!(!is_free(&state, stockpile, tiles[1]));
+
+            leave_stockpile(game_state, &mut command_buffer, item);
+            command_buffer.write(&mut state.world);
+
+            assert!(is_free(&state, stockpile, tiles[0]));
+            assert!(!state
+                .world
+                .has_component::<StockpileItemChildComponent>(item));
+
+            let game_state = GameStateRef {
+                world: &state.world,
+                resources: &state.resources,
+            };
+            release_haul(game_state, stockpile, tiles[1]);
+            assert!(is_free(&state, stockpile, tiles[1]));
+        }
+
+        #[test]
+        fn clear_haul_batch_test() {
+            let (mut state, stockpile, tiles) = setup();
+            let mut command_buffer = CommandBuffer::new(&state.world);
+
+            let placed = spawn_item(&mut state, tiles[0]);
+            let unplaced = spawn_item(&mut state, Vec3i::new(5, 5, 0));
+            let container = spawn_item(&mut state, Vec3i::new(5, 5, 0));
+            let hauler = state.world.insert(
+                (),
+                vec![(CarryComponent {
+                    limbs: std::iter::once((PartGraphId::new(0), Some(container))).collect(),
+                },)],
+            )[0];
+            state
+                .world
+                .add_component(container, ItemContainerChildComponent { parent: hauler })
+                .unwrap();
+
+            let game_state = GameStateRef {
+                world: &state.world,
+                resources: &state.resources,
+            };
+            reserve_haul(game_state, stockpile, tiles[0]);
+            reserve_haul(game_state, stockpile, tiles[1]);
+
+            let mut blackboard = BlackboardComponent::default();
+            blackboard.insert(
+                fnv!("HaulBatch"),
+                HaulBatch {
+                    container,
+                    stockpile,
+                    items: vec![(placed, tiles[0]), (unplaced, tiles[1])].into(),
+                    unloading: true,
+                },
+            );
+            blackboard.insert(
+                fnv!("HaulParameters"),
+                HaulParameters {
+                    stockpile,
+                    item: placed,
+                    target_tile: tiles[0],
+                },
+            );
+
+            let status = clear_haul_batch(
+                game_state,
+                &mut BehaviorArgs {
+                    entity: hauler,
+                    blackboard: &mut blackboard,
+                    tree: &BehaviorTreeComponent::default(),
+                    senses: &SensesComponent::default(),
+                    command_buffer: &mut command_buffer,
+                },
+            );
+            command_buffer.write(&mut state.world);
+
+            assert_eq!(status, BehaviorResult::Success);
+            assert!(!is_free(&state, stockpile, tiles[0]));
+            assert!(is_free(&state, stockpile, tiles[1]));
+            assert!(!blackboard.contains(fnv!("HaulBatch")));
+            assert!(!blackboard.contains(fnv!("HaulParameters")));
+            assert!(state
+                .world
+                .get_component::<CarryComponent>(hauler)
+                .unwrap()
+                .iter()
+                .next()
+                .is_none());
+            assert!(!state
+                .world
+                .has_component::<ItemContainerChildComponent>(container));
+
+            // Nothing is left to release a second time
+            let game_state = GameStateRef {
+                world: &state.world,
+                resources: &state.resources,
+            };
+            let status = clear_haul_batch(
+                game_state,
+                &mut BehaviorArgs {
+                    entity: hauler,
+                    blackboard: &mut blackboard,
+                    tree: &BehaviorTreeComponent::default(),
+                    senses: &SensesComponent::default(),
+                    command_buffer: &mut command_buffer,
+                },
+            );
+            assert_eq!(status, BehaviorResult::Success);
+            assert!(!is_free(&state, stockpile, tiles[0]));
+        }
+    }
 }
@@ -1,12 +1,28 @@
-use rl_ai::bt::{self, make, BehaviorStatus};
+use rl_ai::{
+    action::{Action, ActionStorage, WorldFact},
+    bt::{self, definition::BehaviorNodeRegistry, make, BehaviorStatus},
+};
 use rl_core::{
-    components::PositionComponent, defs::reaction::ReactionDefinitionId, failure, fnv,
-    legion::prelude::*, GameStateRef,
+    components::PositionComponent,
+    defs::{
+        behavior::BehaviorDefinition,
+        item::{ItemAbilityKind, ItemKind},
+        needs::NeedKind,
+        reaction::ReactionDefinitionId,
+        DefinitionStorage,
+    },
+    failure, fnv,
+    legion::prelude::*,
+    GameStateRef,
 };
 
+pub mod combat;
 pub mod creature;
 pub mod haul;
+pub mod mood;
 pub mod needs;
+pub mod sleep;
+pub mod social;
 pub mod task;
 
 #[derive(Copy, Clone, Debug)]
@@ -16,34 +32,136 @@ pub struct ExecuteReactionParameters {
 }
 
 pub fn prepare(_: &mut World, resources: &mut Resources) -> Result<(), failure::Error> {
-    resources.insert(build()?);
+    let registry = build_registry();
+
+    let storage = {
+        let defs = resources
+            .get::<DefinitionStorage<BehaviorDefinition>>()
+            .unwrap();
+
+        build(&registry, &defs)?
+    };
+
+    resources.insert(storage);
+    resources.insert(registry);
+    resources.insert(build_actions());
 
     Ok(())
 }
 
-// Behaviors require being built and runtime and cant use inventory because of make::sub dependencies to copy an already registered node in
-fn build() -> Result<bt::BehaviorStorage, failure::Error> {
-    let mut storage = bt::BehaviorStorage::default();
+fn build_registry() -> BehaviorNodeRegistry {
+    let mut registry = BehaviorNodeRegistry::default();
+
+    registry.register(
+        "idle",
+        make::closure(None, |_, _| bt::BehaviorStatus::running(true)),
+    );
+    registry.register(
+        "succeed",
+        make::closure(None, |_, _| bt::BehaviorStatus::success()),
+    );
+    registry.register("move_to", make::closure(None, nodes::move_to));
+    registry.register(
+        bt::definition::RELEASE_NODE,
+        make::closure(None, nodes::release_held),
+    );
+    registry.register("has_item", make::closure(None, nodes::has_item));
+    registry.register("try_get_reagent", make::closure(None, nodes::try_get_reagent));
+    registry.register("deliver_reagent", make::closure(None, nodes::deliver_reagent));
+    registry.register(
+        "execute_reaction",
+        make::closure(None, nodes::execute_reaction),
+    );
 
-    storage.insert(
-        "Idle",
-        make::sequence(&[make::closure(None, |_, _| {
-            bt::BehaviorStatus::running(true)
-        })]),
+    registry.register(
+        "find_digging_tool",
+        nodes::make_find_item_with_ability(ItemAbilityKind::Digging),
+    );
+    registry.register(
+        "find_chopping_tool",
+        nodes::make_find_item_with_ability(ItemAbilityKind::Chopping),
     );
 
-    storage.insert("move_to", make::closure(None, nodes::move_to));
+    combat::register(&mut registry);
+    creature::register(&mut registry);
+    haul::register(&mut registry);
+    mood::register(&mut registry);
+    needs::register(&mut registry);
+    sleep::register(&mut registry);
+    social::register(&mut registry);
+    task::register(&mut registry);
+
+    registry
+}
+
+fn build_actions() -> ActionStorage {
+    let mut actions = ActionStorage::default();
+
+    actions.insert(
+        "fetch_digging_tool",
+        Action::new("fetch_digging_tool", "fetch_digging_tool", 1.0).with_effect(
+            WorldFact::HasItemAbility(ItemAbilityKind::Digging),
+            true,
+        ),
+    );
+    actions.insert(
+        "fetch_chopping_tool",
+        Action::new("fetch_chopping_tool", "fetch_chopping_tool", 1.0).with_effect(
+            WorldFact::HasItemAbility(ItemAbilityKind::Chopping),
+            true,
+        ),
+    );
+    // Tasks needing a tool are worked by fetching one first
+    actions.insert(
+        "work_task",
+        Action::new("work_task", "work_task", 1.0).with_effect(WorldFact::WorkedTask(None), true),
+    );
+    actions.insert(
+        "work_digging_task",
+        Action::new("work_digging_task", "work_task", 1.0)
+            .with_precondition(WorldFact::HasItemAbility(ItemAbilityKind::Digging), true)
+            .with_effect(WorldFact::WorkedTask(Some(ItemAbilityKind::Digging)), true),
+    );
+    actions.insert(
+        "work_chopping_task",
+        Action::new("work_chopping_task", "work_task", 1.0)
+            .with_precondition(WorldFact::HasItemAbility(ItemAbilityKind::Chopping), true)
+            .with_effect(WorldFact::WorkedTask(Some(ItemAbilityKind::Chopping)), true),
+    );
+    actions.insert(
+        "fetch_food",
+        Action::new("fetch_food", "fetch_food", 1.0)
+            .with_effect(WorldFact::HasItemKind(ItemKind::Food), true),
+    );
+    actions.insert(
+        "eat",
+        Action::new("eat", "try_eat", 1.0)
+            .with_precondition(WorldFact::HasItemKind(ItemKind::Food), true)
+            .with_effect(WorldFact::NeedSatisfied(NeedKind::Calories), true),
+    );
+    actions.insert(
+        "drink",
+        Action::new("drink", "try_drink", 1.0)
+            .with_effect(WorldFact::NeedSatisfied(NeedKind::Hydration), true),
+    );
+    actions.insert(
+        "sleep",
+        Action::new("sleep", "try_sleep", 1.0)
+            .with_effect(WorldFact::NeedSatisfied(NeedKind::Sleep), true),
+    );
+
+    actions
+}
+
+fn build(
+    registry: &BehaviorNodeRegistry,
+    defs: &DefinitionStorage<BehaviorDefinition>,
+) -> Result<bt::BehaviorStorage, failure::Error> {
+    let mut storage = bt::BehaviorStorage::default();
 
-    haul::build(&mut storage)?;
-    task::build(&mut storage)?;
-    needs::build(&mut storage)?;
-    creature::build(&mut storage)?;
+    storage.insert("move_to", make::closure(None, nodes::move_to));
 
-    let do_work = make::selector(&[
-        make::sub("do_task", &storage),
-        make::sub("do_haul", &storage),
-    ]);
-    storage.insert("do_work", do_work);
+    bt::definition::load(&mut storage, registry, defs)?;
 
     Ok(storage)
 }
@@ -52,13 +170,18 @@ pub mod nodes {
     use super::*;
     use rl_ai::bt::*;
     use rl_core::{
-        components::{MovementComponent, MovementRequest, MovementResult},
+        components::{
+            ItemContainerChildComponent, MovementComponent, MovementRequest, MovementResult,
+            PawnTag,
+        },
         data::bt::*,
         defs::{
-            item::{ItemDefinition, ItemProperty},
+            item::{ItemComponent, ItemDefinition, ItemProperty},
             DefinitionComponent, DefinitionStorage,
         },
         event::Channel,
+        map::{spatial::SpatialMap, Map},
+        math::Vec3i,
         AtomicResult,
     };
     use rl_reaction::{BeginReactionEvent, ReactionEntity, ReactionResult};
@@ -257,63 +380,135 @@ pub mod nodes {
         }
     }
 
-    pub fn try_get_reagent(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
-        use rl_core::defs::reaction::Reagent;
-
-        if let Some(kind) = args.blackboard.get::<Reagent>(fnv!("missing_reagent")) {
-            /*
-            match kind {
-                Kind::Item(item_ref) => {
-                    // Search stockpiles first
-                    let stockpile_query = <Read<ItemComponent>>::query()
-                        .filter(component::<StockpileItemChildComponent>());
-
-                    // TODO: this needs to use spatial_map b distance instaed
-                    for (entity, item_comp) in stockpile_query.iter_entities(state.world) {
-                        if *item_ref == *item_comp {
-                            let pickup_parameters = PickupParameters::new(entity);
-
-                            args.blackboard
-                                .insert(fnv!("PickupParameters"), pickup_parameters);
-                            return BehaviorStatus::success();
-                        }
-                    }
+    pub fn make_find_item_with_ability(ability: ItemAbilityKind) -> Arc<dyn BehaviorNode> {
+        make::closure(None, move |state, args| {
+            let (spatial_map, items) = <(
+                Read<SpatialMap>,
+                Read<DefinitionStorage<ItemDefinition>>,
+            )>::fetch(state.resources);
 
-                    // TODO: search random items too?
-                }
-                Kind::ItemAbility(ability) => {
-                    // Ability, we can try to get a tool with this ability
-
-                    let query = <Read<ItemComponent>>::query().filter(
-                        component::<PositionComponent>()
-                            & !component::<ItemContainerChildComponent>(),
-                    );
-
-                    let item_defs = state
-                        .resources
-                        .get::<DefinitionStorage<ItemDefinition>>()
-                        .unwrap();
-
-                    for (entity, item_comp) in query.iter_entities(state.world) {
-                        let def = item_comp.fetch(&item_defs);
-                        // TODO: item quality
-                        if def.abilities.contains(&ability.kind.into()) {
-                            let pickup_parameters = PickupParameters::new(entity);
-
-                            args.blackboard
-                                .insert(fnv!("PickupParameters"), pickup_parameters);
-                            return BehaviorStatus::success();
-                        }
-                    }
-                }
-                _ => unimplemented!(),
+            let position = state
+                .world
+                .get_component::<PositionComponent>(args.entity)
+                .unwrap();
+
+            if let Some(found) = spatial_map.nearest_neighbor_iter(&position).find(|entry| {
+                !state
+                    .world
+                    .has_component::<ItemContainerChildComponent>(entry.entity)
+                    && state
+                        .world
+                        .get_component::<ItemComponent>(entry.entity)
+                        .map_or(false, |item| {
+                            item.fetch(&items)
+                                .abilities
+                                .iter()
+                                .any(|a| a.kind == ability)
+                        })
+            }) {
+                args.blackboard.insert(
+                    fnv!("PickupParameters"),
+                    PickupParameters::new(found.entity),
+                );
+
+                return BehaviorStatus::success();
             }
-            */
+
+            BehaviorStatus::failure()
+        })
+    }
+
+    /// Go and pick up the nearest loose item which would satisfy the reagent `find_task` was
+    /// missing. Items the task's holder should have, such as the log a workshop chops, are then
+    /// brought over by `deliver_reagent`.
+    pub fn try_get_reagent(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        use rl_core::{
+            condition,
+            defs::{condition::Item, reaction::Reagent},
+        };
+
+        args.blackboard.remove(fnv!("ReagentDelivery"));
+
+        let (reagent, holder) = match args
+            .blackboard
+            .remove_get::<(Reagent, Entity)>(fnv!("missing_reagent"))
+        {
+            Some(missing) => missing,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+        let find = |wanted: &[&Item], skip: Option<Vec3i>| {
+            condition::find_loose_item(state.world, state.resources, position, wanted, skip)
+        };
+
+        let wanted = condition::wanted_items(&reagent.conditions, false);
+        if let Some(found) = find(&wanted, None) {
+            args.blackboard
+                .insert(fnv!("PickupParameters"), PickupParameters::new(found));
+
+            return BehaviorStatus::success();
+        }
+
+        // Anything already at the holder didn't satisfy it, so look elsewhere
+        let tile = match state.world.get_component::<PositionComponent>(holder) {
+            Some(tile) => **tile,
+            None => return BehaviorStatus::failure(),
+        };
+        let wanted = condition::wanted_items(&reagent.conditions, true);
+        if let Some(found) = find(&wanted, Some(tile)) {
+            args.blackboard
+                .insert(fnv!("PickupParameters"), PickupParameters::new(found));
+            // Another pawn is handed the item rather than having it dropped at their feet
+            let target =
+                if holder != args.entity && state.world.get_tag::<PawnTag>(holder).is_some() {
+                    Target::Entity(holder)
+                } else {
+                    Target::Tile(tile)
+                };
+            args.blackboard.insert(
+                fnv!("DropParameters"),
+                DropParameters::with_target(found, target),
+            );
+            args.blackboard.insert(fnv!("ReagentDelivery"), tile);
+
+            return BehaviorStatus::success();
         }
 
         BehaviorStatus::failure()
     }
 
+    /// Walk next to where `try_get_reagent` wants its item dropped, failing if it wants it carried
+    pub fn deliver_reagent(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let tile = match args.blackboard.remove_get::<Vec3i>(fnv!("ReagentDelivery")) {
+            Some(tile) => tile,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let map = state.resources.get::<Map>().unwrap();
+        match rl_ai::pathfinding::neighbors(&map, &tile).first() {
+            Some((next_to, _)) => {
+                args.blackboard
+                    .insert(fnv!("MoveParameters"), MoveParameters::new_tile(*next_to));
+
+                BehaviorStatus::success()
+            }
+            None => BehaviorStatus::failure(),
+        }
+    }
+
+    /// Gives up the haul reservations and seat held in the blackboard when a behavior is restarted
+    pub fn release_held(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        haul::nodes::clear_haul_batch(state, args);
+        haul::nodes::abandon_haul(state, args);
+        needs::nodes::leave_seat(state, args);
+
+        BehaviorStatus::success()
+    }
+
     pub fn execute_reaction(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
         let parameters = args
             .blackboard
@@ -0,0 +1,48 @@
+use super::needs::{nodes::make_explore, EXPLORE_RADIUS};
+use rl_ai::bt::{definition::BehaviorNodeRegistry, make};
+
+/// World seconds a breaking down pawn spends sulking before it recovers
+pub const SULK_DURATION: f64 = 7200.0;
+
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register("explore", make_explore(EXPLORE_RADIUS));
+    registry.register("sulk", make::closure(None, nodes::sulk));
+}
+
+pub mod nodes {
+    use super::*;
+    use rl_ai::bt::*;
+    use rl_core::{
+        defs::thought::{ThoughtEvent, ThoughtTrigger},
+        event::Channel,
+        fnv,
+        time::Time,
+        GameStateRef,
+    };
+
+    /// Do nothing for a while, then feel better for it
+    pub fn sulk(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let now = state.resources.get::<Time>().unwrap().world_time;
+
+        let started = *args
+            .blackboard
+            .get::<f64>(fnv!("SulkStarted"))
+            .unwrap_or(&now);
+        args.blackboard.insert(fnv!("SulkStarted"), started);
+
+        if now - started < SULK_DURATION {
+            return BehaviorStatus::running(false);
+        }
+
+        args.blackboard.remove(fnv!("SulkStarted"));
+
+        state
+            .resources
+            .get::<Channel<ThoughtEvent>>()
+            .unwrap()
+            .write(ThoughtEvent::new(args.entity, ThoughtTrigger::Breakdown, 0))
+            .unwrap();
+
+        BehaviorStatus::success()
+    }
+}
@@ -1,202 +1,309 @@
 use super::nodes as general_nodes;
 use crate::behavior::ExecuteReactionParameters;
-use rl_ai::bt::{self, make, BehaviorNode, BehaviorStatus};
+use rl_ai::{
+    action::{self, WorldFact, WorldState},
+    bt::{definition::BehaviorNodeRegistry, make, BehaviorNode, BehaviorStatus},
+    memory::{MemoryComponent, MemoryKind},
+};
 use rl_core::{
     components::{ItemContainerChildComponent, PositionComponent},
     data::bt::*,
     defs::{
-        foliage::{FoliageComponent, FoliageDefinition},
-        item::{ItemComponent, ItemDefinition, ItemProperty},
-        material::{MaterialComponent, MaterialDefinition},
-        needs::{NeedKind, ProvidesNutrition},
-        reaction::ReactionDefinition,
-        DefinitionComponent, DefinitionStorage,
+        item::{ItemComponent, ItemProperty},
+        needs::{get_nutrition_value, NeedKind},
+        reaction::{ReactionDefinition, ReactionDefinitionId},
+        DefinitionStorage,
     },
-    failure, fnv,
+    fnv,
     legion::prelude::*,
-    map::spatial::SpatialMap,
-    GameStateRef,
+    map::Map,
+    math::Vec3i,
+    rand::Rng,
+    Random,
 };
 use rl_reaction::ReactionEntity;
-use std::{ops::Range, sync::Arc};
-
-pub fn build(storage: &mut bt::BehaviorStorage) -> Result<(), failure::Error> {
-    let try_eat = make::selector(&[make::sequence(&[make::selector(&[
-        make::sequence(&[
-            general_nodes::make_find_has_items_with_property(ItemProperty::IsEdible),
-            make::closure(None, |state, args| {
-                let found = args
-                    .blackboard
-                    .remove_get::<Vec<Entity>>(fnv!("FoundItemsProperty"))
-                    .unwrap();
-
-                for entity in found {
-                    if let Some(nut) = get_nutrition_value(state, entity, NeedKind::Calories) {
-                        if nut.start > 0 {
-                            let reaction = state
-                                .resources
-                                .get::<DefinitionStorage<ReactionDefinition>>()
-                                .unwrap()
-                                .get_id("Consume (Any)")
-                                .unwrap();
-
-                            args.blackboard.insert(
-                                fnv!("ExecuteReactionParameters"),
-                                ExecuteReactionParameters {
-                                    reaction,
-                                    target: ReactionEntity::Any(entity),
-                                },
-                            );
-
-                            return BehaviorStatus::success();
-                        }
-                    }
-                }
-
-                BehaviorStatus::failure()
-            }),
-        ]),
-        make::sequence(&[
-            // TODO: Check we can actually get it? LOL
-            nodes::make_try_find_nearest_consumable(NeedKind::Calories),
-            make::sub("pickup_item", storage),
-            make::closure(None, |state, args| {
-                if let Some(pickup) = args
-                    .blackboard
-                    .get::<PickupParameters>(fnv!("PickupParameters"))
-                    .cloned()
-                {
-                    let reaction = state
-                        .resources
-                        .get::<DefinitionStorage<ReactionDefinition>>()
-                        .unwrap()
-                        .get_id("Consume (Any)")
-                        .unwrap();
-
-                    args.blackboard.insert(
-                        fnv!("ExecuteReactionParameters"),
-                        ExecuteReactionParameters {
-                            reaction,
-                            target: ReactionEntity::Any(pickup.target),
-                        },
-                    );
-                    return BehaviorStatus::success();
-                }
+use std::sync::Arc;
 
-                BehaviorStatus::failure()
-            }),
-            make::closure(None, general_nodes::execute_reaction),
-        ]),
-    ])])]);
-    storage.insert("try_eat", try_eat);
-
-    let try_drink = make::selector(&[make::sequence(&[make::selector(&[
-        make::sequence(&[
-            general_nodes::make_find_has_items_with_property(ItemProperty::IsEdible),
-            make::closure(None, |state, args| {
-                let found = args
-                    .blackboard
-                    .remove_get::<Vec<Entity>>(fnv!("FoundItemsProperty"))
-                    .unwrap();
-
-                for entity in found {
-                    if let Some(nut) = get_nutrition_value(state, entity, NeedKind::Hydration) {
-                        if nut.start > 0 {
-                            let reaction = state
-                                .resources
-                                .get::<DefinitionStorage<ReactionDefinition>>()
-                                .unwrap()
-                                .get_id("Consume (Any)")
-                                .unwrap();
-
-                            args.blackboard.insert(
-                                fnv!("ExecuteReactionParameters"),
-                                ExecuteReactionParameters {
-                                    reaction,
-                                    target: ReactionEntity::Any(entity),
-                                },
-                            );
-
-                            return BehaviorStatus::success();
-                        }
-                    }
-                }
+/// Distance in tiles a pawn wanders when it does not remember anything useful
+pub const EXPLORE_RADIUS: i32 = 24;
+/// Distance in tiles a pawn will carry food to find a seat at a table
+pub const DINING_RANGE: i32 = 32;
 
-                BehaviorStatus::failure()
-            }),
-        ]),
-        make::sequence(&[
-            // TODO: Check we can actually get it? LOL
-            nodes::make_try_find_nearest_consumable(NeedKind::Hydration),
-            make::sub("pickup_item", storage),
-            make::closure(None, |state, args| {
-                if let Some(pickup) = args
-                    .blackboard
-                    .get::<PickupParameters>(fnv!("PickupParameters"))
-                    .cloned()
-                {
-                    let reaction = state
-                        .resources
-                        .get::<DefinitionStorage<ReactionDefinition>>()
-                        .unwrap()
-                        .get_id("Consume (Any)")
-                        .unwrap();
-
-                    args.blackboard.insert(
-                        fnv!("ExecuteReactionParameters"),
-                        ExecuteReactionParameters {
-                            reaction,
-                            target: ReactionEntity::Any(pickup.target),
-                        },
-                    );
-                    return BehaviorStatus::success();
-                }
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register(
+        "find_carried_edible",
+        general_nodes::make_find_has_items_with_property(ItemProperty::IsEdible),
+    );
+    registry.register(
+        "prepare_eat_carried",
+        nodes::make_prepare_consume_carried(NeedKind::Calories),
+    );
+    registry.register(
+        "prepare_drink_carried",
+        nodes::make_prepare_consume_carried(NeedKind::Hydration),
+    );
+    registry.register(
+        "prepare_consume_pickup",
+        make::closure(None, nodes::prepare_consume_pickup),
+    );
+    registry.register(
+        "find_food",
+        nodes::make_try_find_nearest_consumable(NeedKind::Calories),
+    );
+    registry.register(
+        "find_water",
+        nodes::make_try_find_nearest_consumable(NeedKind::Hydration),
+    );
+    registry.register(
+        "plan_calories",
+        action::nodes::make_plan_for(
+            WorldState::default().with(WorldFact::NeedSatisfied(NeedKind::Calories), true),
+        ),
+    );
 
-                BehaviorStatus::failure()
-            }),
-            make::closure(None, general_nodes::execute_reaction),
-        ]),
-    ])])]);
-    storage.insert("try_drink", try_drink);
-
-    Ok(())
+    registry.register("find_seat", make::closure(None, nodes::find_seat));
+    registry.register("finish_meal", make::closure(None, nodes::finish_meal));
+    registry.register("leave_seat", make::closure(None, nodes::leave_seat));
 }
 
 pub mod nodes {
     use super::*;
+    use rl_ai::bt::BehaviorArgs;
+    use rl_core::{
+        defs::{
+            building::{
+                BuildingComponent, BuildingDefinition, BuildingProperty, ReservationComponent,
+            },
+            thought::{ThoughtEvent, ThoughtTrigger},
+            DefinitionComponent,
+        },
+        event::Channel,
+        time::Time,
+        GameStateRef,
+    };
+
+    fn consume_reaction(state: GameStateRef) -> ReactionDefinitionId {
+        state
+            .resources
+            .get::<DefinitionStorage<ReactionDefinition>>()
+            .unwrap()
+            .get_id("Consume (Any)")
+            .unwrap()
+    }
+
+    /// Consume the first of the carried items found which satisfies the need
+    pub fn make_prepare_consume_carried(kind: NeedKind) -> Arc<dyn BehaviorNode> {
+        make::closure(None, move |state, args| {
+            let found = args
+                .blackboard
+                .remove_get::<Vec<Entity>>(fnv!("FoundItemsProperty"))
+                .unwrap_or_default();
+
+            let entity = match found.into_iter().find(|entity| {
+                get_nutrition_value(state, *entity, kind).map_or(false, |nut| nut.start > 0)
+            }) {
+                Some(entity) => entity,
+                None => return BehaviorStatus::failure(),
+            };
+
+            args.blackboard.insert(
+                fnv!("ExecuteReactionParameters"),
+                ExecuteReactionParameters {
+                    reaction: consume_reaction(state),
+                    target: ReactionEntity::Any(entity),
+                },
+            );
+
+            BehaviorStatus::success()
+        })
+    }
 
+    /// Consume the item we just picked up
+    pub fn prepare_consume_pickup(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        let pickup = match args
+            .blackboard
+            .get::<PickupParameters>(fnv!("PickupParameters"))
+            .cloned()
+        {
+            Some(pickup) => pickup,
+            None => return BehaviorStatus::failure(),
+        };
+
+        args.blackboard.insert(
+            fnv!("ExecuteReactionParameters"),
+            ExecuteReactionParameters {
+                reaction: consume_reaction(state),
+                target: ReactionEntity::Any(pickup.target),
+            },
+        );
+
+        BehaviorStatus::success()
+    }
+
+    fn distance_2(a: Vec3i, b: Vec3i) -> i32 {
+        let d = a - b;
+        d.x * d.x + d.y * d.y + d.z * d.z
+    }
+
+    /// Reserve the nearest free seat next to a table, keeping the one we already hold
+    pub fn find_seat(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (buildings, time) =
+            <(Read<DefinitionStorage<BuildingDefinition>>, Read<Time>)>::fetch(state.resources);
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let tables = <(Read<BuildingComponent>, Read<PositionComponent>)>::query()
+            .iter(state.world)
+            .filter(|(building, _)| {
+                building
+                    .fetch(&buildings)
+                    .properties
+                    .contains(BuildingProperty::IS_EAT_ASSIST)
+            })
+            .map(|(_, table)| **table)
+            .collect::<Vec<_>>();
+
+        let at_table = |seat: Vec3i| {
+            tables.iter().any(|table| {
+                *table != seat
+                    && table.z == seat.z
+                    && (table.x - seat.x).abs() <= 1
+                    && (table.y - seat.y).abs() <= 1
+            })
+        };
+
+        let seat = <(Read<ReservationComponent>, Read<PositionComponent>)>::query()
+            .iter_entities(state.world)
+            .filter(|(_, (reservation, seat))| {
+                reservation.is_free_for(state.world, args.entity, time.world_time)
+                    && distance_2(position, ***seat) <= DINING_RANGE * DINING_RANGE
+                    && at_table(***seat)
+            })
+            .min_by_key(|(_, (reservation, seat))| {
+                (
+                    reservation.holder != Some(args.entity),
+                    distance_2(position, ***seat),
+                )
+            })
+            .map(|(entity, _)| entity);
+
+        if let Some(seat) = seat {
+            unsafe {
+                state
+                    .world
+                    .get_component_mut_unchecked::<ReservationComponent>(seat)
+            }
+            .unwrap()
+            .reserve(args.entity, time.world_time);
+
+            args.blackboard.insert(fnv!("Seat"), seat);
+            args.blackboard
+                .insert(fnv!("MoveParameters"), MoveParameters::new_entity(seat));
+
+            return BehaviorStatus::success();
+        }
+
+        args.blackboard.remove(fnv!("Seat"));
+
+        BehaviorStatus::failure()
+    }
+
+    fn release_seat(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> Option<Entity> {
+        let seat = args.blackboard.remove_get::<Entity>(fnv!("Seat"))?;
+
+        if let Some(mut reservation) = unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<ReservationComponent>(seat)
+        } {
+            reservation.release(args.entity);
+        }
+
+        Some(seat)
+    }
+
+    /// Give up our seat when the meal could not be finished
+    pub fn leave_seat(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        release_seat(state, args);
+
+        BehaviorStatus::success()
+    }
+
+    /// Give up our seat, and mind it if we had to eat without one
+    pub fn finish_meal(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let seated = release_seat(state, args).map_or(false, |seat| {
+            state
+                .world
+                .get_component::<PositionComponent>(seat)
+                .map_or(false, |seat| **seat == position)
+        });
+
+        if !seated {
+            state
+                .resources
+                .get::<Channel<ThoughtEvent>>()
+                .unwrap()
+                .write(ThoughtEvent::new(
+                    args.entity,
+                    ThoughtTrigger::AteWithoutTable,
+                    0,
+                ))
+                .unwrap();
+        }
+
+        BehaviorStatus::success()
+    }
+
+    /// Pick up the nearest remembered item which satisfies the need
     pub fn make_try_find_nearest_consumable(kind: NeedKind) -> Arc<dyn BehaviorNode> {
+        // Nothing is remembered for other needs, so there is never anything to find
+        let memory_kind = match kind {
+            NeedKind::Calories => MemoryKind::Food,
+            NeedKind::Hydration => MemoryKind::Water,
+            _ => return make::closure(None, |_, _| BehaviorStatus::failure()),
+        };
+
         make::closure(None, move |state, args| {
-            let spatial_map = state.resources.get::<SpatialMap>().unwrap();
+            let memory = match state.world.get_component::<MemoryComponent>(args.entity) {
+                Some(memory) => memory,
+                None => return BehaviorStatus::failure(),
+            };
 
             let position = state
                 .world
                 .get_component::<PositionComponent>(args.entity)
                 .unwrap();
 
-            // TODO: better selection, just pick closest for now
-            if let Some(found) = spatial_map.nearest_neighbor_iter(&position).find(|entry| {
-                if !state
-                    .world
-                    .has_component::<ItemContainerChildComponent>(entry.entity)
-                {
-                    if let Some(_) = state.world.get_component::<ItemComponent>(entry.entity) {
-                        if let Some(nut) = get_nutrition_value(state, entry.entity, kind) {
-                            if nut.start > 0 {
-                                return true;
-                            }
-                        }
-                    }
-                }
+            let found = memory
+                .nearest(memory_kind, **position)
+                .into_iter()
+                .filter_map(|entry| entry.entity)
+                .find(|entity| {
+                    state.world.is_alive(*entity)
+                        && state.world.has_component::<ItemComponent>(*entity)
+                        && !state
+                            .world
+                            .has_component::<ItemContainerChildComponent>(*entity)
+                        && get_nutrition_value(state, *entity, kind)
+                            .map_or(false, |nut| nut.start > 0)
+                });
 
-                false
-            }) {
-                log::trace!(target: "behavior", "found consumption entity, attempting to pickup = {:?}", found.entity);
+            if let Some(found) = found {
+                log::trace!(target: "behavior", "found consumption entity, attempting to pickup = {:?}", found);
 
-                args.blackboard.insert(
-                    fnv!("PickupParameters"),
-                    PickupParameters::new(found.entity),
-                );
+                args.blackboard
+                    .insert(fnv!("PickupParameters"), PickupParameters::new(found));
 
                 return BehaviorStatus::success();
             }
@@ -204,51 +311,40 @@ pub mod nodes {
             BehaviorStatus::failure()
         })
     }
-}
 
-pub fn get_nutrition_value(
-    state: GameStateRef,
-    entity: Entity,
-    kind: NeedKind,
-) -> Option<Range<i32>> {
-    let (items, materials, foliages) = <(
-        Read<DefinitionStorage<ItemDefinition>>,
-        Read<DefinitionStorage<MaterialDefinition>>,
-        Read<DefinitionStorage<FoliageDefinition>>,
-    )>::fetch(state.resources);
-
-    let item_comp = state.world.get_component::<ItemComponent>(entity);
-    let foliage_comp = state.world.get_component::<FoliageComponent>(entity);
-
-    let optional_nutrition = if let Some(comp) = &item_comp {
-        &comp.fetch(&items).nutrition
-    } else if let Some(comp) = &foliage_comp {
-        &comp.fetch(&foliages).nutrition
-    } else {
-        return None;
-    };
+    /// Set a random walkable tile nearby as the movement target
+    pub fn make_explore(radius: i32) -> Arc<dyn BehaviorNode> {
+        make::closure(None, move |state, args| {
+            const ATTEMPTS: usize = 8;
 
-    let nutrition = match &optional_nutrition {
-        ProvidesNutrition::FromMaterial => {
-            if item_comp.is_some() {
-                if let Some(material_comp) = state.world.get_component::<MaterialComponent>(entity)
-                {
-                    let material_state = material_comp.fetch_state(&materials);
+            let map = state.resources.get::<Map>().unwrap();
+            let mut rng = state.resources.get::<Random>().unwrap().make();
 
-                    &material_state.nutrition
-                } else {
-                    return None;
+            let position = **state
+                .world
+                .get_component::<PositionComponent>(args.entity)
+                .unwrap();
+            let dimensions = map.dimensions();
+
+            for _ in 0..ATTEMPTS {
+                let target = Vec3i::new(
+                    (position.x + rng.gen_range(-radius, radius + 1))
+                        .max(0)
+                        .min(dimensions.x - 1),
+                    (position.y + rng.gen_range(-radius, radius + 1))
+                        .max(0)
+                        .min(dimensions.y - 1),
+                    position.z,
+                );
+
+                if target != position && map.get(target).is_walkable() {
+                    args.blackboard
+                        .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+                    return BehaviorStatus::success();
                 }
-            } else {
-                return None;
             }
-        }
-        ProvidesNutrition::Value(value) => value,
-    };
 
-    Some(match kind {
-        NeedKind::Calories => nutrition.calories.clone(),
-        NeedKind::Hydration => nutrition.hydration.clone(),
-        _ => unimplemented!(),
-    })
+            BehaviorStatus::failure()
+        })
+    }
 }
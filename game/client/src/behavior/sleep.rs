@@ -0,0 +1,146 @@
+use rl_ai::bt::{definition::BehaviorNodeRegistry, make};
+
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register("find_bed", make::closure(None, nodes::find_bed));
+    registry.register("sleep", make::closure(None, nodes::sleep));
+}
+
+pub mod nodes {
+    use rl_ai::bt::*;
+    use rl_core::{
+        components::{NeedsComponent, PositionComponent, SleepingComponent},
+        data::bt::*,
+        defs::{
+            building::BedComponent,
+            needs::NeedKind,
+            thought::{ThoughtEvent, ThoughtTrigger},
+        },
+        event::Channel,
+        fnv,
+        legion::prelude::*,
+        time::Time,
+        GameStateRef,
+    };
+
+    /// Our own bed if we have one, otherwise claim the nearest free bed
+    pub fn find_bed(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let query = <(Read<BedComponent>, Read<PositionComponent>)>::query();
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let owned = query
+            .iter_entities(state.world)
+            .find(|(_, (bed, _))| bed.owner == Some(args.entity))
+            .map(|(entity, _)| entity);
+
+        let bed = owned.or_else(|| {
+            query
+                .iter_entities(state.world)
+                .filter(|(_, (bed, _))| bed.is_free_for(state.world, args.entity))
+                .min_by_key(|(_, (_, bed_position))| {
+                    let d = ***bed_position - position;
+                    d.x * d.x + d.y * d.y + d.z * d.z
+                })
+                .map(|(entity, _)| entity)
+        });
+
+        if let Some(bed) = bed {
+            // Only one bed is ours at a time
+            for (other, mut other_bed) in
+                unsafe { <Write<BedComponent>>::query().iter_entities_unchecked(state.world) }
+            {
+                if other_bed.owner == Some(args.entity) && other != bed {
+                    other_bed.owner = None;
+                }
+            }
+
+            unsafe { state.world.get_component_mut_unchecked::<BedComponent>(bed) }
+                .unwrap()
+                .owner = Some(args.entity);
+
+            args.blackboard.insert(fnv!("Bed"), bed);
+            args.blackboard
+                .insert(fnv!("MoveParameters"), MoveParameters::new_entity(bed));
+
+            return BehaviorStatus::success();
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    /// Lie down where we are, in the bed if we are standing on it, until fully rested. We stay
+    /// asleep only while this keeps running.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn sleep(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (now, frame) = {
+            let time = state.resources.get::<Time>().unwrap();
+            (time.world_time, time.frame)
+        };
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        let sleeping = match unsafe {
+            state
+                .world
+                .get_component_mut_unchecked::<SleepingComponent>(args.entity)
+        } {
+            Some(mut sleeping) => {
+                sleeping.renewed = frame;
+                *sleeping
+            }
+            None => {
+                let bed = args
+                    .blackboard
+                    .get::<Entity>(fnv!("Bed"))
+                    .copied()
+                    .filter(|bed| {
+                        state
+                            .world
+                            .get_component::<PositionComponent>(*bed)
+                            .map_or(false, |bed_position| **bed_position == position)
+                    });
+
+                args.command_buffer.add_component(
+                    args.entity,
+                    SleepingComponent::new(bed, position, now, frame),
+                );
+
+                return BehaviorStatus::running(false);
+            }
+        };
+
+        let rested = state
+            .world
+            .get_component::<NeedsComponent>(args.entity)
+            .map_or(true, |needs| needs.get(NeedKind::Sleep).value >= 0);
+        if !rested {
+            return BehaviorStatus::running(false);
+        }
+
+        let trigger = if sleeping.bed.is_some() {
+            ThoughtTrigger::SleptInBed
+        } else {
+            ThoughtTrigger::SleptOnFloor
+        };
+        let hours = ((now - sleeping.started) / 3600.0).round() as i32;
+
+        state
+            .resources
+            .get::<Channel<ThoughtEvent>>()
+            .unwrap()
+            .write(ThoughtEvent::new(args.entity, trigger, hours))
+            .unwrap();
+
+        args.command_buffer
+            .remove_component::<SleepingComponent>(args.entity);
+        args.blackboard.remove(fnv!("Bed"));
+
+        BehaviorStatus::success()
+    }
+}
@@ -0,0 +1,168 @@
+use rl_ai::bt::{definition::BehaviorNodeRegistry, make};
+
+/// Distance in tiles a pawn puts between itself and a rival
+pub const AVOID_DISTANCE: i32 = 8;
+
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register(
+        "find_conversation_partner",
+        make::closure(None, nodes::find_conversation_partner),
+    );
+    registry.register("converse", make::closure(None, nodes::converse));
+    registry.register("find_rival_escape", make::closure(None, nodes::find_rival_escape));
+}
+
+pub mod nodes {
+    use super::*;
+    use rl_ai::{
+        bt::*,
+        social::{
+            conversation_events, RelationshipGraph, SocialComponent, SocialEvent,
+            SocialEventKind, CONVERSATION_RANGE,
+        },
+    };
+    use rl_core::{
+        components::{PawnTag, PositionComponent},
+        data::bt::*,
+        event::Channel,
+        fnv,
+        legion::prelude::*,
+        map::Map,
+        time::Time,
+        GameStateRef, Random,
+    };
+
+    /// Distance in tiles other pawns doing the same work are counted as working together
+    pub const SHARED_WORK_RANGE: f32 = 8.0;
+
+    /// Pick the perceived pawn we like the most, nearest first on ties
+    pub fn find_conversation_partner(
+        state: GameStateRef,
+        args: &mut BehaviorArgs<'_>,
+    ) -> BehaviorStatus {
+        let graph = state.resources.get::<RelationshipGraph>().unwrap();
+
+        let partner = args
+            .senses
+            .perceived
+            .iter()
+            .filter(|percept| {
+                state.world.get_tag::<PawnTag>(percept.entity).is_some()
+                    && !graph.is_rival(args.entity, percept.entity)
+            })
+            .min_by_key(|percept| -graph.opinion(args.entity, percept.entity))
+            .map(|percept| percept.entity);
+
+        if let Some(partner) = partner {
+            args.blackboard.insert(fnv!("ConversationPartner"), partner);
+            args.blackboard
+                .insert(fnv!("MoveParameters"), MoveParameters::new_entity(partner));
+
+            return BehaviorStatus::success();
+        }
+
+        BehaviorStatus::failure()
+    }
+
+    pub fn converse(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let partner = match args
+            .blackboard
+            .get::<Entity>(fnv!("ConversationPartner"))
+            .copied()
+        {
+            Some(partner) if state.world.is_alive(partner) => partner,
+            _ => return BehaviorStatus::failure(),
+        };
+
+        let in_range = match (
+            state.world.get_component::<PositionComponent>(args.entity),
+            state.world.get_component::<PositionComponent>(partner),
+        ) {
+            (Some(a), Some(b)) => {
+                let d = **a - **b;
+                ((d.x * d.x + d.y * d.y + d.z * d.z) as f32).sqrt() <= CONVERSATION_RANGE
+            }
+            _ => false,
+        };
+        if !in_range {
+            return BehaviorStatus::failure();
+        }
+
+        let (channel, graph, random, time) = <(
+            Read<Channel<SocialEvent>>,
+            Read<RelationshipGraph>,
+            Read<Random>,
+            Read<Time>,
+        )>::fetch(state.resources);
+
+        let events =
+            conversation_events(&mut random.make(), state.world, &graph, args.entity, partner);
+        channel.write_iter(events.iter().copied()).unwrap();
+
+        for entity in &[args.entity, partner] {
+            if let Some(mut social) =
+                unsafe { state.world.get_component_mut_unchecked::<SocialComponent>(*entity) }
+            {
+                social.last_conversation = time.world_time;
+            }
+        }
+
+        args.blackboard.remove(fnv!("ConversationPartner"));
+
+        BehaviorStatus::success()
+    }
+
+    /// Move directly away from the nearest perceived rival
+    pub fn find_rival_escape(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
+        let (graph, map) = <(Read<RelationshipGraph>, Read<Map>)>::fetch(state.resources);
+
+        let rival = match args.senses.nearest(|percept| {
+            state.world.get_tag::<PawnTag>(percept.entity).is_some()
+                && graph.is_rival(args.entity, percept.entity)
+        }) {
+            Some(rival) => rival.position,
+            None => return BehaviorStatus::failure(),
+        };
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+
+        match rl_ai::pathfinding::step_away(&map, position, rival, AVOID_DISTANCE) {
+            Some(target) => {
+                args.blackboard
+                    .insert(fnv!("MoveParameters"), MoveParameters::new_tile(target));
+                BehaviorStatus::success()
+            }
+            None => BehaviorStatus::failure(),
+        }
+    }
+
+    /// Pawns nearby running the same behavior worked alongside us
+    pub fn share_work(state: GameStateRef, args: &BehaviorArgs<'_>) {
+        let (channel, running) =
+            <(Read<Channel<SocialEvent>>, Read<RunningBehaviors>)>::fetch(state.resources);
+
+        for percept in &args.senses.perceived {
+            if percept.distance > SHARED_WORK_RANGE
+                || state.world.get_tag::<PawnTag>(percept.entity).is_none()
+            {
+                continue;
+            }
+
+            let same_work = running.get(percept.entity).is_some()
+                && running.get(percept.entity) == args.tree.root.handle();
+
+            if same_work {
+                channel
+                    .write(SocialEvent::new(
+                        args.entity,
+                        percept.entity,
+                        SocialEventKind::SharedWork,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
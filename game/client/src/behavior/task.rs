@@ -1,51 +1,54 @@
-use super::nodes as general_nodes;
 use super::ExecuteReactionParameters;
 
 use rl_ai::{
-    bt::{self, make, BehaviorStatus},
+    action,
+    bt::{definition::BehaviorNodeRegistry, make, BehaviorStatus},
     task::TaskCache,
 };
-use rl_core::failure;
-
-pub fn build(storage: &mut bt::BehaviorStorage) -> Result<(), failure::Error> {
-    let do_task = make::if_else(
-        make::closure(None, nodes::find_task),
-        make::sequence(&[
-            make::selector(&[
-                make::closure(None, general_nodes::move_to),
-                make::not(make::closure(None, nodes::cancel_task)),
-            ]),
-            make::if_else(
-                make::sequence(&[
-                    make::closure(None, nodes::prepare_task_reaction_parameterss),
-                    make::closure(None, general_nodes::execute_reaction),
-                ]),
-                make::closure(None, nodes::complete_task),
-                make::not(make::closure(None, nodes::cancel_task)),
-            ),
-        ]),
-        make::sequence(&[
-            make::closure(None, general_nodes::try_get_reagent),
-            make::sub("pickup_item", &storage),
-        ]),
-    );
-    storage.insert("do_task", do_task);
 
-    Ok(())
+pub fn register(registry: &mut BehaviorNodeRegistry) {
+    registry.register("plan_task", action::nodes::make_plan_with(nodes::task_goal));
+    registry.register("find_task", make::closure(None, nodes::find_task));
+    registry.register("cancel_task", make::closure(None, nodes::cancel_task));
+    registry.register("complete_task", make::closure(None, nodes::complete_task));
+    registry.register(
+        "prepare_task_reaction_parameters",
+        make::closure(None, nodes::prepare_task_reaction_parameterss),
+    );
 }
 
 pub mod nodes {
     use super::*;
     use rl_ai::{
+        action::{WorldFact, WorldState},
         bt::*,
-        task::{HasTasksComponent, Task, TaskHandle, TaskResult},
+        task::{
+            HasTasksComponent, Task, TaskHandle, TaskReservationChange, TaskReservationEvent,
+            TaskResult,
+        },
         TaskPrioritiesComponent,
     };
     use rl_core::{
-        components::PositionComponent, data::bt::*, fnv, legion::prelude::*, GameStateRef,
+        components::PositionComponent, data::bt::*, event::Channel, fnv, legion::prelude::*,
+        time::Time, GameStateRef,
     };
     use rl_reaction::ReactionEntity;
 
+    fn reservation_changed(
+        state: GameStateRef,
+        entity: Entity,
+        handle: TaskHandle,
+        holder: Entity,
+        change: TaskReservationChange,
+    ) {
+        state
+            .resources
+            .get::<Channel<TaskReservationEvent>>()
+            .unwrap()
+            .write(TaskReservationEvent::new(entity, handle, holder, change))
+            .unwrap();
+    }
+
     pub fn find_task(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> BehaviorStatus {
         use rl_ai::task::FindBestTaskError;
 
@@ -87,7 +90,19 @@ pub mod nodes {
                 .unwrap(),
         ) {
             Ok((task_location, entry, handle)) => {
-                let task = entry.queue.get_mut().take(handle).unwrap();
+                let now = state.resources.get::<Time>().unwrap().world_time;
+                let task = entry
+                    .queue
+                    .get_mut()
+                    .take(handle, args.entity, now)
+                    .unwrap();
+                reservation_changed(
+                    state,
+                    entry.entity,
+                    handle,
+                    args.entity,
+                    TaskReservationChange::Taken,
+                );
 
                 args.blackboard
                     .insert(fnv!("current_task"), (entry.entity, handle, task));
@@ -100,8 +115,9 @@ pub mod nodes {
                 return BehaviorStatus::success();
             }
             Err(e) => {
-                if let FindBestTaskError::MissingReagent(kind) = e {
-                    args.blackboard.insert(fnv!("missing_reagent"), kind);
+                if let FindBestTaskError::MissingReagent(reagent, holder) = e {
+                    args.blackboard
+                        .insert(fnv!("missing_reagent"), (reagent, holder));
                     return BehaviorStatus::failure();
                 }
             }
@@ -110,6 +126,52 @@ pub mod nodes {
         BehaviorStatus::failure()
     }
 
+    /// Working the task we hold, or the best one available, fetching the tool it needs first.
+    /// Tasks missing any other reagent are left to be delivered by hand.
+    pub fn task_goal(state: GameStateRef, args: &mut BehaviorArgs<'_>) -> Option<WorldState> {
+        use rl_ai::task::FindBestTaskError;
+        use rl_core::{condition, defs::condition::Item};
+
+        let worked = |ability| WorldState::default().with(WorldFact::WorkedTask(ability), true);
+
+        if args.blackboard.contains(fnv!("current_task")) {
+            return Some(worked(None));
+        }
+
+        let position = **state
+            .world
+            .get_component::<PositionComponent>(args.entity)
+            .unwrap();
+        let best = state.resources.get::<TaskCache>().unwrap().find_best(
+            state,
+            args.entity,
+            position,
+            &state
+                .world
+                .get_component::<TaskPrioritiesComponent>(args.entity)
+                .unwrap(),
+        );
+
+        match best {
+            Ok(_) => Some(worked(None)),
+            Err(FindBestTaskError::MissingReagent(reagent, holder)) => {
+                let tool = condition::wanted_items(&reagent.conditions, false)
+                    .into_iter()
+                    .find_map(|item| match item {
+                        Item::Ability(ability) => Some(*ability),
+                        _ => None,
+                    });
+
+                if tool.is_none() {
+                    args.blackboard
+                        .insert(fnv!("missing_reagent"), (reagent, holder));
+                }
+                tool.map(|tool| worked(Some(tool)))
+            }
+            Err(_) => None,
+        }
+    }
+
     pub fn prepare_task_reaction_parameterss(
         state: GameStateRef,
         args: &mut BehaviorArgs<'_>,
@@ -150,7 +212,18 @@ pub mod nodes {
                 .world
                 .get_component::<HasTasksComponent>(current_task.0)
             {
-                if component.storage.get_mut().cancel(current_task.1) {
+                if component
+                    .storage
+                    .get_mut()
+                    .cancel(current_task.1, args.entity)
+                {
+                    reservation_changed(
+                        state,
+                        current_task.0,
+                        current_task.1,
+                        args.entity,
+                        TaskReservationChange::Released,
+                    );
                     result = BehaviorStatus::success();
                 }
             }
@@ -181,12 +254,21 @@ pub mod nodes {
                 .get_component::<HasTasksComponent>(current_task.0)
             {
                 if component.storage.get_mut().complete(current_task.1) {
+                    reservation_changed(
+                        state,
+                        current_task.0,
+                        current_task.1,
+                        args.entity,
+                        TaskReservationChange::Completed,
+                    );
                     result = BehaviorStatus::success();
                 }
             }
         }
 
         if result == BehaviorStatus::success() {
+            crate::behavior::social::nodes::share_work(state, args);
+
             let last_task = args
                 .blackboard
                 .remove_get::<(Entity, TaskHandle, Task)>(fnv!("current_task"));
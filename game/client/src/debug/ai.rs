@@ -1,5 +1,6 @@
 use rl_ai::{
     bt::{BehaviorStorage, BehaviorTreeComponent},
+    history::DecisionHistoryComponent,
     iaus::{decisions::DecisionStorage, Decision},
     utility::UtilityStateComponent,
 };
@@ -14,6 +15,7 @@ use rl_core::{
     event::Channel,
     inventory,
     legion::prelude::*,
+    skills::{Skill, SkillsComponent},
     strum::{AsStaticRef, IntoEnumIterator},
     Logging,
 };
@@ -141,6 +143,35 @@ pub fn build_decisions_window(world: &mut World, resources: &mut Resources) {
                         }
 
                         ui.columns(1, im_str!(""), false);
+
+                        if let Some(skills) = world.get_component::<SkillsComponent>(entity) {
+                            let mut skills = skills.iter().collect::<Vec<_>>();
+                            skills.sort_by_key(|(category, _)| *category);
+
+                            let columns =
+                                ImString::new(format!("PawnSkills_{}", name.name).as_str());
+                            ui.columns(3, &columns, true);
+                            ui.text("Skill");
+                            ui.next_column();
+                            ui.text("Level");
+                            ui.next_column();
+                            ui.text("Experience");
+                            ui.next_column();
+                            for (category, skill) in skills {
+                                ui.text(&format!("{:?}", category));
+                                ui.next_column();
+                                ui.text(&format!("{}", skill.level));
+                                ui.next_column();
+                                ui.text(&format!(
+                                    "{:.0}/{:.0}",
+                                    skill.experience,
+                                    Skill::experience_to_next(skill.level)
+                                ));
+                                ui.next_column();
+                            }
+
+                            ui.columns(1, im_str!(""), false);
+                        }
                     }
                 });
 
@@ -276,3 +307,200 @@ pub fn build_decisions_window(world: &mut World, resources: &mut Resources) {
         },
     );
 }
+
+fn history_path(name: &str, extension: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("data/decision_history_{}.{}", name, extension)
+}
+
+pub fn build_decision_history_window(world: &mut World, resources: &mut Resources) {
+    let query = <(Read<DecisionHistoryComponent>, TryRead<NameComponent>)>::query();
+
+    let mut selected = 0;
+    let mut export_result = String::new();
+
+    UiWindowSet::create_with(
+        world,
+        resources,
+        "decisionHistoryWindow",
+        true,
+        move |ui, _window_manager, world, resources, _command_buffer| {
+            let (behavior_storage, decision_storage) =
+                <(Read<BehaviorStorage>, Read<DecisionStorage>)>::fetch(&resources);
+
+            imgui::Window::new(im_str!("decisionHistoryWindow"))
+                .size([500.0, 400.0], Condition::Once)
+                .build(ui, || {
+                    let entities = query
+                        .iter_entities(world)
+                        .map(|(entity, (_, name))| {
+                            (
+                                entity,
+                                name.map_or_else(
+                                    || format!("{:?}", entity),
+                                    |name| name.name.clone(),
+                                ),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    if entities.is_empty() {
+                        ui.text("No decision history");
+                        return;
+                    }
+
+                    selected = selected.min(entities.len() - 1);
+                    let names = entities
+                        .iter()
+                        .map(|(_, name)| ImString::new(name.as_str()))
+                        .collect::<Vec<_>>();
+                    imgui::ComboBox::new(im_str!("Entity##DecisionHistory")).build_simple_string(
+                        ui,
+                        &mut selected,
+                        names.iter().collect::<Vec<_>>().as_slice(),
+                    );
+
+                    let (entity, name) = &entities[selected];
+                    let history = world
+                        .get_component::<DecisionHistoryComponent>(*entity)
+                        .unwrap();
+
+                    if ui.button(im_str!("Export JSON"), [0.0, 0.0]) {
+                        let path = history_path(name, "json");
+                        export_result = match history
+                            .to_json(&decision_storage, &behavior_storage)
+                            .and_then(|json| Ok(std::fs::write(&path, json)?))
+                        {
+                            Ok(()) => format!("Wrote {}", path),
+                            Err(e) => format!("Failed to write {}: {}", path, e),
+                        };
+                    }
+                    ui.same_line(0.0);
+                    if ui.button(im_str!("Export CSV"), [0.0, 0.0]) {
+                        let path = history_path(name, "csv");
+                        export_result = match std::fs::write(
+                            &path,
+                            history.to_csv(&decision_storage, &behavior_storage),
+                        ) {
+                            Ok(()) => format!("Wrote {}", path),
+                            Err(e) => format!("Failed to write {}: {}", path, e),
+                        };
+                    }
+                    if !export_result.is_empty() {
+                        ui.text(&export_result);
+                    }
+
+                    if imgui::CollapsingHeader::new(ui, im_str!("Timeline"))
+                        .default_open(true)
+                        .build()
+                    {
+                        let mut decisions = Vec::new();
+                        for record in history.rounds.iter().flat_map(|round| &round.decisions) {
+                            if !decisions.contains(&record.decision) {
+                                decisions.push(record.decision);
+                            }
+                        }
+
+                        // Rounds which skipped a decision carry its previous score forward
+                        for handle in decisions {
+                            let mut last = 0.0;
+                            let scores = history
+                                .rounds
+                                .iter()
+                                .map(|round| {
+                                    if let Some(record) = round
+                                        .decisions
+                                        .iter()
+                                        .find(|record| record.decision == handle)
+                                    {
+                                        last = record.score as f32;
+                                    }
+                                    last
+                                })
+                                .collect::<Vec<_>>();
+
+                            let label = ImString::new(
+                                decision_storage
+                                    .get(handle)
+                                    .map_or("NONAME", |decision| decision.name()),
+                            );
+                            imgui::PlotLines::new(ui, &label, &scores)
+                                .scale_min(0.0)
+                                .scale_max(1.0)
+                                .graph_size([0.0, 30.0])
+                                .build();
+                        }
+                    }
+
+                    if imgui::CollapsingHeader::new(ui, im_str!("Rounds")).build() {
+                        // Newest first
+                        for (n, round) in history.rounds.iter().enumerate().rev() {
+                            let chosen = round
+                                .chosen
+                                .and_then(|handle| decision_storage.get(handle))
+                                .map_or("None", |decision| decision.name());
+                            let behavior = round
+                                .behavior
+                                .and_then(|handle| behavior_storage.get_name(handle))
+                                .unwrap_or("None");
+
+                            let header = ImString::new(format!(
+                                "{:>8.1} {} -> {} ({:?})##round{}",
+                                round.time,
+                                chosen,
+                                behavior,
+                                round.status.result(),
+                                n
+                            ));
+                            if !imgui::CollapsingHeader::new(ui, &header).build() {
+                                continue;
+                            }
+
+                            let columns = ImString::new(format!("DecisionHistory_{}", n));
+                            ui.columns(4, &columns, true);
+                            ui.text("Decision");
+                            ui.next_column();
+                            ui.text("Consideration");
+                            ui.next_column();
+                            ui.text("Input");
+                            ui.next_column();
+                            ui.text("Output");
+                            ui.next_column();
+                            for record in &round.decisions {
+                                let decision = decision_storage.get(record.decision);
+
+                                ui.text(decision.map_or("NONAME", |decision| decision.name()));
+                                ui.next_column();
+                                ui.next_column();
+                                ui.next_column();
+                                ui.text(&format!("{:.3}", record.score));
+                                ui.next_column();
+
+                                for (i, consideration) in record.considerations.iter().enumerate() {
+                                    ui.next_column();
+                                    ui.text(
+                                        decision
+                                            .and_then(|decision| decision.considerations().get(i))
+                                            .map_or("NONAME", |consideration| consideration.name()),
+                                    );
+                                    ui.next_column();
+                                    ui.text(&consideration.input.map_or_else(
+                                        || "-".to_owned(),
+                                        |input| format!("{:.3}", input),
+                                    ));
+                                    ui.next_column();
+                                    ui.text(&format!("{:.3}", consideration.output));
+                                    ui.next_column();
+                                }
+                            }
+                            ui.columns(1, im_str!(""), false);
+                        }
+                    }
+                });
+
+            true
+        },
+    );
+}
@@ -340,7 +340,8 @@ pub fn build_debug_overlay(
                                                     &SpawnArguments::Item {
                                                         material: MaterialComponent::new(
                                                                 material_defs.get_by_name(material_defs.keys().nth(selected_material).unwrap(), ).unwrap().id(),
-                                                                MaterialState::Solid)
+                                                                MaterialState::Solid),
+                                                        quality: None,
                                                     },
                                                 ).unwrap();
                                         },
@@ -269,8 +269,11 @@ fn main() -> Result<(), failure::Error> {
         debug::build_debug_overlay(world, resources)?;
 
         debug::ai::build_decisions_window(world, resources);
+        debug::ai::build_decision_history_window(world, resources);
         rl_ui::tools::build_tools_overlay(world, resources)?;
         rl_ui::tasks::build_task_window(world, resources);
+        rl_ui::schedules::build_schedule_window(world, resources);
+        rl_ui::combat::build_combat_log_window(world, resources);
 
         #[cfg(all(not(feature = "opengl")))]
         {
@@ -1,10 +1,15 @@
-use rl_ai::HasTasksComponent;
+use rl_ai::{
+    combat::CombatComponent, history::DecisionHistoryComponent, orders::OrderQueueComponent,
+    HasTasksComponent,
+};
 use rl_core::defs::{
-    body::*, building::*, creature::*, foliage::*, item::*, material::*, race::*, workshop::*, *,
+    body::*, building::*, creature::*, foliage::*, item::*, labor::*, material::*, race::*,
+    thought::*, workshop::*, *,
 };
+use rl_core::schedule::{ScheduleComponent, ScheduleStorage};
 use rl_core::{
     components::*, data::SpawnEvent, event::Channel, failure, legion::prelude::*, map::Map,
-    time::Time, transform::Translation,
+    skills::SkillsComponent, time::Time, transform::Translation,
 };
 use rl_render_pod::sprite::{SpriteLayer, StaticSpriteTag};
 
@@ -38,7 +43,7 @@ impl Spawnable for WorkshopDefinition {
 
         let (world, tile) = target.from_map(&map);
 
-        Ok(command_buffer
+        let entities = command_buffer
             .insert(
                 (WorkshopTag, BuildingTag, SpriteLayer::Building),
                 vec![(
@@ -54,11 +59,15 @@ impl Spawnable for WorkshopDefinition {
                     self.building.sprite.make(),
                 )],
             )
-            .to_vec())
+            .to_vec();
+
+        command_buffer.add_component(entities[0], OrderQueueComponent::default());
+
+        Ok(entities)
     }
 }
 
-impl Spawnable for ItemDefinition {
+impl Spawnable for BuildingDefinition {
     fn spawn(
         &self,
         resources: &Resources,
@@ -68,12 +77,56 @@ impl Spawnable for ItemDefinition {
     ) -> Result<Vec<Entity>, failure::Error> {
         let map = resources.get::<Map>().unwrap();
 
-        let material = if let SpawnArguments::Item { material } = kind {
+        let material = if let SpawnArguments::Building { material } = kind {
             *material
         } else {
             panic!("Wrong kind to spawner")
         };
 
+        let (world, tile) = target.from_map(&map);
+
+        let entities = command_buffer
+            .insert(
+                (BuildingTag, SpriteLayer::Building),
+                vec![(
+                    EntityMeta::new(resources.get::<Time>().unwrap().stamp()),
+                    Translation(world),
+                    material,
+                    PositionComponent::new(tile),
+                    DimensionsComponent::with_tiles(self.dimensions),
+                    BuildingComponent::new(self.id),
+                    self.sprite.make(),
+                )],
+            )
+            .to_vec();
+
+        if self.properties.contains(BuildingProperty::IS_BED) {
+            command_buffer.add_component(entities[0], BedComponent::default());
+        }
+        if self.properties.contains(BuildingProperty::IS_SEAT) {
+            command_buffer.add_component(entities[0], ReservationComponent::default());
+        }
+
+        Ok(entities)
+    }
+}
+
+impl Spawnable for ItemDefinition {
+    fn spawn(
+        &self,
+        resources: &Resources,
+        command_buffer: &mut CommandBuffer,
+        target: SpawnTarget,
+        kind: &SpawnArguments,
+    ) -> Result<Vec<Entity>, failure::Error> {
+        let map = resources.get::<Map>().unwrap();
+
+        let (material, quality, corpse) = match kind {
+            SpawnArguments::Item { material, quality } => (*material, *quality, None),
+            SpawnArguments::Corpse { material, corpse } => (*material, None, Some(corpse.clone())),
+            _ => panic!("Wrong kind to spawner"),
+        };
+
         let result = match target {
             SpawnTarget::Entity(target_entity) => {
                 let entities = command_buffer
@@ -127,6 +180,14 @@ impl Spawnable for ItemDefinition {
             command_buffer.add_component::<ItemContainerComponent>(result[0], container);
         }
 
+        if let Some(quality) = quality {
+            command_buffer.add_component(result[0], ItemQualityComponent(quality));
+        }
+
+        if let Some(corpse) = corpse {
+            command_buffer.add_component(result[0], corpse);
+        }
+
         Ok(result)
     }
 }
@@ -193,7 +254,7 @@ impl Spawnable for CreatureDefinition {
             bt::{BehaviorStorage, BehaviorTreeComponent},
             iaus::decisions::DecisionStorage,
             task::TaskPrioritiesComponent,
-            SensesComponent,
+            MemoryComponent, SensesComponent,
         };
         use rl_core::{petgraph::visit::IntoNodeReferences, settings::Settings};
         use rl_render_pod::sprite::SparseSpriteArray;
@@ -217,21 +278,35 @@ impl Spawnable for CreatureDefinition {
         let race = self.race.fetch(&races).unwrap();
         let body = race.body.fetch(&bodies).unwrap();
 
-        let utilitycomp = rl_ai::utility::UtilityStateComponent::new(
-            0,
-            vec![
-                rl_ai::utility::DecisionEntry::with_behavior(
-                    behaviors.get_handle("idle").unwrap(),
-                    decisions.get_handle("idle").unwrap(),
-                    0.5,
-                ),
-                rl_ai::utility::DecisionEntry::with_behavior(
-                    behaviors.get_handle("try_graze").unwrap(),
-                    decisions.get_handle("hunger").unwrap(),
-                    0.5,
-                ),
-            ],
-        );
+        let entry = |behavior: &str, decision: &str| {
+            rl_ai::utility::DecisionEntry::with_behavior(
+                behaviors.get_handle(behavior).unwrap(),
+                decisions.get_handle(decision).unwrap(),
+                0.5,
+            )
+        };
+
+        let mut entries = vec![
+            entry("idle", "idle"),
+            entry("attack", "attack"),
+            entry("retreat", "retreat"),
+        ];
+        if self.behavior.grazes {
+            entries.push(entry("try_graze", "hunger"));
+        }
+        if self.behavior.drinks {
+            entries.push(entry("creature_drink", "thirst"));
+        }
+        if self.behavior.home_range.is_some() {
+            entries.push(entry("wander", "wander"));
+        }
+        if self.behavior.herd_range.is_some() {
+            entries.push(entry("herd", "herd"));
+        }
+        if self.behavior.flee_distance.is_some() {
+            entries.push(entry("flee", "flee"));
+        }
+        let utilitycomp = rl_ai::utility::UtilityStateComponent::new(0, entries);
 
         // TODO: make this a body spawner
         let carry = CarryComponent {
@@ -267,12 +342,17 @@ impl Spawnable for CreatureDefinition {
                     BehaviorTreeComponent::default(),
                     utilitycomp.clone(),
                     SensesComponent::default(),
-                    NeedsComponent::default(),
+                    NeedsComponent::from_race(race),
                     TaskPrioritiesComponent::default(),
                 )],
             )
             .to_vec();
 
+        command_buffer.add_component(entities[0], MemoryComponent::default());
+        command_buffer.add_component(entities[0], HomeComponent::new(tile));
+        command_buffer.add_component(entities[0], CombatComponent::default());
+        command_buffer.add_component(entities[0], DecisionHistoryComponent::default());
+
         if let Some(name) = name {
             command_buffer.add_component(entities[0], NameComponent::new(name));
         }
@@ -295,14 +375,15 @@ impl Spawnable for Pawn {
             bt::{BehaviorStorage, BehaviorTreeComponent},
             iaus::decisions::DecisionStorage,
             task::TaskPrioritiesComponent,
-            SensesComponent,
+            MemoryComponent, SensesComponent, SocialComponent,
         };
         use rl_core::{petgraph::visit::IntoNodeReferences, settings::Settings};
         use rl_render_pod::sprite::SparseSpriteArray;
 
-        let (races, bodies, behaviors, decisions, map) = <(
+        let (races, bodies, labors, behaviors, decisions, map) = <(
             Read<DefinitionStorage<RaceDefinition>>,
             Read<DefinitionStorage<BodyDefinition>>,
+            Read<DefinitionStorage<LaborDefinition>>,
             Read<BehaviorStorage>,
             Read<DecisionStorage>,
             Read<Map>,
@@ -325,7 +406,7 @@ impl Spawnable for Pawn {
                     0.5,
                 ),
                 rl_ai::utility::DecisionEntry::with_behavior(
-                    behaviors.get_handle("try_eat").unwrap(),
+                    behaviors.get_handle("plan_eat").unwrap(),
                     decisions.get_handle("hunger").unwrap(),
                     0.5,
                 ),
@@ -339,6 +420,31 @@ impl Spawnable for Pawn {
                     decisions.get_handle("work").unwrap(),
                     0.5,
                 ),
+                rl_ai::utility::DecisionEntry::with_behavior(
+                    behaviors.get_handle("socialize").unwrap(),
+                    decisions.get_handle("socialize").unwrap(),
+                    0.5,
+                ),
+                rl_ai::utility::DecisionEntry::with_behavior(
+                    behaviors.get_handle("avoid_rival").unwrap(),
+                    decisions.get_handle("avoidrival").unwrap(),
+                    0.5,
+                ),
+                rl_ai::utility::DecisionEntry::with_behavior(
+                    behaviors.get_handle("try_sleep").unwrap(),
+                    decisions.get_handle("sleep").unwrap(),
+                    0.5,
+                ),
+                rl_ai::utility::DecisionEntry::with_behavior(
+                    behaviors.get_handle("attack").unwrap(),
+                    decisions.get_handle("attack").unwrap(),
+                    0.5,
+                ),
+                rl_ai::utility::DecisionEntry::with_behavior(
+                    behaviors.get_handle("retreat").unwrap(),
+                    decisions.get_handle("retreat").unwrap(),
+                    0.5,
+                ),
             ],
         );
 
@@ -360,7 +466,7 @@ impl Spawnable for Pawn {
                 .collect(),
         };
 
-        Ok(command_buffer
+        let entities = command_buffer
             .insert(
                 (PawnTag, SpriteLayer::Pawn),
                 vec![(
@@ -379,11 +485,31 @@ impl Spawnable for Pawn {
                     BehaviorTreeComponent::default(),
                     utilitycomp.clone(),
                     SensesComponent::default(),
-                    NeedsComponent::default(),
-                    TaskPrioritiesComponent::default(),
+                    NeedsComponent::from_race(race),
+                    TaskPrioritiesComponent::new(&labors),
                 )],
             )
-            .to_vec())
+            .to_vec();
+
+        command_buffer.add_component(entities[0], MemoryComponent::default());
+        command_buffer.add_component(entities[0], SocialComponent::default());
+        command_buffer.add_component(entities[0], MoodComponent::default());
+        command_buffer.add_component(entities[0], SkillsComponent::default());
+        command_buffer.add_component(entities[0], CombatComponent::default());
+        command_buffer.add_component(entities[0], DecisionHistoryComponent::default());
+        command_buffer.add_component(
+            entities[0],
+            ScheduleComponent::new(resources.get::<ScheduleStorage>().unwrap().default),
+        );
+        command_buffer.add_component(
+            entities[0],
+            AttributesComponent::generate(
+                &mut resources.get::<rl_core::Random>().unwrap().make(),
+                race,
+            ),
+        );
+
+        Ok(entities)
     }
 }
 
@@ -408,12 +534,13 @@ pub fn build_system(
     let mut spawned_entities = Vec::default();
 
     Box::new(move |world, resources| {
-        let (channel, items, _, creatures, foliages) = <(
+        let (channel, items, _, creatures, foliages, buildings) = <(
             Read<Channel<SpawnEvent>>,
             Read<DefinitionStorage<ItemDefinition>>,
             Read<DefinitionStorage<WorkshopDefinition>>,
             Read<DefinitionStorage<CreatureDefinition>>,
             Read<DefinitionStorage<FoliageDefinition>>,
+            Read<DefinitionStorage<BuildingDefinition>>,
         )>::fetch(&resources);
 
         while let Some(event) = channel.read(listener) {
@@ -427,7 +554,7 @@ pub fn build_system(
                 SpawnKind::Pawn => Pawn::default()
                     .spawn(resources, &mut command_buffer, event.target, &event.kind)
                     .unwrap(),
-                SpawnKind::Item => items
+                SpawnKind::Item | SpawnKind::Corpse => items
                     .get(event.id.into())
                     .unwrap()
                     .spawn(resources, &mut command_buffer, event.target, &event.kind)
@@ -437,6 +564,11 @@ pub fn build_system(
                     .unwrap()
                     .spawn(resources, &mut command_buffer, event.target, &event.kind)
                     .unwrap(),
+                SpawnKind::Building => buildings
+                    .get(event.id.into())
+                    .unwrap()
+                    .spawn(resources, &mut command_buffer, event.target, &event.kind)
+                    .unwrap(),
                 _ => unimplemented!(),
             });
         }
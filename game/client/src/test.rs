@@ -5,6 +5,7 @@ use rl_core::{
     components::*,
     data::{CollisionKind, SpawnArguments},
     defs::{
+        building::BuildingDefinition,
         creature::CreatureDefinition,
         foliage::FoliageDefinition,
         item::ItemDefinition,
@@ -264,6 +265,7 @@ pub fn init_minimal_world(
             SpawnTarget::Position(SpawnPosition::Tile(coord)),
             &SpawnArguments::Item {
                 material: MaterialComponent::new(marble_id, MaterialState::Solid),
+                quality: None,
             },
         )
         .unwrap();
@@ -278,10 +280,47 @@ pub fn init_minimal_world(
             SpawnTarget::Position(SpawnPosition::Tile(coord)),
             &SpawnArguments::Item {
                 material: MaterialComponent::new(marble_id, MaterialState::Solid),
+                quality: None,
             },
         )
         .unwrap();
 
+    let coord = Vec3i::new(5, 8, 15);
+    item_storage
+        .get_by_name("Wheelbarrow")
+        .unwrap()
+        .spawn(
+            resources,
+            &mut command_buffer,
+            SpawnTarget::Position(SpawnPosition::Tile(coord)),
+            &SpawnArguments::Item {
+                material: MaterialComponent::new(marble_id, MaterialState::Solid),
+                quality: None,
+            },
+        )
+        .unwrap();
+
+    // A bed, leaving the other pawns to sleep on the floor, and a single seat at a table
+    let building_storage = <Read<DefinitionStorage<BuildingDefinition>>>::fetch(resources);
+    for (name, coord) in &[
+        ("Bed", Vec3i::new(7, 4, 15)),
+        ("Table", Vec3i::new(9, 4, 15)),
+        ("Chair", Vec3i::new(9, 5, 15)),
+    ] {
+        building_storage
+            .get_by_name(name)
+            .unwrap()
+            .spawn(
+                resources,
+                &mut command_buffer,
+                SpawnTarget::Position(SpawnPosition::Tile(*coord)),
+                &SpawnArguments::Building {
+                    material: MaterialComponent::new(marble_id, MaterialState::Solid),
+                },
+            )
+            .unwrap();
+    }
+
     command_buffer.write(world);
 
     Ok(())
@@ -327,6 +366,7 @@ where
     I: Iterator<Item = &'a Vec3i> + std::iter::ExactSizeIterator,
 {
     use crate::spawners::Pawn;
+    use rl_ai::social::{Kinship, RelationshipGraph};
 
     let mut command_buffer = CommandBuffer::new(world);
 
@@ -336,24 +376,36 @@ where
         .get_id("human")
         .unwrap();
 
-    positions.enumerate().for_each(|(n, coord)| {
-        Pawn::default()
-            .spawn(
-                resources,
-                &mut command_buffer,
-                SpawnTarget::Position(SpawnPosition::Tile(*coord)),
-                &SpawnArguments::Pawn {
-                    arguments: SpawnPawnArguments {
-                        name: format!("Pawn_{}", n),
-                        race: human_id,
+    let pawns = positions
+        .enumerate()
+        .map(|(n, coord)| {
+            Pawn::default()
+                .spawn(
+                    resources,
+                    &mut command_buffer,
+                    SpawnTarget::Position(SpawnPosition::Tile(*coord)),
+                    &SpawnArguments::Pawn {
+                        arguments: SpawnPawnArguments {
+                            name: format!("Pawn_{}", n),
+                            race: human_id,
+                        },
                     },
-                },
-            )
-            .unwrap();
-    });
+                )
+                .unwrap()[0]
+        })
+        .collect::<Vec<_>>();
 
     command_buffer.write(world);
 
+    // Pawns arrive in pairs of siblings
+    if !resources.contains::<RelationshipGraph>() {
+        resources.insert(RelationshipGraph::default());
+    }
+    let mut graph = resources.get_mut::<RelationshipGraph>().unwrap();
+    for pair in pawns.chunks_exact(2) {
+        graph.set_kinship(pair[0], pair[1], Kinship::Sibling);
+    }
+
     Ok(())
 }
 
@@ -1,12 +1,15 @@
 #![allow(unused_variables, clippy::cast_sign_loss)]
 
 use rl_core::{
+    components::PositionComponent,
     crossbeam::queue::SegQueue,
     defs::{
         material::{MaterialDefinition, MaterialState},
+        thought::{MoodComponent, ThoughtEvent, ThoughtTrigger},
         DefinitionStorage,
     },
     dispatcher::{DispatcherBuilder, RelativeStage, Stage},
+    event::Channel,
     failure,
     legion::prelude::*,
     map::Map,
@@ -34,6 +37,7 @@ pub fn bundle(
     builder: &mut DispatcherBuilder,
 ) -> Result<(), failure::Error> {
     builder.add_system(RelativeStage(Stage::Logic, 100), build_rain_system);
+    builder.add_system(RelativeStage(Stage::Logic, 100), build_rained_on_system);
     builder.add_system(RelativeStage(Stage::Logic, 101), build_liquid_evap_system);
     builder.add_system(RelativeStage(Stage::Logic, 101), build_liquid_soil_system);
     builder.add_system(
@@ -280,6 +284,41 @@ pub fn build_rain_system(_: &mut World, resources: &mut Resources) -> Box<dyn Sc
         )
 }
 
+/// World seconds between rained on thoughts while it keeps raining
+const RAINED_ON_INTERVAL: f64 = 600.0;
+
+pub fn build_rained_on_system(_: &mut World, resources: &mut Resources) -> Box<dyn Schedulable> {
+    if !resources.contains::<Channel<ThoughtEvent>>() {
+        resources.insert(Channel::<ThoughtEvent>::default());
+    }
+
+    let mut last_check = 0.0;
+
+    SystemBuilder::<()>::new("rained_on_system")
+        .read_resource::<Time>()
+        .read_resource::<Weather>()
+        .read_resource::<Map>()
+        .read_resource::<Channel<ThoughtEvent>>()
+        .with_query(<(Read<PositionComponent>, Read<MoodComponent>)>::query())
+        .build(move |_, world, (time, weather, map, channel), query| {
+            game_metrics::scope!("rained_on_system");
+
+            if weather.rain_frequency < 0.01 || time.world_time - last_check < RAINED_ON_INTERVAL {
+                return;
+            }
+            last_check = time.world_time;
+
+            for (entity, (position, _)) in query.iter_entities(world) {
+                // Anyone at or above the surface is out under the sky
+                if position.z <= map.height_at(Vec2i::new(position.x, position.y)) {
+                    channel
+                        .write(ThoughtEvent::new(entity, ThoughtTrigger::RainedOn, 0))
+                        .unwrap();
+                }
+            }
+        })
+}
+
 /*
 pub fn spawn_liquid(mut self, coord: Vec3i, material: MaterialDefinitionId, depth: u8) -> Self {
         let tile = self.map.get_mut(coord);
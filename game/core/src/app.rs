@@ -49,6 +49,9 @@ impl Application {
         game_state.resources.insert(game_metrics::Metrics::new(1));
 
         game_state.resources.insert(Time::default());
+        game_state
+            .resources
+            .insert(crate::schedule::ScheduleStorage::default());
         game_state.resources.insert(crate::Logging::default());
 
         let global_buffer = GlobalCommandBuffer::new(&mut game_state.world);
@@ -2,7 +2,9 @@ pub use crate::blackboard::BlackboardComponent;
 use crate::{
     data::{CollisionKind, DimensionsVec, PartGraphId},
     defs::{
+        body::BodyDefinitionId,
         foliage::FoliageKind,
+        material::MaterialDefinitionId,
         needs::{NeedKind, NEEDKIND_COUNT},
     },
     math::{Aabbi, Vec3i, Vec3iProxy, Vec3u},
@@ -109,12 +111,47 @@ pub struct DeadTag;
 #[uuid = "6ae294c4-2ab3-47b6-ae5e-47c1adf2bf81"]
 pub struct VirtualTaskTag;
 
+/// What is left of a creature after it dies, carried by its corpse item
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpseComponent {
+    pub name: String,
+    pub body: BodyDefinitionId,
+    /// Tissue materials the body was made of with how much of each, most first
+    pub tissues: SmallVec<[(MaterialDefinitionId, u32); 4]>,
+    pub died_at: f64,
+    /// 0.0 when fresh, the corpse has rotted away at 1.0
+    pub rot: f32,
+}
+impl CorpseComponent {
+    pub fn new(
+        name: String,
+        body: BodyDefinitionId,
+        tissues: SmallVec<[(MaterialDefinitionId, u32); 4]>,
+        died_at: f64,
+    ) -> Self {
+        Self {
+            name,
+            body,
+            tissues,
+            died_at,
+            rot: 0.0,
+        }
+    }
+
+    /// The most abundant tissue, which the corpse itself is made of
+    pub fn material(&self) -> Option<MaterialDefinitionId> {
+        self.tissues.first().map(|(material, _)| *material)
+    }
+}
+
 #[derive(
     Clone, Copy, PartialEq, Eq, Hash, Debug, failure::Fail, serde::Serialize, serde::Deserialize,
 )]
 pub enum MovementError {
     #[fail(display = "No path to target")]
     NoPath,
+    #[fail(display = "Unable to move")]
+    Immobile,
 }
 
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
@@ -481,9 +518,17 @@ impl Default for NeedsComponent {
     }
 }
 impl NeedsComponent {
-    // TODO: do it
-    pub fn from_race(_: &crate::defs::race::RaceDefinition) -> Self {
-        Self::default()
+    pub fn from_race(race: &crate::defs::race::RaceDefinition) -> Self {
+        let mut this = Self(init_array!(NEEDKIND_COUNT, NeedState::default()));
+
+        for rate in &race.needs {
+            this.get_mut(rate.kind).decays.push(NeedDecay::new(
+                rate.value,
+                Duration::from_secs_f64(rate.frequency),
+            ));
+        }
+
+        this
     }
 
     pub fn add(&mut self, kind: NeedKind, value: i32) {
@@ -502,3 +547,39 @@ impl NeedsComponent {
         self.0.get_mut(kind.as_usize()).unwrap()
     }
 }
+
+#[derive(TypeUuid, Shrinkwrap, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[shrinkwrap(mutable)]
+#[uuid = "49a882d9-98c7-46a9-86bb-aaf6d9435346"]
+pub struct AttributesComponent(pub crate::defs::race::Attributes);
+impl AttributesComponent {
+    pub fn generate<R: rand::Rng>(rng: &mut R, race: &crate::defs::race::RaceDefinition) -> Self {
+        Self(crate::defs::race::Attributes::generate(rng, race))
+    }
+}
+
+/// Present while a pawn is asleep, removed when it wakes, is moved or stops sleeping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SleepingComponent {
+    pub bed: Option<Entity>,
+    pub position: Vec3i,
+    pub started: f64,
+    /// Partial need restored but not yet applied
+    pub acc: f64,
+    /// Frame the sleeping behavior last ran on, it lapses once the behavior stops
+    pub renewed: u64,
+}
+impl SleepingComponent {
+    /// Frames sleep lasts without the behavior renewing it
+    pub const TIMEOUT: u64 = 2;
+
+    pub fn new(bed: Option<Entity>, position: Vec3i, started: f64, frame: u64) -> Self {
+        Self {
+            bed,
+            position,
+            started,
+            acc: 0.0,
+            renewed: frame,
+        }
+    }
+}
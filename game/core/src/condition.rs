@@ -1,46 +1,389 @@
-use crate::defs::{
-    common::Property,
-    condition::{Condition, ConditionSet, ConditionSetRef, Item, Operator, Tile, Value},
-    foliage::FoliageKind,
-    DefinitionStorage,
+use crate::{
+    components::{
+        ActivePickupComponent, FoliageTag, ItemContainerChildComponent, NeedsComponent,
+        PositionComponent, WorkshopTag,
+    },
+    defs::{
+        building::{BuildingComponent, BuildingDefinition},
+        condition::{
+            Comparison, Condition, ConditionSet, ConditionSetRef, Item, Nearby, Nutrition,
+            Operator, Subject, Tile, TimeOfDay, Value,
+        },
+        foliage::FoliageKind,
+        item::{ItemComponent, ItemDefinition, ItemQuality, ItemQualityComponent},
+        material::{MaterialComponent, MaterialDefinition},
+        needs::{get_nutrition_value, NeedKind},
+        Definition, DefinitionComponent, DefinitionStorage,
+    },
+    inventory::find_item_recursive,
+    map::{
+        spatial::{SpatialMap, StaticSpatialMap},
+        Map,
+    },
+    math::Vec3i,
+    rstar::AABB,
+    time::Time,
+    GameStateRef,
 };
 use legion::prelude::*;
+use smallvec::SmallVec;
+
+/// Hour of the day `time is day` starts at
+pub const DAY_START: f64 = 6.0;
+
+/// Hour of the day `time is night` starts at
+pub const NIGHT_START: f64 = 20.0;
+
+/// Entities which satisfied the conditions of a set, such as the tool found for `has item`
+pub type ConditionMatches = SmallVec<[Entity; 4]>;
 
 pub struct CheckConditionParameters<'a> {
     world: &'a World,
     resources: &'a Resources,
-    entity: Entity,
+    /// Who the conditions are checked for, `None` if that isn't known yet such as for a workshop
+    /// order, in which case conditions on `self` are assumed to hold
+    entity: Option<Entity>,
     target: Option<Entity>,
-    sets: &'a [ConditionSetRef],
+    /// The target tile, the target's own position unless given
+    tile: Option<Vec3i>,
+}
+impl<'a> CheckConditionParameters<'a> {
+    pub fn new(
+        world: &'a World,
+        resources: &'a Resources,
+        entity: Option<Entity>,
+        target: Option<Entity>,
+    ) -> Self {
+        let tile = target.and_then(|target| position(world, target));
+
+        Self {
+            world,
+            resources,
+            entity,
+            target,
+            tile,
+        }
+    }
+
+    pub fn with_tile(mut self, tile: Vec3i) -> Self {
+        self.tile = Some(tile);
+        self
+    }
+
+    fn subjects(&self, subject: Subject) -> SmallVec<[Entity; 2]> {
+        match subject {
+            Subject::Me => self.entity.into_iter().collect(),
+            Subject::Target => self.target.into_iter().collect(),
+            Subject::Any => self.entity.into_iter().chain(self.target).collect(),
+        }
+    }
+
+    fn target_tile(&self, subject: Subject) -> Option<Vec3i> {
+        if subject == Subject::Me {
+            None
+        } else {
+            self.tile
+        }
+    }
+
+    /// Where the subject is, and for the target also the target tile
+    fn positions(&self, subject: Subject) -> SmallVec<[Vec3i; 2]> {
+        let mut positions = self
+            .subjects(subject)
+            .into_iter()
+            .filter_map(|entity| position(self.world, entity))
+            .collect::<SmallVec<[Vec3i; 2]>>();
+
+        if let Some(tile) = self.target_tile(subject) {
+            if !positions.contains(&tile) {
+                positions.push(tile);
+            }
+        }
+
+        positions
+    }
+}
+
+fn position(world: &World, entity: Entity) -> Option<Vec3i> {
+    world
+        .get_component::<PositionComponent>(entity)
+        .map(|position| **position)
+}
+
+/// Everything else standing or lying on `tile`
+fn entities_at(resources: &Resources, tile: Vec3i) -> SmallVec<[Entity; 8]> {
+    let (spatial_map, static_spatial_map) =
+        <(Read<SpatialMap>, Read<StaticSpatialMap>)>::fetch(resources);
+    let point = PositionComponent::new(tile);
+
+    spatial_map
+        .locate_all_at_point(&point)
+        .chain(static_spatial_map.locate_all_at_point(&point))
+        .map(|entry| entry.entity)
+        .collect()
+}
+
+/// Everything within `range` tiles of `tile` on the same level
+fn entities_near(resources: &Resources, tile: Vec3i, range: u32) -> SmallVec<[Entity; 8]> {
+    let (spatial_map, static_spatial_map) =
+        <(Read<SpatialMap>, Read<StaticSpatialMap>)>::fetch(resources);
+    #[allow(clippy::cast_possible_wrap)]
+    let range = Vec3i::new(range as i32, range as i32, 0);
+    let area = AABB::from_corners(
+        PositionComponent::new(tile - range),
+        PositionComponent::new(tile + range),
+    );
+
+    spatial_map
+        .locate_in_envelope_intersecting(&area)
+        .chain(static_spatial_map.locate_in_envelope_intersecting(&area))
+        .map(|entry| entry.entity)
+        .collect()
 }
 
-pub fn check_tile(property: &Tile, state: &CheckConditionParameters) -> bool {
-    unimplemented!()
+/// Item names are matched ignoring case, with `_` standing in for a space
+fn name_matches(name: &str, ident: &str) -> bool {
+    name.len() == ident.len()
+        && name
+            .chars()
+            .zip(ident.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b) || (a == ' ' && b == '_'))
+}
+
+fn has_nutrition(world: &World, resources: &Resources, entity: Entity, kind: NeedKind) -> bool {
+    get_nutrition_value(GameStateRef { resources, world }, entity, kind)
+        .map_or(false, |nutrition| nutrition.start > 0)
+}
+
+/// Whether `entity` is an item described by `item`
+pub fn item_matches(world: &World, resources: &Resources, entity: Entity, item: &Item) -> bool {
+    let comp = match world.get_component::<ItemComponent>(entity) {
+        Some(comp) => comp,
+        None => return false,
+    };
+    let items = resources
+        .get::<DefinitionStorage<ItemDefinition>>()
+        .unwrap();
+    let def = comp.fetch(&items);
+
+    match item {
+        Item::Name(name) => name_matches(def.name(), name),
+        Item::Property(property) => def.properties.contains(*property),
+        Item::Ability(kind) => def.abilities.iter().any(|ability| ability.kind == *kind),
+        Item::Material(material) => {
+            let materials = resources
+                .get::<DefinitionStorage<MaterialDefinition>>()
+                .unwrap();
+            let matches = world
+                .get_component::<MaterialComponent>(entity)
+                .map_or(false, |comp| material.limit.matches(&comp, &materials));
+
+            matches == (material.op != Operator::False)
+        }
+        Item::Nutrition(Nutrition::Kind(Some(kind))) => {
+            has_nutrition(world, resources, entity, *kind)
+        }
+        Item::Nutrition(Nutrition::Kind(None)) => [NeedKind::Calories, NeedKind::Hydration]
+            .iter()
+            .any(|kind| has_nutrition(world, resources, entity, *kind)),
+        Item::Quality(comparison) => {
+            let quality = world
+                .get_component::<ItemQualityComponent>(entity)
+                .map_or_else(ItemQuality::default, |quality| **quality);
+
+            comparison.holds(quality as i32)
+        }
+    }
+}
+
+fn is_foliage(world: &World, entity: Entity, kind: FoliageKind) -> bool {
+    world
+        .get_tag::<FoliageTag>(entity)
+        .map_or(false, |foliage| **foliage == kind)
+}
+
+/// Buildings are matched by name like items, or `workshop` for any workshop
+fn is_building(world: &World, resources: &Resources, entity: Entity, name: &str) -> bool {
+    if name == "workshop" {
+        return world.get_tag::<WorkshopTag>(entity).is_some();
+    }
+
+    world
+        .get_component::<BuildingComponent>(entity)
+        .map_or(false, |building| {
+            let buildings = resources
+                .get::<DefinitionStorage<BuildingDefinition>>()
+                .unwrap();
+            name_matches(building.fetch(&buildings).name(), name)
+        })
+}
+
+pub fn check_tile(property: &Tile, subject: Subject, state: &CheckConditionParameters) -> bool {
+    let map = state.resources.get::<Map>().unwrap();
+    let Tile::Kind(kind) = property;
+
+    state
+        .positions(subject)
+        .into_iter()
+        .any(|tile| map.get(tile).kind == *kind)
+}
+
+/// Up to `count` matches, checking the subject itself then for the target anything on its tile
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub fn check_foliage(
+    property: &FoliageKind,
+    subject: Subject,
+    count: usize,
+    state: &CheckConditionParameters,
+) -> ConditionMatches {
+    let mut found = state
+        .subjects(subject)
+        .into_iter()
+        .filter(|entity| is_foliage(state.world, *entity, *property))
+        .take(count)
+        .collect::<ConditionMatches>();
+
+    if let Some(tile) = state.target_tile(subject) {
+        for entity in entities_at(state.resources, tile) {
+            if found.len() >= count {
+                break;
+            }
+            if is_foliage(state.world, entity, *property) && !found.contains(&entity) {
+                found.push(entity);
+            }
+        }
+    }
+
+    found
+}
+
+/// Up to `count` matches among the subject itself, anything it carries or contains and for the
+/// target anything on its tile
+pub fn check_item(
+    property: &Item,
+    subject: Subject,
+    count: usize,
+    state: &CheckConditionParameters,
+) -> ConditionMatches {
+    let world = state.world;
+    let matches = |entity: Entity| item_matches(world, state.resources, entity, property);
+    let mut found = ConditionMatches::new();
+
+    for entity in state.subjects(subject) {
+        if found.len() >= count {
+            return found;
+        }
+        if matches(entity) && !found.contains(&entity) {
+            found.push(entity);
+        }
+
+        find_item_recursive(entity, world, |_, (item, _)| {
+            if found.len() < count && matches(item) && !found.contains(&item) {
+                found.push(item);
+            }
+            found.len() >= count
+        });
+    }
+
+    if let Some(tile) = state.target_tile(subject) {
+        for entity in entities_at(state.resources, tile) {
+            if found.len() >= count {
+                break;
+            }
+            if matches(entity) && !found.contains(&entity) {
+                found.push(entity);
+            }
+        }
+    }
+
+    found
 }
 
-pub fn check_foliage(property: &FoliageKind, state: &CheckConditionParameters) -> bool {
-    unimplemented!()
+/// Whether any subject's need compares, subjects without needs never do
+pub fn check_need(
+    kind: NeedKind,
+    comparison: Comparison,
+    subject: Subject,
+    state: &CheckConditionParameters,
+) -> bool {
+    state.subjects(subject).into_iter().any(|entity| {
+        state
+            .world
+            .get_component::<NeedsComponent>(entity)
+            .map_or(false, |needs| comparison.holds(needs.get(kind).value))
+    })
 }
 
-pub fn check_item(property: &Item, state: &CheckConditionParameters) -> bool {
-    unimplemented!()
+#[allow(clippy::cast_possible_truncation)]
+pub fn check_time(time_of_day: &TimeOfDay, state: &CheckConditionParameters) -> bool {
+    let hour = state.resources.get::<Time>().unwrap().hour_of_day();
+
+    match time_of_day {
+        TimeOfDay::Day => hour >= DAY_START && hour < NIGHT_START,
+        TimeOfDay::Night => hour < DAY_START || hour >= NIGHT_START,
+        TimeOfDay::Hour(comparison) => comparison.holds(hour.floor() as i32),
+    }
 }
 
-pub fn check_property(property: &Property, state: &CheckConditionParameters) -> bool {
-    unimplemented!()
+/// The first thing within `range` of the subject, or for the target of the target tile
+pub fn check_near(
+    what: &Nearby,
+    range: u32,
+    subject: Subject,
+    state: &CheckConditionParameters,
+) -> Option<Entity> {
+    let (world, resources) = (state.world, state.resources);
+    let subjects = state.subjects(subject);
+
+    state.positions(subject).into_iter().find_map(|tile| {
+        entities_near(resources, tile, range)
+            .into_iter()
+            .filter(|entity| !subjects.contains(entity))
+            .find(|entity| match what {
+                Nearby::Building(name) => is_building(world, resources, *entity, name),
+                Nearby::Item(item) => item_matches(world, resources, *entity, item),
+                Nearby::Foliage(kind) => is_foliage(world, *entity, *kind),
+            })
+    })
 }
 
 pub fn check_condition<'a, 'p>(
     condition: &'a Condition,
     state: &'p CheckConditionParameters,
-) -> Result<(), &'a Condition> {
-    if match &condition.value {
-        Value::Tile(value) => check_tile(value, state),
-        Value::Item(value) => check_item(value, state),
-        Value::Foliage(value) => check_foliage(value, state),
-        Value::Property(value) => check_property(value, state),
-    } {
-        Ok(())
+) -> Result<ConditionMatches, &'a Condition> {
+    if condition.subject == Subject::Me && state.entity.is_none() {
+        return Ok(ConditionMatches::new());
+    }
+
+    let (subject, count) = (condition.subject, condition.count.max(1));
+    let (holds, found) = match &condition.value {
+        Value::Tile(value) => (check_tile(value, subject, state), ConditionMatches::new()),
+        Value::Item(value) => {
+            let found = check_item(value, subject, count, state);
+            (found.len() >= count, found)
+        }
+        Value::Foliage(value) => {
+            let found = check_foliage(value, subject, count, state);
+            (found.len() >= count, found)
+        }
+        Value::Need(kind, comparison) => (
+            check_need(*kind, *comparison, subject, state),
+            ConditionMatches::new(),
+        ),
+        Value::Time(value) => (check_time(value, state), ConditionMatches::new()),
+        Value::Near { what, range } => {
+            let found = check_near(what, *range, subject, state);
+            (found.is_some(), found.into_iter().collect())
+        }
+    };
+
+    if holds == (condition.op != Operator::False) {
+        // Nothing was found to satisfy a negated condition
+        Ok(if holds {
+            found
+        } else {
+            ConditionMatches::new()
+        })
     } else {
         Err(condition)
     }
@@ -49,113 +392,261 @@ pub fn check_condition<'a, 'p>(
 pub fn check_set<'a, 'p>(
     set: &'a ConditionSet,
     state: &'p CheckConditionParameters,
-) -> Result<(), &'a Condition> {
+) -> Result<ConditionMatches, &'a Condition> {
     let left_res = check_condition(&set.left, state);
-    if let Some(right) = set.right.as_ref() {
-        match right.op {
-            Operator::And => {
-                if left_res.is_ok() {
-                    check_set(&right.set, state)?;
-                } else {
-                    return left_res;
-                }
-            }
-            Operator::Or => {
-                if left_res.is_err() {
-                    check_set(&right.set, state)?;
-                } else {
-                    return left_res;
-                }
-            }
-            _ => panic!("invalid op for set"),
+    let right = match set.right.as_ref() {
+        Some(right) => right,
+        None => return left_res,
+    };
+
+    match right.op {
+        Operator::And => {
+            let mut found = left_res?;
+            found.extend(check_set(&right.set, state)?);
+            Ok(found)
         }
+        Operator::Or => left_res.or_else(|_| check_set(&right.set, state)),
+        _ => panic!("invalid op for set"),
     }
+}
 
-    Ok(())
+/// Items the positive conditions of a reagent ask for, either of the target or of whoever works
+/// the reaction, to know what to fetch when it is missing
+pub fn wanted_items(sets: &[ConditionSetRef], target: bool) -> SmallVec<[&Item; 4]> {
+    sets.iter()
+        .flat_map(|set| set.iter())
+        .filter(|condition| {
+            condition.op != Operator::False && (condition.subject == Subject::Target) == target
+        })
+        .filter_map(|condition| match &condition.value {
+            Value::Item(item) => Some(item),
+            _ => None,
+        })
+        .collect()
 }
 
-pub fn check<'a, 's>(
-    world: &'a World,
-    resources: &'a Resources,
-    entity: Entity,
-    target: Option<Entity>,
+/// The nearest item to `from` lying loose for someone to pick up which is any of `wanted`,
+/// ignoring whatever already lies on `skip`
+pub fn find_loose_item(
+    world: &World,
+    resources: &Resources,
+    from: Vec3i,
+    wanted: &[&Item],
+    skip: Option<Vec3i>,
+) -> Option<Entity> {
+    let spatial_map = resources.get::<SpatialMap>().unwrap();
+
+    spatial_map
+        .nearest_neighbor_iter(&PositionComponent::new(from))
+        .filter(|entry| skip != Some(entry.position()))
+        .map(|entry| entry.entity)
+        .find(|entity| {
+            !world.has_component::<ItemContainerChildComponent>(*entity)
+                && !world.has_component::<ActivePickupComponent>(*entity)
+                && wanted
+                    .iter()
+                    .any(|item| item_matches(world, resources, *entity, item))
+        })
+}
+
+/// Check every set holds, returning the entities which satisfied them or the first condition
+/// which failed
+pub fn check<'s>(
+    state: &CheckConditionParameters,
     sets: &'s [ConditionSetRef],
-) -> Result<(), &'s Condition> {
-    let state = CheckConditionParameters {
-        world,
-        resources,
-        entity,
-        target,
-        sets,
-    };
+) -> Result<ConditionMatches, &'s Condition> {
+    let mut found = ConditionMatches::new();
     for set in sets {
-        check_set(&set, &state)?;
+        found.extend(check_set(set, state)?);
     }
 
-    unimplemented!()
+    Ok(found)
 }
 
-/*
-#[allow(clippy::trivially_copy_pass_by_ref)]
-fn check_item_materials(
-    _state: GameStateRef,
-    _source: Entity,
-    _item: &ItemComponent,
-    _limits: &[MaterialLimit],
-) -> bool {
-    true
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::ItemContainerComponent, data::CollisionKind, map::spatial::SpatialMapEntry,
+        GameState,
+    };
 
-pub fn check_item_available(
-    state: GameStateRef,
-    source: Entity,
-    item: &rl_core::defs::item::ItemRef,
-    limits: &[MaterialLimit],
-) -> Result<Entity, ()> {
-    rl_core::inventory::find_item_recursive(source, state.world, |_, (_, comp)| {
-        comp.id() == item.id() && check_item_materials(state, source, &comp, limits)
-    })
-    .map(|(_, item)| item)
-    .ok_or(())
-}
-
-pub fn check_item_property(
-    state: GameStateRef,
-    source: Entity,
-    property: rl_core::defs::item::ItemProperty,
-    limits: &[MaterialLimit],
-) -> Result<Entity, ()> {
-    let item_defs = state
-        .resources
-        .get::<DefinitionStorage<ItemDefinition>>()
-        .unwrap();
+    fn setup() -> Result<GameState, failure::Error> {
+        let mut state = GameState::default();
 
-    rl_core::inventory::find_item_recursive(source, state.world, |_, (_, comp)| {
-        comp.fetch(&item_defs).properties.contains(property)
-            && check_item_materials(state, source, &comp, limits)
-    })
-    .map(|(_, item)| item)
-    .ok_or(())
-}
-
-pub fn check_item_ability(
-    state: GameStateRef,
-    source: Entity,
-    ability: rl_core::defs::item::ItemAbilityKind,
-    quality: u32,
-    limits: &[MaterialLimit],
-) -> Result<Entity, ()> {
-    let item_defs = state
-        .resources
-        .get::<DefinitionStorage<ItemDefinition>>()
-        .unwrap();
+        DefinitionStorage::<MaterialDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/materials",
+        )?;
+        DefinitionStorage::<ItemDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/items",
+        )?;
+        state.resources.insert(SpatialMap::default());
+        state.resources.insert(StaticSpatialMap::default());
 
-    rl_core::inventory::find_item_recursive(source, state.world, |_, (_, comp)| {
-        comp.fetch(&item_defs)
-            .abilities
-            .contains(&ItemAbility::new(ability, quality))
-            && check_item_materials(state, source, &comp, limits)
-    })
-    .map(|(_, item)| item)
-    .ok_or(())
-}*/
+        Ok(state)
+    }
+
+    fn spawn(state: &mut GameState, tile: Vec3i) -> Entity {
+        let entity = state
+            .world
+            .insert((), vec![(PositionComponent::new(tile),)])[0];
+        state
+            .resources
+            .get_mut::<SpatialMap>()
+            .unwrap()
+            .insert(SpatialMapEntry::new_single(
+                entity,
+                tile,
+                CollisionKind::None,
+            ));
+
+        entity
+    }
+
+    fn spawn_item(state: &mut GameState, name: &str, tile: Vec3i) -> Entity {
+        let id = state
+            .resources
+            .get::<DefinitionStorage<ItemDefinition>>()
+            .unwrap()
+            .get_id(name)
+            .unwrap();
+        let entity = spawn(state, tile);
+        state
+            .world
+            .add_component(entity, ItemComponent::new(id))
+            .unwrap();
+
+        entity
+    }
+
+    /// Whether the condition holds for `(entity, target)`
+    fn holds(
+        state: &GameState,
+        subjects: (Option<Entity>, Option<Entity>),
+        condition: &str,
+    ) -> bool {
+        let mut set = ConditionSetRef::new(condition);
+        set.parse().unwrap();
+
+        let parameters =
+            CheckConditionParameters::new(&state.world, &state.resources, subjects.0, subjects.1);
+        check(&parameters, &[set]).is_ok()
+    }
+
+    #[test]
+    fn check_subjects_test() -> Result<(), failure::Error> {
+        let mut state = setup()?;
+        let workshop = spawn(&mut state, Vec3i::new(0, 0, 0));
+        let pawn = spawn(&mut state, Vec3i::new(5, 5, 0));
+        spawn_item(&mut state, "Log", Vec3i::new(0, 0, 0));
+        let both = (Some(pawn), Some(workshop));
+
+        // Anything on the target tile counts for the target
+        assert!(holds(&state, both, "target has item log"));
+        assert!(holds(&state, both, "any has item log"));
+        assert!(!holds(&state, both, "self has item log"));
+
+        // Without anyone to do it yet, conditions on them are assumed to hold
+        assert!(holds(&state, (None, Some(workshop)), "self has item log"));
+
+        // Contents count, but only for whoever holds them
+        let knife = spawn_item(&mut state, "Knife", Vec3i::new(5, 5, 0));
+        let mut container = ItemContainerComponent::default();
+        container.inside.push(knife);
+        state.world.add_component(pawn, container).unwrap();
+        assert!(holds(&state, both, "self has item knife"));
+        assert!(holds(&state, both, "target !has item knife"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_tile_test() -> Result<(), failure::Error> {
+        let mut state = setup()?;
+        let log = spawn_item(&mut state, "Log", Vec3i::new(2, 0, 0));
+
+        let on_tile = |tile| {
+            let parameters =
+                CheckConditionParameters::new(&state.world, &state.resources, None, None)
+                    .with_tile(tile);
+            let mut set = ConditionSetRef::new("target is item log");
+            set.parse().unwrap();
+
+            check(&parameters, &[set]).map_err(|_| ())
+        };
+        assert_eq!(on_tile(Vec3i::new(2, 0, 0)).unwrap().as_slice(), &[log]);
+        assert!(on_tile(Vec3i::new(3, 0, 0)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_count_and_near_test() -> Result<(), failure::Error> {
+        let mut state = setup()?;
+        let workshop = spawn(&mut state, Vec3i::new(0, 0, 0));
+        let pawn = spawn(&mut state, Vec3i::new(4, 0, 0));
+        spawn_item(&mut state, "Log", Vec3i::new(0, 0, 0));
+
+        assert!(!holds(
+            &state,
+            (None, Some(workshop)),
+            "target has 2 item log"
+        ));
+        spawn_item(&mut state, "Log", Vec3i::new(0, 0, 0));
+        assert!(holds(
+            &state,
+            (None, Some(workshop)),
+            "target has 2 item log"
+        ));
+
+        assert!(holds(&state, (Some(pawn), None), "near item log within 4"));
+        assert!(!holds(&state, (Some(pawn), None), "near item log within 3"));
+        assert!(holds(&state, (Some(pawn), None), "!near item log within 3"));
+
+        let both = (Some(pawn), Some(workshop));
+        let mut needs = NeedsComponent::default();
+        needs.get_mut(NeedKind::Calories).value = 100;
+        state.world.add_component(pawn, needs).unwrap();
+        assert!(holds(&state, both, "need calories < 200"));
+        assert!(!holds(&state, both, "target need calories < 200"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_loose_item_test() -> Result<(), failure::Error> {
+        let mut state = setup()?;
+        let far = spawn_item(&mut state, "Log", Vec3i::new(6, 0, 0));
+        let near = spawn_item(&mut state, "Log", Vec3i::new(2, 0, 0));
+
+        let mut sets = vec![ConditionSetRef::new(
+            "target is item log & self has item axe",
+        )];
+        sets[0].parse()?;
+        let wanted = wanted_items(&sets, true);
+        assert_eq!(wanted.as_slice(), &[&Item::Name("log".to_owned())]);
+        assert_eq!(
+            wanted_items(&sets, false).as_slice(),
+            &[&Item::Name("axe".to_owned())]
+        );
+
+        let from = Vec3i::new(0, 0, 0);
+        let find = |state: &GameState, skip| {
+            find_loose_item(&state.world, &state.resources, from, &wanted, skip)
+        };
+        assert_eq!(find(&state, None), Some(near));
+        assert_eq!(find(&state, Some(Vec3i::new(2, 0, 0))), Some(far));
+
+        // Someone is already on their way to pick it up
+        let hauler = spawn(&mut state, from);
+        state
+            .world
+            .add_component(near, ActivePickupComponent::new(hauler, 0.0))
+            .unwrap();
+        assert_eq!(find(&state, None), Some(far));
+
+        Ok(())
+    }
+}
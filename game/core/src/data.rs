@@ -1,6 +1,6 @@
 use crate::{
-    components::{DimensionsComponent, MovementRequest},
-    defs::{material::MaterialComponent, race::RaceDefinitionId},
+    components::{CorpseComponent, DimensionsComponent, MovementRequest},
+    defs::{item::ItemQuality, material::MaterialComponent, race::RaceDefinitionId},
     map::Map,
     math::{Vec3, Vec3i, Vec3u, Vec3uProxy},
 };
@@ -59,6 +59,17 @@ pub mod bt {
         pub target_tile: Vec3i,
     }
 
+    /// Items hauled together to one stockpile in a carried container
+    #[derive(Clone, Debug)]
+    pub struct HaulBatch {
+        pub container: Entity,
+        pub stockpile: Entity,
+        /// Each item and the stockpile tile reserved for it
+        pub items: crate::smallvec::SmallVec<[(Entity, Vec3i); 8]>,
+        /// Set once everything which could be loaded is in the container
+        pub unloading: bool,
+    }
+
     #[derive(Copy, Clone, Debug)]
     pub enum PickupDestination {
         Container(Entity),
@@ -134,10 +145,20 @@ impl SpawnTarget {
 pub enum SpawnArguments {
     Item {
         material: MaterialComponent,
+        /// Items made by a pawn carry the quality it managed, anything else has none
+        quality: Option<ItemQuality>,
+    },
+    /// A corpse item, spawned from the item definition like any other item
+    Corpse {
+        material: MaterialComponent,
+        corpse: CorpseComponent,
     },
     Workshop {
         material: MaterialComponent,
     },
+    Building {
+        material: MaterialComponent,
+    },
     Creature {
         name: Option<String>,
     },
@@ -167,6 +188,33 @@ where
     pub arguments: T,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoiseKind {
+    Digging,
+    Chopping,
+    Combat,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseEvent {
+    pub source: Option<Entity>,
+    pub position: Vec3i,
+    pub kind: NoiseKind,
+    /// Distance in tiles the noise carries
+    pub volume: u32,
+}
+impl NoiseEvent {
+    pub fn new(source: Option<Entity>, position: Vec3i, kind: NoiseKind, volume: u32) -> Self {
+        Self {
+            source,
+            position,
+            kind,
+            volume,
+        }
+    }
+}
+
 #[derive(
     shrinkwraprs::Shrinkwrap,
     Debug,
@@ -0,0 +1,66 @@
+use crate::defs::DefinitionDetails;
+use rl_macros::Definition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum LoopCondition {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum BehaviorNodeDefinition {
+    /// A leaf node registered from code
+    Node(String),
+
+    Sequence(Vec<BehaviorNodeDefinition>),
+    Selector(Vec<BehaviorNodeDefinition>),
+    All(Vec<BehaviorNodeDefinition>),
+    Not(Box<BehaviorNodeDefinition>),
+    IfElse {
+        condition: Box<BehaviorNodeDefinition>,
+        success: Box<BehaviorNodeDefinition>,
+        failure: Box<BehaviorNodeDefinition>,
+    },
+    TryUntil {
+        limit: usize,
+        until: LoopCondition,
+        node: Box<BehaviorNodeDefinition>,
+    },
+
+    /// Evaluate another behavior in place
+    Sub(String),
+    /// Replace the running behavior with another one
+    Switch(String),
+}
+impl BehaviorNodeDefinition {
+    pub fn visit<F: FnMut(&Self)>(&self, f: &mut F) {
+        f(self);
+
+        match self {
+            Self::Sequence(children) | Self::Selector(children) | Self::All(children) => {
+                children.iter().for_each(|child| child.visit(f));
+            }
+            Self::Not(node) | Self::TryUntil { node, .. } => node.visit(f),
+            Self::IfElse {
+                condition,
+                success,
+                failure,
+            } => {
+                condition.visit(f);
+                success.visit(f);
+                failure.visit(f);
+            }
+            Self::Node(_) | Self::Sub(_) | Self::Switch(_) => {}
+        }
+    }
+}
+
+#[derive(Definition, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct BehaviorDefinition {
+    pub details: DefinitionDetails,
+
+    #[serde(skip)]
+    pub id: BehaviorDefinitionId,
+
+    pub root: BehaviorNodeDefinition,
+}
@@ -1,10 +1,10 @@
 use crate::defs::{
-    material::MaterialRef, DefinitionDetails, DefinitionLoader, DefinitionResolver,
-    DefinitionStorage,
+    material::{MaterialDefinition, MaterialDefinitionId, MaterialRef},
+    DefinitionDetails, DefinitionLoader, DefinitionResolver, DefinitionStorage,
 };
 use crate::{
     bitflags::*, bitflags_serial, data::PartGraphId, failure, fxhash::FxHashMap,
-    legion::prelude::*, petgraph, ron,
+    legion::prelude::*, petgraph, ron, smallvec::SmallVec,
 };
 use rl_macros::Definition;
 use std::{path::Path, sync::Arc};
@@ -28,6 +28,8 @@ bitflags_serial! {
         const CIRCULATION   = 1 << 13;
         const RESPITORY     = 1 << 14;
 
+        const WEAPON        = 1 << 15;
+
     }
 }
 
@@ -60,6 +62,19 @@ pub enum PartLayerKind {
     },
 }
 
+/// How a blow is delivered, which decides what resists it as it passes through layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum AttackKind {
+    Blunt,
+    Edged,
+    Pierce,
+}
+impl Default for AttackKind {
+    fn default() -> Self {
+        Self::Blunt
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct PartLayer {
     pub material: MaterialRef,
@@ -68,6 +83,7 @@ pub struct PartLayer {
 }
 
 #[derive(Definition, Debug, serde::Deserialize, serde::Serialize)]
+#[definition(resolver = "Self")]
 pub struct PartDefinition {
     pub details: DefinitionDetails,
     #[serde(skip)]
@@ -78,7 +94,12 @@ pub struct PartDefinition {
 
     pub relative_size: u32,
     pub flags: PartFlag,
+    /// Outermost first
     pub layers: Vec<PartLayer>,
+
+    /// How a `WEAPON` part strikes, `MANIPULATE` parts without one punch
+    #[serde(default)]
+    pub attack: Option<AttackKind>,
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -171,14 +192,89 @@ pub struct BodyDefinition {
     pub graph: petgraph::graph::UnGraph<Arc<PartDefinition>, PartRelation>,
 }
 
-#[derive(Debug, Default, Copy, Clone)]
-pub struct PartState;
+/// Parts below this fraction of their thickness stop working
+pub const FUNCTIONAL_HEALTH: f32 = 0.25;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Wound {
+    /// Thickness of the part torn open, reduced as it heals
+    pub severity: f32,
+    /// Blood lost per second
+    pub bleeding: f32,
+    /// 0.0 is clean, the part is lost at 1.0
+    pub infection: f32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PartState {
+    /// Thickness lost from each layer, outermost first
+    pub layer_damage: SmallVec<[u32; 4]>,
+    pub wounds: SmallVec<[Wound; 2]>,
+    /// Severed, crushed or rotted away, destroyed parts never heal
+    pub destroyed: bool,
+}
+impl PartState {
+    pub fn new(part: &PartDefinition) -> Self {
+        Self {
+            layer_damage: part.layers.iter().map(|_| 0).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Fraction of the part's thickness remaining
+    pub fn health(&self, part: &PartDefinition) -> f32 {
+        if self.destroyed {
+            return 0.0;
+        }
+
+        let total = part.layers.iter().map(|l| l.thickness).sum::<u32>();
+        if total == 0 {
+            return 1.0;
+        }
+        let lost = part
+            .layers
+            .iter()
+            .zip(self.layer_damage.iter())
+            .map(|(layer, damage)| (*damage).min(layer.thickness))
+            .sum::<u32>();
+
+        1.0 - lost as f32 / total as f32
+    }
+
+    pub fn is_functional(&self, part: &PartDefinition) -> bool {
+        !self.destroyed && self.health(part) > FUNCTIONAL_HEALTH
+    }
+
+    pub fn bleeding(&self) -> f32 {
+        self.wounds.iter().map(|w| w.bleeding).sum()
+    }
+
+    pub fn infection(&self) -> f32 {
+        self.wounds.iter().map(|w| w.infection).fold(0.0, f32::max)
+    }
+
+    /// Remove `amount` thickness from a layer, destroying the part once nothing is left
+    pub fn damage_layer(&mut self, part: &PartDefinition, layer: usize, amount: u32) {
+        if self.layer_damage.len() < part.layers.len() {
+            self.layer_damage.resize(part.layers.len(), 0);
+        }
+        if let Some(damage) = self.layer_damage.get_mut(layer) {
+            *damage = (*damage + amount).min(part.layers[layer].thickness);
+        }
+        if self.health(part) <= 0.0 {
+            self.destroyed = true;
+        }
+    }
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct BodyComponent {
     def: BodyDefinitionId,
     pub flags: PartFlag,
     pub part_states: FxHashMap<PartGraphId, PartState>,
+    pub blood: f32,
+    /// Blood of an unharmed body, the sum of its part sizes
+    pub max_blood: f32,
 }
 impl BodyComponent {
     pub fn new(
@@ -188,12 +284,16 @@ impl BodyComponent {
         let body = def.fetch(storage);
         let mut flags = PartFlag::empty();
 
+        let mut max_blood = 0.0;
+
         let part_states = body
             .graph
             .node_indices()
             .map(|idx| {
-                flags |= body.graph.node_weight(idx).unwrap().flags;
-                (idx, PartState::default())
+                let part = body.graph.node_weight(idx).unwrap();
+                flags |= part.flags;
+                max_blood += part.relative_size as f32;
+                (idx, PartState::new(part))
             })
             .collect();
 
@@ -201,7 +301,119 @@ impl BodyComponent {
             def,
             part_states,
             flags,
+            blood: max_blood,
+            max_blood,
+        }
+    }
+
+    fn parts_with<'a>(
+        &'a self,
+        body: &'a BodyDefinition,
+        flag: PartFlag,
+    ) -> impl Iterator<Item = (&'a PartDefinition, &'a PartState)> + 'a {
+        self.part_states.iter().filter_map(move |(idx, state)| {
+            body.graph
+                .node_weight(*idx)
+                .filter(|part| part.flags.contains(flag))
+                .map(|part| (&**part, state))
+        })
+    }
+
+    /// Number of working parts with `flag`
+    pub fn count_parts(&self, body: &BodyDefinition, flag: PartFlag) -> usize {
+        self.parts_with(body, flag)
+            .filter(|(part, state)| state.is_functional(part))
+            .count()
+    }
+
+    /// Fraction of the parts with `flag` still working, 1.0 if the body has none
+    pub fn functional_fraction(&self, body: &BodyDefinition, flag: PartFlag) -> f32 {
+        let (total, working) =
+            self.parts_with(body, flag)
+                .fold((0, 0), |(total, working), (part, state)| {
+                    (total + 1, working + usize::from(state.is_functional(part)))
+                });
+
+        if total == 0 {
+            1.0
+        } else {
+            working as f32 / total as f32
+        }
+    }
+
+    pub fn is_part_functional(&self, body: &BodyDefinition, idx: PartGraphId) -> bool {
+        match (body.graph.node_weight(idx), self.part_states.get(&idx)) {
+            (Some(part), Some(state)) => state.is_functional(part),
+            _ => false,
+        }
+    }
+
+    /// Blood remaining, from 1.0 down to 0.0
+    pub fn blood_fraction(&self) -> f32 {
+        if self.max_blood <= 0.0 {
+            1.0
+        } else {
+            (self.blood / self.max_blood).max(0.0)
+        }
+    }
+
+    /// Overall condition, the worst of blood loss and the average health of every part
+    pub fn health(&self, body: &BodyDefinition) -> f32 {
+        let (total, sum) = self
+            .part_states
+            .iter()
+            .fold((0, 0.0), |(total, sum), (idx, state)| {
+                body.graph
+                    .node_weight(*idx)
+                    .map_or((total, sum), |part| (total + 1, sum + state.health(part)))
+            });
+        let parts = if total == 0 { 1.0 } else { sum / total as f32 };
+
+        parts.min(self.blood_fraction())
+    }
+
+    /// Every tissue material left in the body with how much of it remains, most first
+    pub fn tissues(&self, body: &BodyDefinition) -> SmallVec<[(MaterialDefinitionId, u32); 4]> {
+        let mut tissues = SmallVec::<[(MaterialDefinitionId, u32); 4]>::new();
+
+        for (idx, state) in &self.part_states {
+            let part = match body.graph.node_weight(*idx) {
+                Some(part) if !state.destroyed => part,
+                _ => continue,
+            };
+
+            for (index, layer) in part.layers.iter().enumerate() {
+                let damage = state.layer_damage.get(index).copied().unwrap_or(0);
+                let amount = layer.thickness.saturating_sub(damage) * part.relative_size;
+                if amount == 0 {
+                    continue;
+                }
+
+                let id = layer.material.id();
+                match tissues.iter_mut().find(|(material, _)| *material == id) {
+                    Some((_, total)) => *total += amount,
+                    None => tissues.push((id, amount)),
+                }
+            }
+        }
+
+        tissues.sort_by(|a, b| b.1.cmp(&a.1));
+        tissues
+    }
+
+    /// Bled out, lost every `THOUGHT` part or lost a `CIRCULATION` part which isn't a limb
+    pub fn is_dead(&self, body: &BodyDefinition) -> bool {
+        if self.max_blood > 0.0 && self.blood <= 0.0 {
+            return true;
         }
+
+        if self.flags.contains(PartFlag::THOUGHT) && self.count_parts(body, PartFlag::THOUGHT) == 0
+        {
+            return true;
+        }
+
+        self.parts_with(body, PartFlag::CIRCULATION)
+            .any(|(part, state)| !part.flags.contains(PartFlag::LIMB) && !state.is_functional(part))
     }
 }
 impl crate::defs::DefinitionComponent<BodyDefinition> for BodyComponent {
@@ -217,6 +429,19 @@ impl crate::defs::DefinitionComponent<BodyDefinition> for BodyComponent {
     }
 }
 
+impl DefinitionResolver<Self> for PartDefinition {
+    fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
+        // Bodies can be loaded on their own, layers are then left unresolved
+        if let Some(materials) = resources.get::<DefinitionStorage<MaterialDefinition>>() {
+            for layer in &mut def.layers {
+                layer.material.resolve(&materials)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl DefinitionResolver<Self> for PartGroupDefinition {
     fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
         let parts = resources
@@ -331,6 +556,9 @@ mod loader {
                 }
             }
 
+            parts.iter_mut().for_each(|def| {
+                PartDefinition::resolve(Arc::get_mut(def).unwrap(), &resources).unwrap()
+            });
             resources.insert(parts);
 
             groups.iter_mut().for_each(|def| {
@@ -404,6 +632,7 @@ mod tests {
             relative_size: 100,
             flags: PartFlag::NERVOUS | PartFlag::THOUGHT,
             layers: vec![],
+            attack: None,
         }));
         storage.push(BodyDefProxy::Part(PartDefinition {
             id: PartDefinitionId(0),
@@ -413,6 +642,7 @@ mod tests {
             relative_size: 100,
             flags: PartFlag::NERVOUS | PartFlag::CIRCULATION,
             layers: vec![],
+            attack: None,
         }));
 
         storage.push(BodyDefProxy::Part(PartDefinition {
@@ -423,6 +653,7 @@ mod tests {
             relative_size: 100,
             flags: PartFlag::CIRCULATION,
             layers: vec![],
+            attack: None,
         }));
         storage.push(BodyDefProxy::PartGroup(PartGroupDefinition {
             details: DefinitionDetails::new("Head"),
@@ -1,5 +1,6 @@
 use crate::bitflags_serial;
 use crate::defs::{common::SpriteRef, DefinitionDetails};
+use crate::legion::prelude::*;
 use crate::math::{Vec3i, Vec3iProxy};
 use bitflags::*;
 use rl_macros::Definition;
@@ -8,6 +9,7 @@ bitflags_serial! {
     pub struct BuildingProperty: u32 {
         const IS_SEAT           =  0b1000_0000_0000_0000_0000_0000_0000_0000;
         const IS_EAT_ASSIST     =  0b0100_0000_0000_0000_0000_0000_0000_0000;
+        const IS_BED            =  0b0010_0000_0000_0000_0000_0000_0000_0000;
     }
 }
 
@@ -24,7 +26,7 @@ pub struct BuildingDefinition {
     pub id: BuildingDefinitionId,
 
     #[serde(default)]
-    pub properties: Vec<BuildingProperty>,
+    pub properties: BuildingProperty,
 
     #[serde(default)]
     pub sprite: SpriteRef,
@@ -39,3 +41,43 @@ impl BuildingDefinition {
         PlacementKind::Entity
     }
 }
+
+/// Added to spawned buildings which are `IS_BED`. A pawn keeps the first free bed it sleeps in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BedComponent {
+    pub owner: Option<Entity>,
+}
+impl BedComponent {
+    pub fn is_free_for(&self, world: &World, entity: Entity) -> bool {
+        self.owner
+            .map_or(true, |owner| owner == entity || !world.is_alive(owner))
+    }
+}
+
+/// Added to spawned seats. Reservations lapse if the holder dies or stops renewing them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReservationComponent {
+    pub holder: Option<Entity>,
+    pub taken: f64,
+}
+impl ReservationComponent {
+    /// World seconds a reservation lasts without being renewed
+    pub const TIMEOUT: f64 = 300.0;
+
+    pub fn is_free_for(&self, world: &World, entity: Entity, now: f64) -> bool {
+        self.holder.map_or(true, |holder| {
+            holder == entity || !world.is_alive(holder) || now - self.taken > Self::TIMEOUT
+        })
+    }
+
+    pub fn reserve(&mut self, entity: Entity, now: f64) {
+        self.holder = Some(entity);
+        self.taken = now;
+    }
+
+    pub fn release(&mut self, entity: Entity) {
+        if self.holder == Some(entity) {
+            self.holder = None;
+        }
+    }
+}
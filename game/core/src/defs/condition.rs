@@ -1,6 +1,5 @@
 use crate::{
     defs::{
-        common::Property,
         foliage::FoliageKind,
         item::{ItemAbilityKind, ItemProperty},
         material::MaterialLimit,
@@ -30,6 +29,37 @@ pub enum Operator {
     Or,
 }
 
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize,
+)]
+pub enum Compare {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+/// A numeric check such as `< 200`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Comparison {
+    pub op: Compare,
+    pub value: i32,
+}
+impl Comparison {
+    pub fn holds(self, value: i32) -> bool {
+        match self.op {
+            Compare::Less => value < self.value,
+            Compare::LessEqual => value <= self.value,
+            Compare::Greater => value > self.value,
+            Compare::GreaterEqual => value >= self.value,
+            Compare::Equal => value == self.value,
+            Compare::NotEqual => value != self.value,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize)]
 pub enum Item {
     Name(String),
@@ -37,6 +67,8 @@ pub enum Item {
     Ability(ItemAbilityKind),
     Material(Material),
     Nutrition(Nutrition),
+    /// Quality from `0` for poor up to `4` for masterwork
+    Quality(Comparison),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize)]
@@ -49,12 +81,33 @@ pub enum Nutrition {
     Kind(Option<NeedKind>),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize)]
+pub enum TimeOfDay {
+    Day,
+    Night,
+    Hour(Comparison),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize)]
+pub enum Nearby {
+    /// A building by name, or `workshop` for any workshop
+    Building(String),
+    Item(Item),
+    Foliage(FoliageKind),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize)]
 pub enum Value {
-    Property(Property),
     Item(Item),
     Tile(Tile),
     Foliage(FoliageKind),
+    Need(NeedKind, Comparison),
+    Time(TimeOfDay),
+    /// Something within `range` tiles on the same level
+    Near {
+        what: Nearby,
+        range: u32,
+    },
 }
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Material {
@@ -66,6 +119,8 @@ pub struct Material {
 pub struct Condition {
     pub subject: Subject,
     pub op: Operator,
+    /// How many items or foliage must match, as in `has 3 item log`
+    pub count: usize,
     pub value: Value,
 }
 
@@ -80,6 +135,15 @@ pub struct ConditionSet {
     pub left: Condition,
     pub right: Option<ConditionRight>,
 }
+impl ConditionSet {
+    /// Every condition in the set, however they are combined
+    pub fn iter(&self) -> impl Iterator<Item = &Condition> {
+        std::iter::successors(Some(self), |set| {
+            set.right.as_ref().map(|right| &*right.set)
+        })
+        .map(|set| &set.left)
+    }
+}
 
 fn to_ron_case(value: &str) -> String {
     let mut res = String::with_capacity(value.len());
@@ -195,13 +259,40 @@ peg::parser! {
             = value:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '_']+) { value.parse().unwrap() }
             / expected!("ident")
 
+        rule number() -> u32
+            = v:$(['0'..='9']+) {? v.parse().or(Err("a number")) }
+            / expected!("a number")
+
+        rule integer() -> i32
+            = v:$("-"? ['0'..='9']+) {? v.parse().or(Err("a number")) }
+            / expected!("a number")
+
+        rule count() -> usize
+            = v:$(['0'..='9']+) _ {? v.parse().or(Err("a count")) }
+
+        rule compare() -> Compare
+            = "<=" { Compare::LessEqual }
+            / ">=" { Compare::GreaterEqual }
+            / "==" { Compare::Equal }
+            / "!=" { Compare::NotEqual }
+            / "<" { Compare::Less }
+            / ">" { Compare::Greater }
+            / expected!("<, <=, >, >=, == or !=")
+
+        rule comparison() -> Comparison
+            = op:compare() _ value:integer() { Comparison { op, value } }
+
         rule operator() -> Operator
               = "is" { Operator::True }
-              / "!is" { Operator::True }
+              / "!is" { Operator::False }
               / "has" { Operator::True }
               / "!has" { Operator::False }
               / expected!("is, !is, has or !has")
 
+        rule near_operator() -> Operator
+              = "near" { Operator::True }
+              / "!near" { Operator::False }
+
         rule set_operator() -> Operator
               = "|" { Operator::Or }
               / "&" { Operator::And }
@@ -210,7 +301,11 @@ peg::parser! {
         rule subject() -> Subject
               = "self" { Subject::Me }
               / "target" { Subject::Target }
-              / expected!("self or target")
+              / "any" { Subject::Any }
+              / expected!("self, target or any")
+
+        rule subject_prefix() -> Subject
+            = v:subject() _ { v }
 
         rule material() -> Material
             = op:operator() _ limit:ron_string() {?
@@ -219,7 +314,7 @@ peg::parser! {
 
                 ron::de::from_str::<Wrapper>(&format!("Wrapper({})", to_ron_case(&limit)))
                     .map_or(
-                        Err("Failed to parse ron value"),
+                        Err("a material limit such as any(solid)"),
                         |limit|  Ok(Material { op, limit: limit.0})
                     )
             }
@@ -228,68 +323,103 @@ peg::parser! {
             = "any" { Nutrition::Kind(None) }
             / "calories" { Nutrition::Kind(Some(NeedKind::Calories)) }
             / "hydration" { Nutrition::Kind(Some(NeedKind::Hydration)) }
+            / expected!("any, calories or hydration")
+
+        rule need() -> NeedKind
+            = "calories" { NeedKind::Calories }
+            / "hydration" { NeedKind::Hydration }
+            / "sleep" { NeedKind::Sleep }
+            / expected!("calories, hydration or sleep")
+
+        rule time_of_day() -> TimeOfDay
+            = "day" { TimeOfDay::Day }
+            / "night" { TimeOfDay::Night }
+            / expected!("day or night")
 
         rule item() -> Item
             = "name" _ v:ident() { Item::Name(v) }
             / "material" _ v:material() { Item::Material(v) }
             / "property" _ v:ident() {?
                 ItemProperty::from_str(&v).map_or(
-                    Err("Failed to parse ItemProperty"),
+                    Err("an item property"),
                     |v| Ok(Item::Property(v)))
             }
             / "ability" _ v:ident() {?
                 ItemAbilityKind::from_str(&v).map_or(
-                    Err("Failed to parse ItemAbilityKind"),
+                    Err("an item ability"),
                     |v| Ok(Item::Ability(v)))
             }
             / "nutrition" _ v:nutrition() { Item::Nutrition(v) }
+            / "quality" _ v:comparison() { Item::Quality(v) }
             / v:ident() { Item::Name(v) }
-            / expected!("name, property or ability")
-
-        rule property() -> Property
-            = v:ident() { Property::Test }
+            / expected!("name, material, property, ability, nutrition or quality")
 
         rule foliage() -> FoliageKind
             = v:ident() {?
                 FoliageKind::from_str(&v).map_or(
-                    Err("Failed to parse FoliageKind"),
+                    Err("a foliage kind"),
                     |v| Ok(v))
             }
             / "kind" _ v:ident() {?
                 FoliageKind::from_str(&v).map_or(
-                    Err("Failed to parse FoliageKind"),
+                    Err("a foliage kind"),
                     |v| Ok(v))
             }
-            / expected!("[kind] or kind [kind]")
+            / expected!("a foliage kind or kind [kind]")
 
         rule tile() -> Tile
             = v:ident() {?
                 TileKind::from_str(&v).map_or(
-                    Err("Failed to parse TileKind"),
+                    Err("a tile kind"),
                     |v| Ok(Tile::Kind(v)))
             }
             / "kind" _ v:ident() {?
                 TileKind::from_str(&v).map_or(
-                    Err("Failed to parse TileKind"),
+                    Err("a tile kind"),
                     |v| Ok(Tile::Kind(v)))
             }
-            / expected!("[kind] or kind [kind]")
+            / expected!("a tile kind or kind [kind]")
 
         rule value() -> Value
             = "item" _ v:item() { Value::Item(v) }
-            / "property" _ v:property() { Value::Property(v) }
             / "tile" _ v:tile() { Value::Tile(v) }
             / "foliage" _ v:foliage() { Value::Foliage(v) }
+            / expected!("item, tile or foliage")
+
+        rule nearby() -> Nearby
+            = "building" _ v:ident() { Nearby::Building(v) }
+            / "item" _ v:item() { Nearby::Item(v) }
+            / "foliage" _ v:foliage() { Nearby::Foliage(v) }
+            / expected!("building, item or foliage")
+
+        rule within() -> u32
+            = _ "within" _ v:number() { v }
+
+        rule body() -> (Operator, usize, Value)
+            = "need" _ kind:need() _ v:comparison() { (Operator::True, 1, Value::Need(kind, v)) }
+            / "item" _ "quality" _ v:comparison() { (Operator::True, 1, Value::Item(Item::Quality(v))) }
+            / op:near_operator() _ what:nearby() range:within()? {
+                (op, 1, Value::Near { what, range: range.unwrap_or(1) })
+            }
+            / op:operator() _ count:count()? value:value() { (op, count.unwrap_or(1), value) }
 
         rule condition() -> Condition
-            = subject:subject() _ op:operator() _ value:value() { Condition { subject, op, value } }
-            / op:operator() _ value:value() { Condition { subject: Subject::Me, op, value } }
-            / expected!("Failed condition")
+            = "time" _ "hour" _ v:comparison() {
+                Condition { subject: Subject::Any, op: Operator::True, count: 1, value: Value::Time(TimeOfDay::Hour(v)) }
+            }
+            / "time" _ op:operator() _ v:time_of_day() {
+                Condition { subject: Subject::Any, op, count: 1, value: Value::Time(v) }
+            }
+            / subject:subject_prefix()? body:body() {
+                let (op, count, value) = body;
+                Condition { subject: subject.unwrap_or(Subject::Me), op, count, value }
+            }
+            / expected!("a condition")
 
         rule condition_set() -> ConditionSet
             = l:condition() _ op:set_operator() _ r:statement() { ConditionSet { left: l, right: Some(ConditionRight { op, set: Box::new(r) } ) } }
             / l:condition() { ConditionSet { left: l, right: None } }
-            / expected!("Failed condition_set")
+            / expected!("a condition or set of conditions")
 
         pub rule statement() -> ConditionSet
             = "(" _ v:condition_set() _ ")" { v }
@@ -307,8 +437,20 @@ where
     T: AsRef<str>,
 {
     fn to_condition_set(&self) -> Result<ConditionSet, failure::Error> {
-        parser::statement(self.as_ref()).map_err(|e| {
-            failure::format_err!("Failed to parse condition: {}, ('{}')", e, self.as_ref())
+        let string = self.as_ref();
+        parser::statement(string).map_err(|e| {
+            let found = match string.get(e.location.offset..) {
+                Some(rest) if !rest.is_empty() => format!("'{}'", rest),
+                _ => "the end".to_owned(),
+            };
+
+            failure::format_err!(
+                "Failed to parse condition '{}': expected {} at column {} but found {}",
+                string,
+                e.expected,
+                e.location.column,
+                found
+            )
         })
     }
 }
@@ -322,7 +464,8 @@ mod tests {
     fn condition_parse_test() -> Result<(), failure::Error> {
         // "self (has item property is_flammable) | (has item property is_edible) | (has item property is_flammable & (has item property is_edible))";
 
-        println!("{:?}", parser::statement("self has property test").unwrap());
+        // Nothing has properties to check, so they are refused rather than never holding
+        assert!("self has property test".to_condition_set().is_err());
 
         println!(
             "{:?}",
@@ -363,6 +506,133 @@ mod tests {
                 .unwrap()
         );
 
+        // Negation
+        let set = parser::statement("target !is item log").unwrap();
+        assert_eq!(set.left.subject, Subject::Target);
+        assert_eq!(set.left.op, Operator::False);
+        assert_eq!(
+            parser::statement("!has item ability digging")
+                .unwrap()
+                .left
+                .op,
+            Operator::False
+        );
+        assert_eq!(
+            parser::statement("has item material !is any(liquid)")
+                .unwrap()
+                .left
+                .value,
+            Value::Item(Item::Material(Material {
+                op: Operator::False,
+                limit: MaterialLimit::Any(crate::defs::material::MaterialState::Liquid),
+            }))
+        );
+
+        // Numeric comparisons
+        let set = parser::statement("self need calories < 200").unwrap();
+        assert_eq!(set.left.subject, Subject::Me);
+        assert_eq!(
+            set.left.value,
+            Value::Need(
+                NeedKind::Calories,
+                Comparison {
+                    op: Compare::Less,
+                    value: 200
+                }
+            )
+        );
+        let set = parser::statement("target item quality >= 3").unwrap();
+        assert_eq!(set.left.subject, Subject::Target);
+        assert_eq!(
+            set.left.value,
+            Value::Item(Item::Quality(Comparison {
+                op: Compare::GreaterEqual,
+                value: 3
+            }))
+        );
+        assert_eq!(
+            parser::statement("need sleep != -5").unwrap().left.value,
+            Value::Need(
+                NeedKind::Sleep,
+                Comparison {
+                    op: Compare::NotEqual,
+                    value: -5
+                }
+            )
+        );
+
+        // Counts
+        let set = parser::statement("has 3 item ability chopping").unwrap();
+        assert_eq!(set.left.count, 3);
+        assert_eq!(
+            set.left.value,
+            Value::Item(Item::Ability(ItemAbilityKind::Chopping))
+        );
+        assert_eq!(parser::statement("has item log").unwrap().left.count, 1);
+
+        // Proximity
+        assert_eq!(
+            parser::statement("near building workshop within 5")
+                .unwrap()
+                .left
+                .value,
+            Value::Near {
+                what: Nearby::Building("workshop".to_owned()),
+                range: 5
+            }
+        );
+        let set = parser::statement("target !near foliage tree").unwrap();
+        assert_eq!(set.left.op, Operator::False);
+        assert_eq!(
+            set.left.value,
+            Value::Near {
+                what: Nearby::Foliage(FoliageKind::Tree),
+                range: 1
+            }
+        );
+
+        // Time of day
+        assert_eq!(
+            parser::statement("time is night").unwrap().left.value,
+            Value::Time(TimeOfDay::Night)
+        );
+        assert_eq!(
+            parser::statement("time !is day").unwrap().left.op,
+            Operator::False
+        );
+        assert_eq!(
+            parser::statement("time hour >= 20").unwrap().left.value,
+            Value::Time(TimeOfDay::Hour(Comparison {
+                op: Compare::GreaterEqual,
+                value: 20
+            }))
+        );
+
+        // Mixed sets
+        let set = parser::statement("need hydration < 50 & (time is day | near item log within 2)")
+            .unwrap();
+        assert_eq!(set.right.as_ref().unwrap().op, Operator::And);
+        assert_eq!(set.iter().count(), 3);
+
+        // Errors say what was expected and where
+        let error = "self need thirst < 2".to_condition_set().unwrap_err();
+        assert!(error.to_string().contains("calories, hydration or sleep"));
+        assert!(error.to_string().contains("'thirst < 2'"));
+        let error = "has item quality 3".to_condition_set().unwrap_err();
+        assert!(error.to_string().contains("<, <=, >, >=, == or !="));
+        assert!("target is tile".to_condition_set().is_err());
+
         Ok(())
     }
+
+    #[test]
+    fn comparison_test() {
+        let at_most = Comparison {
+            op: Compare::LessEqual,
+            value: 3,
+        };
+        assert!(at_most.holds(3));
+        assert!(at_most.holds(-1));
+        assert!(!at_most.holds(4));
+    }
 }
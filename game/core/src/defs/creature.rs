@@ -1,9 +1,11 @@
 use crate::{
+    components::{Destroy, PositionComponent},
     defs::{
         race::{RaceDefinition, RaceRef},
-        DefinitionDetails, DefinitionResolver, DefinitionStorage,
+        DefinitionComponent, DefinitionDetails, DefinitionResolver, DefinitionStorage,
     },
     legion::prelude::*,
+    math::Vec3i,
 };
 use rl_macros::Definition;
 
@@ -21,6 +23,13 @@ pub struct CreatureDefinition {
     pub sprite: crate::defs::common::SpriteRef,
 
     pub decisions: Vec<String>,
+
+    /// Pawns remember hostile creatures as a threat
+    #[serde(default)]
+    pub hostile: bool,
+
+    #[serde(default)]
+    pub behavior: CreatureBehavior,
 }
 impl DefinitionResolver<Self> for CreatureDefinition {
     fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
@@ -33,3 +42,50 @@ impl DefinitionResolver<Self> for CreatureDefinition {
         Ok(())
     }
 }
+
+/// Which of the creature behaviors a creature uses, and how far it strays while doing them
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct CreatureBehavior {
+    /// Eat grass and other edible foliage when hungry
+    #[serde(default)]
+    pub grazes: bool,
+    /// Drink from standing liquid when thirsty
+    #[serde(default)]
+    pub drinks: bool,
+    /// Wander at most this many tiles from where the creature was spawned
+    #[serde(default)]
+    pub home_range: Option<i32>,
+    /// Return to the nearest creature of the same kind when further than this
+    #[serde(default)]
+    pub herd_range: Option<i32>,
+    /// Run this many tiles away from perceived threats
+    #[serde(default)]
+    pub flee_distance: Option<i32>,
+}
+
+/// Where a creature wanders around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HomeComponent {
+    pub position: Vec3i,
+}
+impl HomeComponent {
+    pub fn new(position: Vec3i) -> Self {
+        Self { position }
+    }
+}
+
+/// The nearest other creature of the same kind as `entity`, and where it is
+pub fn nearest_herd_mate(world: &World, entity: Entity) -> Option<(Entity, Vec3i)> {
+    let id = world.get_component::<CreatureComponent>(entity)?.id();
+    let position = **world.get_component::<PositionComponent>(entity)?;
+
+    <(Read<CreatureComponent>, Read<PositionComponent>)>::query()
+        .filter(!component::<Destroy>())
+        .iter_entities(world)
+        .filter(|(other, (creature, _))| *other != entity && creature.id() == id)
+        .map(|(other, (_, other_position))| (other, **other_position))
+        .min_by_key(|(_, other_position)| {
+            let d = *other_position - position;
+            d.x * d.x + d.y * d.y + d.z * d.z
+        })
+}
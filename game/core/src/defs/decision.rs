@@ -0,0 +1,97 @@
+use crate::{
+    defs::{item::ItemKind, needs::NeedKind, DefinitionDetails},
+    schedule::ScheduleBlock,
+};
+use rl_macros::Definition;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ConsiderationInput {
+    Constant(f64),
+    Need(NeedKind),
+    NeedDeficit(NeedKind),
+    DistanceToItem(ItemKind),
+    TimeOfDay,
+    ItemCount(ItemKind),
+    PerceivedCreatures,
+    HeardNoises,
+    /// Perceived pawns which are not rivals
+    PerceivedPawns,
+    PerceivedRivals,
+    /// Hours since the last conversation
+    TimeSinceSocial,
+    Mood,
+    /// 1.0 in the given block of the pawn's schedule, 0.5 during free hours and 0.0 otherwise
+    Schedule(ScheduleBlock),
+    /// Perceived entities a creature is afraid of
+    PerceivedThreats,
+    /// Distance to the nearest herd mate as a multiple of the creature's herd range
+    DistanceToHerd,
+    /// Perceived entities we would fight
+    PerceivedEnemies,
+    /// 1.0 right after being attacked, falling to 0.0 as the attack is forgotten
+    RecentlyHit,
+    /// 0.0 unharmed, rising to 1.0 as blood and body parts are lost
+    Injury,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ConsiderationCurve {
+    Linear {
+        range: Range<f64>,
+        slope: f64,
+        intercept: f64,
+    },
+    Exponential {
+        range: Range<f64>,
+        power: f64,
+        intercept: f64,
+    },
+    Sine {
+        range: Range<f64>,
+        magnitude: f64,
+        intercept: f64,
+    },
+    Cosine {
+        range: Range<f64>,
+        magnitude: f64,
+        intercept: f64,
+    },
+    Logistic {
+        range: Range<f64>,
+        steepness: f64,
+        midpoint: f64,
+    },
+    Logit {
+        range: Range<f64>,
+        base: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ConsiderationDefinition {
+    pub name: String,
+    pub input: ConsiderationInput,
+
+    /// A consideration without a curve returns its raw input
+    #[serde(default)]
+    pub curve: Option<ConsiderationCurve>,
+
+    #[serde(default = "ConsiderationDefinition::default_weight")]
+    pub weight: f64,
+}
+impl ConsiderationDefinition {
+    fn default_weight() -> f64 {
+        1.0
+    }
+}
+
+#[derive(Definition, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DecisionDefinition {
+    pub details: DefinitionDetails,
+
+    #[serde(skip)]
+    pub id: DecisionDefinitionId,
+
+    pub considerations: Vec<ConsiderationDefinition>,
+}
@@ -1,8 +1,11 @@
 use crate::defs::{
-    common::SpriteRef, material::MaterialLimit, needs::ProvidesNutrition, DefinitionDetails,
-    DefinitionResolver,
+    common::SpriteRef,
+    material::{MaterialComponent, MaterialDefinition, MaterialLimit},
+    needs::ProvidesNutrition,
+    Definition, DefinitionDetails, DefinitionResolver, DefinitionStorage,
 };
 use crate::{
+    components::EntityMeta,
     data::DimensionsVec,
     failure,
     legion::prelude::*,
@@ -191,25 +194,172 @@ impl DefinitionResolver<Self> for ItemDefinition {
     }
 }
 
+#[derive(
+    Debug,
+    AsRefStr,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+pub enum ItemQuality {
+    Poor,
+    Normal,
+    Good,
+    Excellent,
+    Masterwork,
+}
+impl Default for ItemQuality {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+#[derive(Shrinkwrap, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ItemQualityComponent(pub ItemQuality);
+
 #[derive(Shrinkwrap, Default)]
 #[shrinkwrap(mutable)]
 pub struct StockpileSpatialMap(pub crate::rstar::RTree<crate::map::spatial::SpatialMapEntry>);
 
+/// Items are hauled out of stockpiles into ones of a higher priority which accept them
+#[derive(
+    Debug,
+    AsRefStr,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+pub enum StockpilePriority {
+    Low,
+    Normal,
+    Preferred,
+    Important,
+    Critical,
+}
+impl StockpilePriority {
+    pub const ALL: [Self; 5] = [
+        Self::Low,
+        Self::Normal,
+        Self::Preferred,
+        Self::Important,
+        Self::Critical,
+    ];
+}
+impl Default for StockpilePriority {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+/// The items a stockpile accepts. Empty `definitions` or `materials` accept anything
+#[derive(Debug, Clone, PartialEq)]
+pub struct StockpileFilter {
+    pub kinds: BitFlags<ItemKind>,
+    pub definitions: Vec<ItemDefinitionId>,
+    pub materials: Vec<MaterialLimit>,
+    pub min_quality: ItemQuality,
+    pub max_quality: ItemQuality,
+    /// Oldest an item may be, in world seconds
+    pub max_age: Option<f64>,
+}
+impl Default for StockpileFilter {
+    fn default() -> Self {
+        Self {
+            kinds: BitFlags::all(),
+            definitions: Vec::new(),
+            materials: Vec::new(),
+            min_quality: ItemQuality::Poor,
+            max_quality: ItemQuality::Masterwork,
+            max_age: None,
+        }
+    }
+}
+impl From<BitFlags<ItemKind>> for StockpileFilter {
+    fn from(kinds: BitFlags<ItemKind>) -> Self {
+        Self {
+            kinds,
+            ..Self::default()
+        }
+    }
+}
+impl StockpileFilter {
+    /// Items without a quality count as `Normal`, and items without a creation stamp are always fresh
+    pub fn accepts(
+        &self,
+        world: &World,
+        entity: Entity,
+        item: &ItemDefinition,
+        materials: &DefinitionStorage<MaterialDefinition>,
+        now: f64,
+    ) -> bool {
+        if !self.kinds.contains(item.kind) {
+            return false;
+        }
+
+        if !self.definitions.is_empty() && !self.definitions.contains(&item.id()) {
+            return false;
+        }
+
+        if !self.materials.is_empty()
+            && !world
+                .get_component::<MaterialComponent>(entity)
+                .map_or(false, |material| {
+                    self.materials
+                        .iter()
+                        .any(|limit| limit.matches(&material, materials))
+                })
+        {
+            return false;
+        }
+
+        let quality = world
+            .get_component::<ItemQualityComponent>(entity)
+            .map_or_else(ItemQuality::default, |quality| **quality);
+        if quality < self.min_quality || quality > self.max_quality {
+            return false;
+        }
+
+        if let Some(max_age) = self.max_age {
+            if let Some(meta) = world.get_component::<EntityMeta>(entity) {
+                if now - meta.created().world > max_age {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StockpileComponent {
     pub tiles: FreeList<Vec3i>,
-    pub stores: BitFlags<ItemKind>,
+    pub filter: StockpileFilter,
+    pub priority: StockpilePriority,
     pub contains: Vec<Entity>,
     pub children_tiles: Vec<Entity>,
 }
 impl StockpileComponent {
-    pub fn new(stores: BitFlags<ItemKind>, tiles: Vec<Vec3i>) -> Self {
+    pub fn new(filter: StockpileFilter, priority: StockpilePriority, tiles: Vec<Vec3i>) -> Self {
         use std::iter::FromIterator;
 
         Self {
             contains: Vec::with_capacity(tiles.len()),
             children_tiles: Vec::with_capacity(tiles.len()),
-            stores,
+            filter,
+            priority,
             tiles: FreeList::from_iter(tiles),
         }
     }
@@ -223,3 +373,152 @@ pub struct StockpileItemChildComponent {
 pub struct StockpileTileChildComponent {
     pub parent: Entity,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        defs::material::{MaterialKind, MaterialState, OrganicSubKind, RockSubKind},
+        time::TimeStamp,
+        GameState,
+    };
+
+    fn setup() -> Result<GameState, failure::Error> {
+        let mut state = GameState::default();
+
+        DefinitionStorage::<MaterialDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/materials",
+        )?;
+        DefinitionStorage::<ItemDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/items",
+        )?;
+
+        Ok(state)
+    }
+
+    #[test]
+    fn stockpile_priority_test() {
+        assert!(StockpilePriority::ALL
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            StockpilePriority::ALL.iter().max(),
+            Some(&StockpilePriority::Critical)
+        );
+        assert!(StockpilePriority::default() > StockpilePriority::Low);
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn stockpile_filter_accepts_test() -> Result<(), failure::Error> {
+        let mut state = setup()?;
+
+        let (meat_material, meat_id, liquid_id) = {
+            let materials = state
+                .resources
+                .get::<DefinitionStorage<MaterialDefinition>>()
+                .unwrap();
+            let items = state
+                .resources
+                .get::<DefinitionStorage<ItemDefinition>>()
+                .unwrap();
+            (
+                materials.get_id("meat").unwrap(),
+                items.get_id("Meat").unwrap(),
+                items.get_id("Liquid").unwrap(),
+            )
+        };
+
+        let plain = state.world.insert((), vec![(ItemComponent::new(meat_id),)])[0];
+        let made = state.world.insert(
+            (),
+            vec![(
+                ItemComponent::new(meat_id),
+                MaterialComponent::new(meat_material, MaterialState::Solid),
+                ItemQualityComponent(ItemQuality::Good),
+                EntityMeta::new(TimeStamp {
+                    real: 0.0,
+                    world: 10.0,
+                    frame: 0,
+                }),
+            )],
+        )[0];
+
+        let items = state
+            .resources
+            .get::<DefinitionStorage<ItemDefinition>>()
+            .unwrap();
+        let materials = state
+            .resources
+            .get::<DefinitionStorage<MaterialDefinition>>()
+            .unwrap();
+        let meat = items.get(meat_id).unwrap();
+        let accepts = |filter: &StockpileFilter, entity: Entity, now: f64| {
+            filter.accepts(&state.world, entity, meat, &materials, now)
+        };
+
+        let filter = StockpileFilter::default();
+        assert!(accepts(&filter, plain, 0.0));
+        assert!(accepts(&filter, made, 0.0));
+
+        // Kinds
+        let filter = StockpileFilter::from(BitFlags::from(ItemKind::Wood));
+        assert!(!accepts(&filter, plain, 0.0));
+        let filter = StockpileFilter::from(ItemKind::Wood | ItemKind::Food);
+        assert!(accepts(&filter, plain, 0.0));
+
+        // Definitions
+        let filter = StockpileFilter {
+            definitions: vec![liquid_id],
+            ..StockpileFilter::default()
+        };
+        assert!(!accepts(&filter, plain, 0.0));
+        let filter = StockpileFilter {
+            definitions: vec![liquid_id, meat_id],
+            ..StockpileFilter::default()
+        };
+        assert!(accepts(&filter, plain, 0.0));
+
+        // Materials, which items without one never match
+        let filter = StockpileFilter {
+            materials: vec![MaterialLimit::Kind(MaterialKind::Organic(
+                OrganicSubKind::Flesh,
+            ))],
+            ..StockpileFilter::default()
+        };
+        assert!(accepts(&filter, made, 0.0));
+        assert!(!accepts(&filter, plain, 0.0));
+        let filter = StockpileFilter {
+            materials: vec![MaterialLimit::Kind(MaterialKind::Rock(RockSubKind::Any))],
+            ..StockpileFilter::default()
+        };
+        assert!(!accepts(&filter, made, 0.0));
+
+        // Quality, where items without one are Normal
+        let filter = StockpileFilter {
+            min_quality: ItemQuality::Good,
+            ..StockpileFilter::default()
+        };
+        assert!(accepts(&filter, made, 0.0));
+        assert!(!accepts(&filter, plain, 0.0));
+        let filter = StockpileFilter {
+            max_quality: ItemQuality::Normal,
+            ..StockpileFilter::default()
+        };
+        assert!(!accepts(&filter, made, 0.0));
+        assert!(accepts(&filter, plain, 0.0));
+
+        // Age, where items without a creation stamp are always fresh
+        let filter = StockpileFilter {
+            max_age: Some(50.0),
+            ..StockpileFilter::default()
+        };
+        assert!(accepts(&filter, made, 60.0));
+        assert!(!accepts(&filter, made, 61.0));
+        assert!(accepts(&filter, plain, 1000.0));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use crate::defs::{
+    reaction::{ReactionCategory, ReactionDefinition, ReactionRef},
+    Definition, DefinitionDetails, DefinitionResolver, DefinitionStorage,
+};
+use crate::{failure, legion::prelude::*};
+use rl_macros::Definition;
+
+/// A kind of work pawns can be prioritized for, such as mining or hauling
+#[derive(Definition, Debug, serde::Deserialize, serde::Serialize)]
+#[definition(resolver = "Self")]
+pub struct LaborDefinition {
+    pub details: DefinitionDetails,
+    #[serde(skip)]
+    pub id: LaborDefinitionId,
+
+    /// Reactions of these categories fall under this labor, unless another labor lists them
+    #[serde(default)]
+    pub categories: Vec<ReactionCategory>,
+
+    /// Reactions which always fall under this labor
+    #[serde(default)]
+    pub reactions: Vec<ReactionRef>,
+
+    #[serde(default = "LaborDefinition::default_priority")]
+    pub default_priority: u8,
+}
+impl LaborDefinition {
+    fn default_priority() -> u8 {
+        3
+    }
+}
+impl DefinitionResolver<Self> for LaborDefinition {
+    fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
+        let reactions = resources
+            .get::<DefinitionStorage<ReactionDefinition>>()
+            .unwrap();
+
+        for reaction in &mut def.reactions {
+            reaction.resolve(&reactions)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DefinitionStorage<LaborDefinition> {
+    /// The labor tasks for `reaction` are done under
+    pub fn for_reaction(&self, reaction: &ReactionDefinition) -> Option<LaborDefinitionId> {
+        self.iter()
+            .find(|labor| labor.reactions.iter().any(|r| r.id() == reaction.id()))
+            .or_else(|| {
+                self.iter()
+                    .find(|labor| labor.categories.contains(&reaction.category))
+            })
+            .map(|labor| labor.id())
+    }
+}
@@ -77,6 +77,7 @@ pub enum OrganicSubKind {
     Wood,
     Bone,
     Flesh,
+    Skin,
     Any,
 }
 impl Default for OrganicSubKind {
@@ -110,6 +111,16 @@ impl Default for MaterialKind {
         Self::Todo
     }
 }
+impl MaterialKind {
+    /// True if `other` falls under this kind, an `Any` subkind covers every subkind
+    pub fn contains(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Rock(RockSubKind::Any), Self::Rock(_))
+            | (Self::Organic(OrganicSubKind::Any), Self::Organic(_)) => true,
+            _ => self == other,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, EnumString)]
 #[strum(serialize_all = "snake_case")]
@@ -126,6 +137,20 @@ impl Default for MaterialLimit {
         Self::Any(MaterialState::Solid)
     }
 }
+impl MaterialLimit {
+    pub fn matches(
+        &self,
+        material: &MaterialComponent,
+        storage: &DefinitionStorage<MaterialDefinition>,
+    ) -> bool {
+        match self {
+            Self::Any(state) => *state == MaterialState::Any || *state == material.state,
+            Self::Kind(kind) => kind.contains(&material.def.fetch(storage).category),
+            Self::Source => true,
+            Self::Material(material_ref) => material_ref.id() == material.def,
+        }
+    }
+}
 impl DefinitionResolver<Self> for MaterialLimit {
     fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
         let materials = resources
@@ -233,6 +258,10 @@ impl MaterialComponent {
         Self { def, state }
     }
 
+    pub fn state(&self) -> MaterialState {
+        self.state
+    }
+
     pub fn fetch_state<'a>(
         &self,
         storage: &'a crate::defs::DefinitionStorage<MaterialDefinition>,
@@ -252,3 +281,49 @@ impl crate::defs::DefinitionComponent<MaterialDefinition> for MaterialComponent
         self.def.fetch(storage)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_limit_matches_test() -> Result<(), failure::Error> {
+        let mut resources = Resources::default();
+        DefinitionStorage::<MaterialDefinition>::from_folder(
+            &mut resources,
+            "../assets/defs/materials",
+        )?;
+        let materials = resources
+            .get::<DefinitionStorage<MaterialDefinition>>()
+            .unwrap();
+
+        let wood = MaterialComponent::new(materials.get_id("wood").unwrap(), MaterialState::Solid);
+        let marble =
+            MaterialComponent::new(materials.get_id("marble").unwrap(), MaterialState::Powder);
+
+        assert!(MaterialLimit::Any(MaterialState::Solid).matches(&wood, &materials));
+        assert!(!MaterialLimit::Any(MaterialState::Solid).matches(&marble, &materials));
+        assert!(MaterialLimit::Any(MaterialState::Any).matches(&marble, &materials));
+
+        let organic = MaterialLimit::Kind(MaterialKind::Organic(OrganicSubKind::Any));
+        assert!(organic.matches(&wood, &materials));
+        assert!(!organic.matches(&marble, &materials));
+        assert!(
+            MaterialLimit::Kind(MaterialKind::Organic(OrganicSubKind::Wood))
+                .matches(&wood, &materials)
+        );
+        assert!(
+            !MaterialLimit::Kind(MaterialKind::Organic(OrganicSubKind::Bone))
+                .matches(&wood, &materials)
+        );
+
+        assert!(MaterialLimit::Source.matches(&marble, &materials));
+
+        let mut material_ref = MaterialRef::new("wood");
+        material_ref.resolve(&materials)?;
+        assert!(MaterialLimit::Material(material_ref.clone()).matches(&wood, &materials));
+        assert!(!MaterialLimit::Material(material_ref).matches(&marble, &materials));
+
+        Ok(())
+    }
+}
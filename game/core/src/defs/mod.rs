@@ -9,17 +9,21 @@
     clippy::module_name_repetitions,
     non_camel_case_types
 )]
+pub mod behavior;
 pub mod body;
 pub mod building;
 pub mod common;
 pub mod condition;
 pub mod creature;
+pub mod decision;
 pub mod foliage;
 pub mod item;
+pub mod labor;
 pub mod material;
 pub mod needs;
 pub mod race;
 pub mod reaction;
+pub mod thought;
 pub mod workshop;
 
 use crate::{
@@ -146,7 +150,7 @@ where
     where
         P: AsRef<Path>,
     {
-        let mut storage = DefinitionStorage::<T>::default();
+        let mut storage = DefinitionStorage::<T>::new(&folder);
 
         // Collect the files and try the root name as well.
         let files = Self::collect_files(folder);
@@ -236,6 +240,10 @@ where
         self.storage.len() >= id.into()
     }
 
+    pub fn source(&self) -> &Path {
+        self.source.as_path()
+    }
+
     pub fn bitset(&self) -> &BitSet {
         &self.bitset
     }
@@ -311,6 +319,11 @@ pub fn load_all_defs(resources: &mut Resources, root: Option<&str>) -> Result<()
         root.join("assets/defs/reactions"),
     )?;
 
+    DefinitionStorage::<labor::LaborDefinition>::from_folder(
+        resources,
+        root.join("assets/defs/labors"),
+    )?;
+
     ////
     DefinitionStorage::<foliage::FoliageDefinition>::from_folder(
         resources,
@@ -334,6 +347,21 @@ pub fn load_all_defs(resources: &mut Resources, root: Option<&str>) -> Result<()
         root.join("assets/defs/creatures"),
     )?;
 
+    DefinitionStorage::<decision::DecisionDefinition>::from_folder(
+        resources,
+        root.join("assets/defs/decisions"),
+    )?;
+
+    DefinitionStorage::<behavior::BehaviorDefinition>::from_folder(
+        resources,
+        root.join("assets/defs/behaviors"),
+    )?;
+
+    DefinitionStorage::<thought::ThoughtDefinition>::from_folder(
+        resources,
+        root.join("assets/defs/thoughts"),
+    )?;
+
     ////
 
     DefinitionStorage::<building::BuildingDefinition>::from_folder(
@@ -1,3 +1,15 @@
+use crate::{
+    defs::{
+        foliage::{FoliageComponent, FoliageDefinition},
+        item::{ItemComponent, ItemDefinition},
+        material::{MaterialComponent, MaterialDefinition, MaterialState},
+        DefinitionComponent, DefinitionStorage,
+    },
+    legion::prelude::*,
+    map::Map,
+    math::Vec3i,
+    GameStateRef,
+};
 use std::ops::Range;
 use strum_macros::{AsStaticStr, EnumCount, EnumIter};
 
@@ -62,3 +74,72 @@ impl NeedKind {
         self as usize
     }
 }
+
+/// Nutrition an item or foliage entity provides for the need, if it provides any
+pub fn get_nutrition_value(
+    state: GameStateRef,
+    entity: Entity,
+    kind: NeedKind,
+) -> Option<Range<i32>> {
+    let (items, materials, foliages) = <(
+        Read<DefinitionStorage<ItemDefinition>>,
+        Read<DefinitionStorage<MaterialDefinition>>,
+        Read<DefinitionStorage<FoliageDefinition>>,
+    )>::fetch(state.resources);
+
+    let item_comp = state.world.get_component::<ItemComponent>(entity);
+    let foliage_comp = state.world.get_component::<FoliageComponent>(entity);
+
+    let optional_nutrition = if let Some(comp) = &item_comp {
+        &comp.fetch(&items).nutrition
+    } else if let Some(comp) = &foliage_comp {
+        &comp.fetch(&foliages).nutrition
+    } else {
+        return None;
+    };
+
+    let nutrition = match &optional_nutrition {
+        ProvidesNutrition::FromMaterial => {
+            if item_comp.is_some() {
+                if let Some(material_comp) = state.world.get_component::<MaterialComponent>(entity)
+                {
+                    let material_state = material_comp.fetch_state(&materials);
+
+                    &material_state.nutrition
+                } else {
+                    return None;
+                }
+            } else {
+                return None;
+            }
+        }
+        ProvidesNutrition::Value(value) => value,
+    };
+
+    Some(nutrition_for(nutrition, kind))
+}
+
+/// Nutrition of the liquid standing on a tile, if any
+pub fn get_tile_nutrition_value(
+    map: &Map,
+    materials: &DefinitionStorage<MaterialDefinition>,
+    coord: Vec3i,
+    kind: NeedKind,
+) -> Option<Range<i32>> {
+    let liquid = map.get(coord).liquid.as_ref()?;
+    let state = liquid
+        .material
+        .fetch(materials)
+        .states
+        .get(&MaterialState::Liquid)?;
+
+    Some(nutrition_for(&state.nutrition, kind))
+}
+
+fn nutrition_for(nutrition: &Nutrition, kind: NeedKind) -> Range<i32> {
+    match kind {
+        NeedKind::Calories => nutrition.calories.clone(),
+        NeedKind::Hydration => nutrition.hydration.clone(),
+        _ => unimplemented!(),
+    }
+}
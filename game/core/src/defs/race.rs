@@ -10,6 +10,7 @@
 use crate::{
     defs::{
         body::{BodyDefinition, BodyRef},
+        needs::NeedKind,
         DefinitionDetails, DefinitionResolver, DefinitionStorage,
     },
     legion::prelude::*,
@@ -222,6 +223,24 @@ pub struct RaceAttributes {
     pub deviation: Attributes,
 }
 
+/// How quickly a need drains for members of a race
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct NeedRate {
+    pub kind: NeedKind,
+    /// Change to the need every `frequency` world seconds
+    pub value: i32,
+    pub frequency: f64,
+}
+impl NeedRate {
+    pub fn new(kind: NeedKind, value: i32, frequency: f64) -> Self {
+        Self {
+            kind,
+            value,
+            frequency,
+        }
+    }
+}
+
 #[derive(Definition, Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[definition(resolver = "Self")]
 pub struct RaceDefinition {
@@ -237,6 +256,18 @@ pub struct RaceDefinition {
 
     #[serde(default = "Attributes::default_with_deviation")]
     pub attributes: RaceAttributes, //base, deviation
+
+    #[serde(default = "RaceDefinition::default_needs")]
+    pub needs: Vec<NeedRate>,
+}
+impl RaceDefinition {
+    pub fn default_needs() -> Vec<NeedRate> {
+        vec![
+            NeedRate::new(NeedKind::Calories, -1, 1.0),
+            NeedRate::new(NeedKind::Hydration, -1, 1.0),
+            NeedRate::new(NeedKind::Sleep, -1, 1.0),
+        ]
+    }
 }
 impl DefinitionResolver<Self> for RaceDefinition {
     fn resolve(def: &mut Self, resources: &Resources) -> Result<(), failure::Error> {
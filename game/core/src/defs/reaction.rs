@@ -4,13 +4,15 @@ use crate::defs::{
     condition::ConditionSetRef,
     item::{ItemDefinition, ItemRef},
     material::{MaterialLimit, MaterialState},
+    needs::NeedKind,
     DefinitionDetails, DefinitionResolver, DefinitionStorage,
 };
-use crate::{failure, legion::prelude::*};
+use crate::{failure, legion::prelude::*, map::tile::TileKind};
 use rl_macros::Definition;
+use strum_macros::EnumDiscriminants;
 
 #[derive(
-    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
 )]
 pub enum ReactionCategory {
     MapTransformation,
@@ -92,16 +94,64 @@ impl Product {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-pub enum EffectParameters {
-    Todo, // TODO:
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, EnumDiscriminants, serde::Serialize, serde::Deserialize,
+)]
+#[strum_discriminants(name(EffectParameterKind), derive(Hash))]
+pub enum EffectParameter {
+    /// The ramp made on a tile, such as the one left above a channel
+    Ramp(TileKind),
+    /// How many items the effect creates, such as the logs from a felled tree
+    Count(usize),
+    /// The need the effect satisfies
+    Need(NeedKind),
+    /// How many tiles around the target the effect also reaches
+    Radius(u32),
+}
+
+/// The parameters given to an effect by a reaction, checked against the effect's schema on load
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct EffectParameters(pub Vec<EffectParameter>);
+impl EffectParameters {
+    pub fn iter(&self) -> impl Iterator<Item = &EffectParameter> {
+        self.0.iter()
+    }
+
+    pub fn ramp(&self) -> Option<TileKind> {
+        self.iter().find_map(|parameter| match *parameter {
+            EffectParameter::Ramp(kind) => Some(kind),
+            _ => None,
+        })
+    }
+
+    pub fn count(&self) -> Option<usize> {
+        self.iter().find_map(|parameter| match *parameter {
+            EffectParameter::Count(count) => Some(count),
+            _ => None,
+        })
+    }
+
+    pub fn need(&self) -> Option<NeedKind> {
+        self.iter().find_map(|parameter| match *parameter {
+            EffectParameter::Need(kind) => Some(kind),
+            _ => None,
+        })
+    }
+
+    pub fn radius(&self) -> Option<u32> {
+        self.iter().find_map(|parameter| match *parameter {
+            EffectParameter::Radius(radius) => Some(radius),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ReactionEffect {
     pub name: String,
     #[serde(default)]
-    pub parameters: Option<EffectParameters>,
+    pub parameters: EffectParameters,
 }
 
 #[derive(Definition, Debug, serde::Serialize, serde::Deserialize)]
@@ -115,6 +165,9 @@ pub struct ReactionDefinition {
     pub reagents: Vec<Reagent>,
     #[serde(default)]
     pub product: Option<Product>,
+    /// Produced alongside `product`, such as the bones and skin of a butchered corpse
+    #[serde(default)]
+    pub byproducts: Vec<Product>,
     #[serde(default)]
     pub effects: Vec<ReactionEffect>,
     #[serde(default)]
@@ -133,7 +186,7 @@ impl DefinitionResolver<Self> for ReactionDefinition {
             }
         }
 
-        if let Some(product) = def.product.as_mut() {
+        for product in def.product.iter_mut().chain(def.byproducts.iter_mut()) {
             if let ProductKind::Item(item) = &mut product.kind {
                 item.resolve(&items)?;
             }
@@ -142,3 +195,31 @@ impl DefinitionResolver<Self> for ReactionDefinition {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effect_parameters_ron_test() -> Result<(), failure::Error> {
+        let effect = ron::de::from_str::<ReactionEffect>(
+            r#"( name: "TileChannelEffect", parameters: [ Ramp(RampUpWest) ] )"#,
+        )?;
+        assert_eq!(
+            effect.parameters,
+            EffectParameters(vec![EffectParameter::Ramp(TileKind::RampUpWest)])
+        );
+        assert_eq!(effect.parameters.ramp(), Some(TileKind::RampUpWest));
+
+        let s = ron::ser::to_string(&effect)?;
+        let again = ron::de::from_str::<ReactionEffect>(&s)?;
+        assert_eq!(again.name, effect.name);
+        assert_eq!(again.parameters, effect.parameters);
+
+        // Parameters are optional
+        let effect = ron::de::from_str::<ReactionEffect>(r#"( name: "TileDigEffect" )"#)?;
+        assert!(effect.parameters.0.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    defs::{Definition, DefinitionDetails, DefinitionResolver, DefinitionStorage},
+    legion::prelude::*,
+};
+use rl_macros::Definition;
+use std::ops::Range;
+
+/// Lowest and highest mood a pawn can have
+pub const MOOD_RANGE: Range<i32> = -100..100;
+
+/// Things which happen to a pawn that thoughts can be declared for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum ThoughtTrigger {
+    /// Magnitude is the calories eaten
+    AteMeal,
+    AteWithoutTable,
+    SleptOnFloor,
+    SleptInBed,
+    /// Magnitude is the number of corpses in sight
+    SawCorpse,
+    /// Magnitude is the number of rotting corpses close enough to smell
+    SmelledMiasma,
+    RainedOn,
+    Conversation,
+    Complimented,
+    Insulted,
+    Breakdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ThoughtEvent {
+    pub entity: Entity,
+    pub trigger: ThoughtTrigger,
+    pub magnitude: i32,
+}
+impl ThoughtEvent {
+    pub fn new(entity: Entity, trigger: ThoughtTrigger, magnitude: i32) -> Self {
+        Self {
+            entity,
+            trigger,
+            magnitude,
+        }
+    }
+}
+
+#[derive(Definition, Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[definition(resolver = "Self")]
+pub struct ThoughtDefinition {
+    pub details: DefinitionDetails,
+
+    #[serde(skip)]
+    pub id: ThoughtDefinitionId,
+
+    pub trigger: ThoughtTrigger,
+
+    /// Only events with a magnitude in this range cause the thought
+    #[serde(default = "ThoughtDefinition::default_magnitude")]
+    pub magnitude: Range<i32>,
+
+    /// Mood when the thought is fresh, fading to nothing as it expires
+    pub mood: i32,
+
+    /// Hours the thought lasts, must be positive
+    pub duration: f64,
+
+    /// How many of this thought can be held at once, further triggers refresh the oldest
+    #[serde(default = "ThoughtDefinition::default_stack_limit")]
+    pub stack_limit: usize,
+}
+impl ThoughtDefinition {
+    fn default_magnitude() -> Range<i32> {
+        std::i32::MIN..std::i32::MAX
+    }
+
+    fn default_stack_limit() -> usize {
+        1
+    }
+
+    pub fn matches(&self, event: &ThoughtEvent) -> bool {
+        self.trigger == event.trigger && self.magnitude.contains(&event.magnitude)
+    }
+}
+impl DefinitionResolver<Self> for ThoughtDefinition {
+    fn resolve(def: &mut Self, _resources: &Resources) -> Result<(), failure::Error> {
+        // Mood fades over the duration, so a thought has to last for some time
+        if def.duration <= 0.0 {
+            return Err(failure::format_err!(
+                "Thought '{}' has a duration of {} hours",
+                def.name(),
+                def.duration
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thought {
+    pub def: ThoughtDefinitionId,
+    pub started: f64,
+    pub expires: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MoodComponent {
+    pub thoughts: Vec<Thought>,
+    pub mood: i32,
+}
+impl MoodComponent {
+    pub fn add(&mut self, def: &ThoughtDefinition, now: f64) {
+        let expires = now + def.duration * 3600.0;
+
+        let mut existing = self
+            .thoughts
+            .iter_mut()
+            .filter(|thought| thought.def == def.id)
+            .collect::<Vec<_>>();
+
+        if existing.len() >= def.stack_limit {
+            if let Some(oldest) = existing
+                .iter_mut()
+                .min_by(|a, b| a.started.partial_cmp(&b.started).unwrap())
+            {
+                oldest.started = now;
+                oldest.expires = expires;
+            }
+            return;
+        }
+
+        self.thoughts.push(Thought {
+            def: def.id,
+            started: now,
+            expires,
+        });
+    }
+
+    /// Drop expired thoughts and recalculate mood from the rest
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn update(&mut self, defs: &DefinitionStorage<ThoughtDefinition>, now: f64) {
+        self.thoughts.retain(|thought| thought.expires > now);
+
+        let mood = self
+            .thoughts
+            .iter()
+            .map(|thought| {
+                let remaining = (thought.expires - now) / (thought.expires - thought.started);
+                f64::from(defs.get(thought.def).unwrap().mood) * remaining
+            })
+            .sum::<f64>();
+
+        self.mood = (mood.round() as i32)
+            .max(MOOD_RANGE.start)
+            .min(MOOD_RANGE.end);
+    }
+
+    /// Multiplier on work speed, unhappy pawns work at down to half speed
+    pub fn work_speed(&self) -> f64 {
+        if self.mood >= 0 {
+            1.0
+        } else {
+            1.0 + f64::from(self.mood) / f64::from(MOOD_RANGE.start.abs()) / 2.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_duration_test() -> Result<(), failure::Error> {
+        let mut def = ron::de::from_str::<ThoughtDefinition>(
+            r#"(
+                details: ( name: "Fleeting", description: "" ),
+                trigger: RainedOn,
+                mood: -2,
+                duration: 0.0,
+            )"#,
+        )?;
+        assert!(ThoughtDefinition::resolve(&mut def, &Resources::default()).is_err());
+
+        def.duration = 1.0;
+        assert!(ThoughtDefinition::resolve(&mut def, &Resources::default()).is_ok());
+
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
     components::{CarryComponent, ItemContainerComponent},
-    data::DimensionsVec,
+    data::{DimensionsVec, PartGraphId},
     defs::{
-        body::{BodyComponent, BodyDefinition},
+        body::{BodyComponent, BodyDefinition, PartFlag},
         item::{ItemComponent, ItemDefinition},
     },
     legion::prelude::*,
@@ -10,13 +10,7 @@ use crate::{
 use std::cmp::Ordering;
 
 pub fn can_contain_item(container: &ItemContainerComponent, item: &ItemDefinition) -> bool {
-    if let Ordering::Greater =
-        compare_volumes(container.consumed + item.dimensions, container.capacity)
-    {
-        true
-    } else {
-        false
-    }
+    compare_volumes(container.consumed + item.dimensions, container.capacity) == Ordering::Less
 }
 
 #[allow(clippy::many_single_char_names)]
@@ -35,7 +29,23 @@ pub fn compare_volumes(a: DimensionsVec, b: DimensionsVec) -> Ordering {
     Ordering::Less
 }
 
-pub fn can_carry(_body: &BodyComponent, _body_def: &BodyDefinition) {}
+/// Whether the body has any working `MANIPULATE` part left to carry with
+pub fn can_carry(body: &BodyComponent, body_def: &BodyDefinition) -> bool {
+    body.count_parts(body_def, PartFlag::MANIPULATE) > 0
+}
+
+/// First empty limb which still works, bodiless carriers can use any empty limb
+pub fn free_limb(
+    carry: &CarryComponent,
+    body: Option<(&BodyComponent, &BodyDefinition)>,
+) -> Option<PartGraphId> {
+    carry
+        .limbs
+        .iter()
+        .filter(|(_, item)| item.is_none())
+        .map(|(limb, _)| *limb)
+        .find(|limb| body.map_or(true, |(body, def)| body.is_part_functional(def, *limb)))
+}
 
 pub fn carry_has_item(carry: &CarryComponent, item: Entity) -> bool {
     for limb in &carry.limbs {
@@ -90,6 +100,17 @@ pub fn remove_item(world: &World, holder: Entity, item: Entity) -> bool {
             if carry_has_item(&carry, item) {
                 return carry.remove(item);
             }
+
+            // Items inside a carried container, such as a hauling bin
+            for carried in carry.iter() {
+                if let Some(mut container) =
+                    world.get_component_mut_unchecked::<ItemContainerComponent>(carried)
+                {
+                    if container_has_item(&container, item) {
+                        return container.remove(item);
+                    }
+                }
+            }
         }
     }
 
@@ -128,7 +149,7 @@ pub fn for_all_items_recursive_impl<F: FnMut(Entity, (Entity, &ItemComponent)) -
                 }
             }
             if world.has_component::<ItemContainerComponent>(item_entity) {
-                if let Some(ret) = for_all_items_recursive_impl(source, world, f) {
+                if let Some(ret) = for_all_items_recursive_impl(item_entity, world, f) {
                     return Some(ret);
                 }
             }
@@ -142,7 +163,7 @@ pub fn for_all_items_recursive_impl<F: FnMut(Entity, (Entity, &ItemComponent)) -
                 }
             }
             if world.has_component::<ItemContainerComponent>(*item_entity) {
-                if let Some(ret) = for_all_items_recursive_impl(source, world, f) {
+                if let Some(ret) = for_all_items_recursive_impl(*item_entity, world, f) {
                     return Some(ret);
                 }
             }
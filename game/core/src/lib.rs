@@ -92,7 +92,9 @@ pub mod map;
 pub mod math;
 pub mod morton;
 pub mod saveload;
+pub mod schedule;
 pub mod settings;
+pub mod skills;
 pub mod systems;
 pub mod time;
 pub mod transform;
@@ -102,6 +104,8 @@ pub fn is_game_tick(state: &GameState) -> bool {
 
     // Update world time for a tick
     time.world_time += 30.0;
+    let world_time = time.world_time;
+    time.date.advance(world_time);
 
     true
 }
@@ -421,6 +425,14 @@ impl<T: Copy + Eq + std::hash::Hash> FreeList<T> {
         }
         None
     }
+    /// Consumes `value` if it is still free
+    pub fn take(&mut self, value: &T) -> Option<T> {
+        let res = self.free.remove_item(value);
+        if let Some(res) = res {
+            self.consumed.insert(res);
+        }
+        res
+    }
     pub fn push(&mut self, value: T) -> Result<(), failure::Error> {
         if !self.consumed.remove(&value) {
             return Err(failure::format_err!("Item returned not already in the consumed list. This belongs to a different FreeList"));
@@ -0,0 +1,129 @@
+use crate::{legion::prelude::*, time::Time};
+use slotmap::SlotMap;
+
+pub const HOURS_PER_DAY: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ScheduleBlock {
+    Anything,
+    Work,
+    Leisure,
+    Sleep,
+}
+impl ScheduleBlock {
+    pub const ALL: [Self; 4] = [Self::Anything, Self::Work, Self::Leisure, Self::Sleep];
+}
+impl Default for ScheduleBlock {
+    fn default() -> Self {
+        Self::Anything
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleTemplate {
+    pub name: String,
+    pub blocks: [ScheduleBlock; HOURS_PER_DAY],
+}
+impl ScheduleTemplate {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            blocks: [ScheduleBlock::Anything; HOURS_PER_DAY],
+        }
+    }
+
+    pub fn block(&self, hour: u32) -> ScheduleBlock {
+        self.blocks[hour as usize % HOURS_PER_DAY]
+    }
+
+    /// Sets the block for every hour in `start..end`, wrapping past midnight
+    pub fn set(&mut self, start: u32, end: u32, block: ScheduleBlock) -> &mut Self {
+        let mut hour = start as usize % HOURS_PER_DAY;
+        let end = end as usize % HOURS_PER_DAY;
+        while hour != end {
+            self.blocks[hour] = block;
+            hour = (hour + 1) % HOURS_PER_DAY;
+        }
+        self
+    }
+
+    pub fn day_shift() -> Self {
+        let mut template = Self::new("Day Shift");
+        template
+            .set(22, 6, ScheduleBlock::Sleep)
+            .set(6, 8, ScheduleBlock::Anything)
+            .set(8, 18, ScheduleBlock::Work)
+            .set(18, 22, ScheduleBlock::Leisure);
+        template
+    }
+
+    pub fn night_shift() -> Self {
+        let mut template = Self::new("Night Shift");
+        template
+            .set(10, 18, ScheduleBlock::Sleep)
+            .set(18, 20, ScheduleBlock::Anything)
+            .set(20, 6, ScheduleBlock::Work)
+            .set(6, 10, ScheduleBlock::Leisure);
+        template
+    }
+}
+
+slotmap::new_key_type! { pub struct ScheduleHandle; }
+
+/// Schedule templates players can edit and assign to pawns
+pub struct ScheduleStorage {
+    pub templates: SlotMap<ScheduleHandle, ScheduleTemplate>,
+    pub default: ScheduleHandle,
+}
+impl Default for ScheduleStorage {
+    fn default() -> Self {
+        let mut templates = SlotMap::with_key();
+        let default = templates.insert(ScheduleTemplate::day_shift());
+        templates.insert(ScheduleTemplate::night_shift());
+
+        Self { templates, default }
+    }
+}
+impl ScheduleStorage {
+    pub fn get(&self, handle: ScheduleHandle) -> Option<&ScheduleTemplate> {
+        self.templates.get(handle)
+    }
+
+    pub fn get_mut(&mut self, handle: ScheduleHandle) -> Option<&mut ScheduleTemplate> {
+        self.templates.get_mut(handle)
+    }
+
+    pub fn insert(&mut self, template: ScheduleTemplate) -> ScheduleHandle {
+        self.templates.insert(template)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ScheduleHandle, &ScheduleTemplate)> {
+        self.templates.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduleComponent {
+    pub template: ScheduleHandle,
+}
+impl ScheduleComponent {
+    pub fn new(template: ScheduleHandle) -> Self {
+        Self { template }
+    }
+}
+
+/// The block `entity` should currently be in, `Anything` if it has no schedule
+pub fn current_block(world: &World, resources: &Resources, entity: Entity) -> ScheduleBlock {
+    let component = match world.get_component::<ScheduleComponent>(entity) {
+        Some(component) => *component,
+        None => return ScheduleBlock::Anything,
+    };
+
+    let (storage, time) = <(Read<ScheduleStorage>, Read<Time>)>::fetch(resources);
+
+    storage
+        .get(component.template)
+        .map_or(ScheduleBlock::Anything, |template| {
+            template.block(time.date.hour())
+        })
+}
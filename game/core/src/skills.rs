@@ -0,0 +1,147 @@
+use crate::{
+    defs::{item::ItemQuality, race::Attributes, reaction::ReactionCategory},
+    fxhash::FxHashMap,
+};
+use rand::Rng;
+
+pub const MAX_LEVEL: u32 = 20;
+
+/// Experience for completing any reaction, on top of one point per second of work
+pub const BASE_EXPERIENCE: f64 = 10.0;
+
+/// Attribute value an average member of a race has
+const AVERAGE_ATTRIBUTE: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Skill {
+    pub level: u32,
+    /// Experience towards the next level
+    pub experience: f64,
+}
+impl Skill {
+    /// Experience needed to go from `level` to the next
+    pub fn experience_to_next(level: u32) -> f64 {
+        100.0 * f64::from(level + 1)
+    }
+
+    /// 0.0 when unskilled, 1.0 at the maximum level
+    pub fn fraction(&self) -> f64 {
+        f64::from(self.level) / f64::from(MAX_LEVEL)
+    }
+
+    /// Multiplier on how long a reaction takes, halved at the maximum level
+    pub fn duration_modifier(&self) -> f64 {
+        1.0 / (1.0 + self.fraction())
+    }
+
+    /// Unskilled pawns succeed at the base chance and skilled ones more often, `modifier` scales how
+    /// much skill matters for a given product.
+    pub fn chance(&self, chance: f32, modifier: f32) -> f32 {
+        #[allow(clippy::cast_possible_truncation)]
+        let fraction = self.fraction() as f32;
+
+        (chance * (1.0 + modifier * fraction)).max(0.0).min(1.0)
+    }
+
+    /// Roll the quality of something made at this skill level
+    pub fn roll_quality<R: Rng>(&self, rng: &mut R) -> ItemQuality {
+        let roll = self.fraction() + rng.gen_range(-0.25, 0.25);
+
+        if roll < 0.1 {
+            ItemQuality::Poor
+        } else if roll < 0.45 {
+            ItemQuality::Normal
+        } else if roll < 0.75 {
+            ItemQuality::Good
+        } else if roll < 1.0 {
+            ItemQuality::Excellent
+        } else {
+            ItemQuality::Masterwork
+        }
+    }
+
+    /// Adds experience, returning true if a level was gained
+    pub fn gain(&mut self, experience: f64) -> bool {
+        if self.level >= MAX_LEVEL {
+            return false;
+        }
+
+        let start = self.level;
+        self.experience += experience;
+        while self.level < MAX_LEVEL && self.experience >= Self::experience_to_next(self.level) {
+            self.experience -= Self::experience_to_next(self.level);
+            self.level += 1;
+        }
+        if self.level >= MAX_LEVEL {
+            self.experience = 0.0;
+        }
+
+        self.level > start
+    }
+}
+
+/// How quickly a pawn learns a category relative to an average pawn, from the attributes it uses
+pub fn learning_rate(category: ReactionCategory, attributes: &Attributes) -> f64 {
+    let (a, b) = match category {
+        ReactionCategory::MapTransformation => (attributes.strength, attributes.endurance),
+        ReactionCategory::WorkshopProduction => (attributes.creativity, attributes.focus),
+        ReactionCategory::Construction => (attributes.spatial, attributes.strength),
+        ReactionCategory::PawnAction => (attributes.kinesthetic, attributes.agility),
+    };
+
+    (f64::from(a) + f64::from(b)) / 2.0 / AVERAGE_ATTRIBUTE
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SkillsComponent {
+    skills: FxHashMap<ReactionCategory, Skill>,
+}
+impl SkillsComponent {
+    pub fn get(&self, category: ReactionCategory) -> Skill {
+        self.skills.get(&category).copied().unwrap_or_default()
+    }
+
+    pub fn level(&self, category: ReactionCategory) -> u32 {
+        self.get(category).level
+    }
+
+    /// Adds experience scaled by the learning rate for the category, returning true on a level up
+    pub fn learn(
+        &mut self,
+        category: ReactionCategory,
+        experience: f64,
+        attributes: Option<&Attributes>,
+    ) -> bool {
+        let rate = attributes.map_or(1.0, |attributes| learning_rate(category, attributes));
+
+        self.skills
+            .entry(category)
+            .or_default()
+            .gain(experience * rate)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ReactionCategory, &Skill)> {
+        self.skills
+            .iter()
+            .map(|(category, skill)| (*category, skill))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn chance_test() {
+        let unskilled = Skill::default();
+        assert_eq!(unskilled.chance(0.5, 1.0), 0.5);
+
+        let master = Skill {
+            level: MAX_LEVEL,
+            experience: 0.0,
+        };
+        assert_eq!(master.chance(0.5, 1.0), 1.0);
+        assert_eq!(master.chance(0.5, 0.0), 0.5);
+    }
+}
@@ -4,11 +4,25 @@ use parking_lot::RwLock;
 use slotmap::SlotMap;
 use std::time::{Duration, Instant};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct GameDate {
     start_date: DateTime<Utc>,
     current_date: DateTime<Utc>,
 }
+impl Default for GameDate {
+    /// Midnight on the first day of spring, so the hour matches `Time::hour_of_day`
+    fn default() -> Self {
+        Self::new(Utc.ymd(1, 3, 1).and_hms(0, 0, 0))
+    }
+}
 impl GameDate {
     pub fn new(start_date: DateTime<Utc>) -> Self {
         Self {
@@ -16,6 +30,48 @@ impl GameDate {
             current_date: start_date,
         }
     }
+
+    /// Move the date to `world_time` seconds after the start
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn advance(&mut self, world_time: f64) {
+        self.current_date =
+            self.start_date + chrono::Duration::milliseconds((world_time * 1000.0) as i64);
+    }
+
+    pub fn date(&self) -> DateTime<Utc> {
+        self.current_date
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.current_date.hour()
+    }
+
+    /// Whole days since the start date
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    pub fn day(&self) -> u32 {
+        (self.current_date - self.start_date).num_days().max(0) as u32
+    }
+
+    pub fn season(&self) -> Season {
+        match self.current_date.month() {
+            3..=5 => Season::Spring,
+            6..=8 => Season::Summer,
+            9..=11 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+}
+impl std::fmt::Display for GameDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}, day {} {:02}:{:02}",
+            self.season(),
+            self.day() + 1,
+            self.current_date.hour(),
+            self.current_date.minute()
+        )
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -36,6 +92,9 @@ pub struct Time {
     pub world_time: f64,
     pub real_time: f64,
 
+    /// Calendar date of `world_time`
+    pub date: GameDate,
+
     pub last_tick: Instant,
 
     pub fps_accumluator: ArrayDeque<[f32; 32], Wrapping>,
@@ -52,6 +111,7 @@ impl Default for Time {
             real_delta: Duration::new(0, 0),
             world_time: 0.0,
             real_time: 0.0,
+            date: GameDate::default(),
             world_speed: 0.0,
             real_speed: 1.0,
             last_tick: Instant::now(),
@@ -133,6 +193,11 @@ impl Time {
             .progress(self.real_time, self.world_time)
     }
 
+    /// World hour of the day, in the range `0.0..24.0`
+    pub fn hour_of_day(&self) -> f64 {
+        (self.world_time / 3600.0) % 24.0
+    }
+
     pub fn stamp(&self) -> TimeStamp {
         TimeStamp {
             world: self.world_time,
@@ -14,14 +14,20 @@
 use num_derive::{FromPrimitive, ToPrimitive};
 use rl_core::{
     blackboard::Blackboard,
-    components::{Destroy, FoliageTag, PositionComponent},
+    components::{AttributesComponent, CorpseComponent, Destroy, FoliageTag, PositionComponent},
+    condition::{self, CheckConditionParameters},
     data::{SpawnArguments, SpawnEvent, SpawnPosition, SpawnTarget},
     defs::{
+        body::{BodyComponent, BodyDefinition, PartFlag},
         foliage::FoliageKind,
         item::{ItemAbility, ItemComponent, ItemDefinition, ItemProperty},
-        material::{MaterialComponent, MaterialLimit, MaterialState},
-        reaction::{ProductKind, ReactionDefinition, ReactionDefinitionId, Reagent},
-        DefinitionComponent, DefinitionStorage,
+        material::{MaterialComponent, MaterialDefinition, MaterialLimit, MaterialState},
+        reaction::{
+            EffectParameterKind, EffectParameters, ProductKind, ReactionCategory,
+            ReactionDefinition, ReactionDefinitionId, Reagent,
+        },
+        thought::MoodComponent,
+        Definition, DefinitionComponent, DefinitionStorage,
     },
     derivative::Derivative,
     dispatcher::{DispatcherBuilder, Stage},
@@ -36,6 +42,7 @@ use rl_core::{
         Map,
     },
     math::Vec3i,
+    skills::{Skill, SkillsComponent, BASE_EXPERIENCE},
     smallvec::SmallVec,
     systems::progress_bar::ProgressBar,
     time::Time,
@@ -49,28 +56,48 @@ pub mod needs;
 
 pub fn effect_registration() -> Vec<ReactionEffectRegistration> {
     vec![
-        ReactionEffectRegistration::new("ConsumeEdibleEffect", needs::ConsumeEdibleEffect::new),
-        ReactionEffectRegistration::new(
-            "TileChannelEffect",
-            map_transformations::TileChannelEffect::new,
-        ),
-        ReactionEffectRegistration::new("TileDigEffect", map_transformations::TileDigEffect::new),
-        ReactionEffectRegistration::new("ProduceItemEffect", ProduceItemEffect::new),
-        ReactionEffectRegistration::new("TreeChopEffect", TreeChopEffect::new),
+        ReactionEffectRegistration::of::<needs::ConsumeEdibleEffect>(),
+        ReactionEffectRegistration::of::<map_transformations::TileChannelEffect>(),
+        ReactionEffectRegistration::of::<map_transformations::TileDigEffect>(),
+        ReactionEffectRegistration::of::<ProduceItemEffect>(),
+        ReactionEffectRegistration::of::<TreeChopEffect>(),
     ]
 }
 
+/// A parameter an effect reads from the reactions using it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectParameterSchema {
+    pub kind: EffectParameterKind,
+    pub required: bool,
+}
+
 pub trait ReactionEffect: Send + Sync {
     fn name() -> &'static str
     where
         Self: Sized;
 
+    fn parameters() -> &'static [EffectParameterSchema]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+
+    /// Checks the values of parameters which already match the schema
+    fn validate(_: &EffectParameters) -> Result<(), failure::Error>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
     fn new(
         _: GameStateRef,
         _: &ReactionDefinition,
         _: &ActiveReactionComponent,
         _: &BeginReactionEvent,
         _: &FxHashMap<Reagent, Entity>,
+        _: &EffectParameters,
     ) -> Box<dyn ReactionEffect>
     where
         Self: 'static + Sized + Default,
@@ -177,11 +204,60 @@ pub fn bundle(
 ) -> Result<(), failure::Error> {
     resources.insert(Channel::<BeginReactionEvent>::default());
 
+    if let Some(reactions) = resources.get::<DefinitionStorage<ReactionDefinition>>() {
+        validate_effects(&reactions)?;
+    }
+
     builder.add_thread_local_fn(Stage::Logic, build_execute_reaction_system);
 
     Ok(())
 }
 
+/// How quickly the initiator works through a reaction, relative to normal speed
+pub fn work_speed(world: &World, initiator: Option<ReactionEntity>) -> f64 {
+    initiator
+        .and_then(|initiator| world.get_component::<MoodComponent>(initiator.entity()))
+        .map_or(1.0, |mood| mood.work_speed())
+}
+
+/// Whether the initiator is able to work on a reaction, pawns need a working `MANIPULATE` part
+pub fn can_work(world: &World, resources: &Resources, initiator: Option<ReactionEntity>) -> bool {
+    let entity = match initiator {
+        Some(ReactionEntity::Pawn(entity)) => entity,
+        _ => return true,
+    };
+
+    world
+        .get_component::<BodyComponent>(entity)
+        .map_or(true, |body| {
+            let bodies = resources
+                .get::<DefinitionStorage<BodyDefinition>>()
+                .unwrap();
+            body.count_parts(body.fetch(&bodies), PartFlag::MANIPULATE) > 0
+        })
+}
+
+/// The initiators skill in the category, unskilled if it has none
+pub fn skill(
+    world: &World,
+    initiator: Option<ReactionEntity>,
+    category: ReactionCategory,
+) -> Skill {
+    initiator
+        .and_then(|initiator| world.get_component::<SkillsComponent>(initiator.entity()))
+        .map_or_else(Skill::default, |skills| skills.get(category))
+}
+
+fn learn(world: &mut World, entity: Entity, category: ReactionCategory, experience: f64) {
+    let attributes = world
+        .get_component::<AttributesComponent>(entity)
+        .map(|attributes| attributes.0);
+
+    if let Some(mut skills) = world.get_component_mut::<SkillsComponent>(entity) {
+        skills.learn(category, experience, attributes.as_ref());
+    }
+}
+
 pub fn build_execute_reaction_system(
     _: &mut World,
     resources: &mut Resources,
@@ -192,6 +268,7 @@ pub fn build_execute_reaction_system(
         .bind_listener(64);
 
     let mut remove_components = SmallVec::<[Entity; 16]>::default();
+    let mut learned = SmallVec::<[(Entity, ReactionCategory, f64); 16]>::default();
     let active_query = <(Write<ActiveReactionComponent>, Write<Option<ProgressBar>>)>::query();
 
     // Generate the effects table and store it
@@ -208,7 +285,18 @@ pub fn build_execute_reaction_system(
         for (entity, (mut active_reaction, mut progress_bar)) in
             unsafe { active_query.iter_entities_unchecked(world) }
         {
-            active_reaction.progress += time.world_delta;
+            // Lost the hands it was working with
+            if !can_work(world, resources, active_reaction.event.initiator) {
+                *progress_bar = None;
+                if let Some(callback) = &active_reaction.event.callback {
+                    (callback)(ReactionResult::Failure);
+                }
+                remove_components.push(entity);
+                continue;
+            }
+
+            let speed = work_speed(world, active_reaction.event.initiator);
+            active_reaction.progress += time.world_delta.mul_f64(speed);
             progress_bar.as_mut().unwrap().progress =
                 active_reaction.progress.as_secs_f64() / active_reaction.duration.as_secs_f64();
 
@@ -221,6 +309,7 @@ pub fn build_execute_reaction_system(
                 if let Ok(entities) = def.can_initiate(
                     GameStateRef { world, resources },
                     active_reaction.event.initiator.unwrap(),
+                    Some(active_reaction.event.target),
                 ) {
                     if let Some(_effect) = active_reaction.active_effect.as_mut() {
                         // TODO: just tick the current effect
@@ -250,6 +339,16 @@ pub fn build_execute_reaction_system(
                     }
                 };
 
+                if res == ReactionResult::Success {
+                    if let Some(initiator) = active_reaction.event.initiator {
+                        learned.push((
+                            initiator.entity(),
+                            def.category,
+                            BASE_EXPERIENCE + def.duration,
+                        ));
+                    }
+                }
+
                 if res.is_complete() {
                     if let Some(callback) = &active_reaction.event.callback {
                         (callback)(res);
@@ -267,12 +366,18 @@ pub fn build_execute_reaction_system(
                 .unwrap();
         }
 
+        for (entity, category, experience) in learned.drain(..) {
+            learn(world, entity, category, experience);
+        }
+
         // Add new ones
         while let Some(event) = channel.read(listener_id) {
             // Trigger the reaction as complete for now
             let def = reaction_defs.get(event.reaction).unwrap();
 
             let target = event.target.entity();
+            let duration =
+                def.duration * skill(world, event.initiator, def.category).duration_modifier();
 
             world
                 .add_component(
@@ -280,7 +385,7 @@ pub fn build_execute_reaction_system(
                     ActiveReactionComponent {
                         reaction: event.reaction,
                         started: time.world_time,
-                        duration: Duration::from_secs_f64(def.duration),
+                        duration: Duration::from_secs_f64(duration),
                         progress: Duration::from_secs_f64(0.0),
                         cancellable: false,
                         event,
@@ -296,11 +401,65 @@ pub fn build_execute_reaction_system(
     })
 }
 
+/// The entity products take their material from, the first consumed reagent or else the target
+fn source_entity(
+    def: &ReactionDefinition,
+    component: &ActiveReactionComponent,
+    reagent_entities: &FxHashMap<Reagent, Entity>,
+) -> Entity {
+    def.reagents
+        .iter()
+        .filter(|reagent| reagent.consume_chance > 0)
+        .find_map(|reagent| reagent_entities.get(reagent).copied())
+        .unwrap_or_else(|| component.event.target.entity())
+}
+
+/// Materials an entity can give its products, most abundant first. Corpses give each of their
+/// tissues, anything else its own material.
+fn source_materials(world: &World, entity: Entity) -> SmallVec<[MaterialComponent; 4]> {
+    if let Some(corpse) = world.get_component::<CorpseComponent>(entity) {
+        return corpse
+            .tissues
+            .iter()
+            .map(|(material, _)| MaterialComponent::new(*material, MaterialState::Solid))
+            .collect();
+    }
+
+    world
+        .get_component::<MaterialComponent>(entity)
+        .map(|material| *material)
+        .into_iter()
+        .collect()
+}
+
+/// The first source material within both the product's limit and the item's own limits, falling
+/// back to the most abundant source material
+fn product_material(
+    limit: &MaterialLimit,
+    item: &ItemDefinition,
+    sources: &[MaterialComponent],
+    materials: &DefinitionStorage<MaterialDefinition>,
+) -> MaterialComponent {
+    sources
+        .iter()
+        .copied()
+        .find(|material| {
+            limit.matches(material, materials)
+                && (item.material_limits.is_empty()
+                    || item
+                        .material_limits
+                        .iter()
+                        .any(|limit| limit.matches(material, materials)))
+        })
+        .unwrap_or(sources[0])
+}
+
 pub trait ReactionExecution {
     fn can_initiate(
         &self,
         state: GameStateRef,
         initiator: ReactionEntity,
+        target: Option<ReactionEntity>,
     ) -> Result<FxHashMap<Reagent, Entity>, Reagent>;
 
     fn can_designate(
@@ -339,41 +498,80 @@ impl ReactionExecution for ReactionDefinition {
         &self,
         state: GameStateRef,
         component: &ActiveReactionComponent,
-        _reagent_entities: &FxHashMap<Reagent, Entity>,
+        reagent_entities: &FxHashMap<Reagent, Entity>,
     ) -> ReactionResult {
         use rl_core::rand::{thread_rng, Rng};
 
-        if let Some(product) = self.product.as_ref() {
-            let map = state.resources.get::<Map>().unwrap();
-            let spatial_map = state.resources.get::<SpatialMap>().unwrap();
-            let spawn_channel = state.resources.get::<Channel<SpawnEvent>>().unwrap();
-            let src_coord = **state
-                .world
-                .get_component::<PositionComponent>(component.event.target.entity())
-                .unwrap();
+        if self.product.is_none() && self.byproducts.is_empty() {
+            return ReactionResult::Success;
+        }
 
-            let reaction_target_material = if let Some(material) = state
-                .world
-                .get_component::<MaterialComponent>(component.event.target.entity())
-            {
-                *material
-            } else {
-                // TODO: how do we discern the task source material?
-                // TODO: task can have a tile target or something or reagent for reaction?
-                MaterialComponent::new(map.get(src_coord).material.into(), MaterialState::Solid)
-            };
+        let map = state.resources.get::<Map>().unwrap();
+        let spatial_map = state.resources.get::<SpatialMap>().unwrap();
+        let spawn_channel = state.resources.get::<Channel<SpawnEvent>>().unwrap();
+        let (items, materials) = <(
+            Read<DefinitionStorage<ItemDefinition>>,
+            Read<DefinitionStorage<MaterialDefinition>>,
+        )>::fetch(state.resources);
+        let target = component.event.target.entity();
+        let src_coord = **state
+            .world
+            .get_component::<PositionComponent>(target)
+            .unwrap();
+
+        let mut sources = source_materials(
+            state.world,
+            source_entity(self, component, reagent_entities),
+        );
+        if sources.is_empty() {
+            sources = source_materials(state.world, target);
+        }
+        if sources.is_empty() {
+            // TODO: task can have a tile target or something or reagent for reaction?
+            sources.push(MaterialComponent::new(
+                map.get(src_coord).material.into(),
+                MaterialState::Solid,
+            ));
+        }
 
-            let mut pos_cache = SmallVec::<[Vec3i; 10]>::with_capacity(product.count);
+        let mut pos_cache = SmallVec::<[Vec3i; 10]>::new();
 
-            // TODO: seed
-            let mut rng = thread_rng();
+        // TODO: seed
+        let mut rng = thread_rng();
 
-            for _ in 0..product.count {
-                // TODO: check material limits
-                // just pull from src for now
+        let skill = skill(state.world, component.event.initiator, self.category);
+        let skilled = component.event.initiator.map_or(false, |initiator| {
+            state
+                .world
+                .has_component::<SkillsComponent>(initiator.entity())
+        });
+
+        // An effect's `Count`, such as the logs a tree gives, overrides how many of the product
+        let product_count = self
+            .effects
+            .iter()
+            .find_map(|effect| effect.parameters.count());
+        let products = self
+            .product
+            .iter()
+            .map(|product| (product, product_count.unwrap_or(product.count)))
+            .chain(
+                self.byproducts
+                    .iter()
+                    .map(|product| (product, product.count)),
+            );
+
+        for (product, count) in products {
+            let material = match &product.kind {
+                ProductKind::Item(item_ref) => item_ref.fetch(&items).map_or(sources[0], |item| {
+                    product_material(&product.material, item, &sources, &materials)
+                }),
+            };
 
+            for _ in 0..count {
                 if let Some(random) = product.random.as_ref() {
-                    if rng.gen_range(0.0, 1.0) >= random.chance {
+                    if rng.gen_range(0.0, 1.0) >= skill.chance(random.chance, random.skill_modifier)
+                    {
                         continue;
                     }
                 }
@@ -430,13 +628,17 @@ impl ReactionExecution for ReactionDefinition {
 
                 match &product.kind {
                     ProductKind::Item(item_ref) => {
+                        let quality = if skilled {
+                            Some(skill.roll_quality(&mut rng))
+                        } else {
+                            None
+                        };
+
                         // TODO: call item spawners
                         spawn_channel
                             .write(SpawnEvent {
                                 target: SpawnTarget::Position(SpawnPosition::Tile(target_coord)),
-                                kind: SpawnArguments::Item {
-                                    material: reaction_target_material,
-                                },
+                                kind: SpawnArguments::Item { material, quality },
                                 id: item_ref.id().into(),
                                 arguments: (),
                             })
@@ -455,59 +657,14 @@ impl ReactionExecution for ReactionDefinition {
         state: GameStateRef,
         _map: &Map,
         coord: Vec3i,
-        tile: &Tile,
+        _tile: &Tile,
     ) -> Result<Option<SmallVec<[Entity; 16]>>, ()> {
         let mut entities = SmallVec::<[Entity; 16]>::default();
+        let parameters = CheckConditionParameters::new(state.world, state.resources, None, None)
+            .with_tile(coord);
 
         for reagent in &self.reagents {
-
-            //Kind::Foliage(foliage_kind) => {
-            //                    let static_spatial_map = state.resources.get::<StaticSpatialMap>().unwrap();
-            //
-            //                    let local_entities = static_spatial_map
-            //                        .locate_all_at_point(&PositionComponent::new(coord))
-            //                        .filter_map(|entry| {
-            //                            if let Some(foliage) = state.world.get_tag::<FoliageTag>(entry.entity) {
-            //                                if **foliage == foliage_kind {
-            //                                    return Some(entry.entity);
-            //                                }
-            //                            }
-            //
-            //                            None
-            //                        })
-            //                        .collect::<Vec<_>>();
-            //
-            //                    if local_entities.is_empty() {
-            //                        return Err(());
-            //                    } else {
-            //                        entities.extend(local_entities);
-            //                    }
-            //                }
-            //                Kind::Tile { flags, kind } => {
-            //                    if let Some(flags) = flags {
-            //                        if !tile.flags.contains(flags) {
-            //                            return Err(());
-            //                        }
-            //                    }
-            //                    if let Some(kind) = kind {
-            //                        if kind != tile.kind {
-            //                            return Err(());
-            //                        }
-            //                    }
-            //                }
-            //                Kind::TileNot { flags, kind } => {
-            //                    if let Some(flags) = flags {
-            //                        if tile.flags.contains(flags) {
-            //                            return Err(());
-            //                        }
-            //                    }
-            //                    if let Some(kind) = kind {
-            //                        if kind == tile.kind {
-            //                            return Err(());
-            //                        }
-            //                    }
-            //                }
-            //                _ => {}
+            entities.extend(condition::check(&parameters, &reagent.conditions).map_err(|_| ())?);
         }
 
         if entities.is_empty() {
@@ -518,100 +675,39 @@ impl ReactionExecution for ReactionDefinition {
         }
     }
 
-    #[allow(clippy::single_match)] // TODO: Finish these
     fn can_initiate(
         &self,
         state: GameStateRef,
         initiator: ReactionEntity,
+        target: Option<ReactionEntity>,
     ) -> Result<FxHashMap<Reagent, Entity>, Reagent> {
         let mut entities =
             FxHashMap::with_capacity_and_hasher(self.reagents.len(), FxBuildHasher::default());
 
-        let position = state
-            .world
-            .get_component::<PositionComponent>(initiator.entity())
-            .unwrap();
+        // A workshop doesn't know who will work its order yet, that is checked once someone takes it
+        let entity = match initiator {
+            ReactionEntity::Workshop(_) => None,
+            initiator => Some(initiator.entity()),
+        };
+        let parameters = CheckConditionParameters::new(
+            state.world,
+            state.resources,
+            entity,
+            target.map(|target| target.entity()),
+        );
 
         for reagent in &self.reagents {
-            //Kind::Foliage(foliage_kind) => {
-            //                    let static_spatial_map = state.resources.get::<StaticSpatialMap>().unwrap();
-            //
-            //                    let local_entities = static_spatial_map
-            //                        .locate_all_at_point(&position)
-            //                        .filter_map(|entry| {
-            //                            if let Some(foliage) = state.world.get_tag::<FoliageTag>(entry.entity) {
-            //                                if **foliage == *foliage_kind {
-            //                                    return Some(entry.entity);
-            //                                }
-            //                            }
-            //
-            //                            None
-            //                        })
-            //                        .collect::<Vec<_>>();
-            //
-            //                    if local_entities.is_empty() {
-            //                        return Err(reagent.kind.clone());
-            //                    } else {
-            //                        entities.extend(local_entities.into_iter().map(|e| (reagent.clone(), e)));
-            //                    }
-            //                }
-            //                Kind::Item(item_ref) => {
-            //                    match check_item_available(
-            //                        state,
-            //                        initiator.entity(),
-            //                        &item_ref,
-            //                        reagent.materials.as_slice(),
-            //                    ) {
-            //                        Ok(entity) => {
-            //                            entities.insert(reagent.clone(), entity);
-            //                        }
-            //                        Err(_) => return Err(reagent.kind.clone()),
-            //                    }
-            //                }
-            //                Kind::ItemAbility(ability_desc) => {
-            //                    // Does any item the pawn is has on them satisify this?
-            //                    // Check CarryComponent and ItemContainerComponent
-            //                    match check_item_ability(
-            //                        state,
-            //                        initiator.entity(),
-            //                        ability_desc.kind,
-            //                        ability_desc.quality,
-            //                        reagent.materials.as_slice(),
-            //                    ) {
-            //                        Ok(entity) => {
-            //                            entities.insert(reagent.clone(), entity);
-            //                        }
-            //                        Err(_) => return Err(reagent.kind.clone()),
-            //                    }
-            //                }
-            //                Kind::ItemConsumable(_) => {
-            //                    match check_item_property(
-            //                        state,
-            //                        initiator.entity(),
-            //                        ItemProperty::IsEdible,
-            //                        reagent.materials.as_slice(),
-            //                    ) {
-            //                        Ok(entity) => {
-            //                            entities.insert(reagent.clone(), entity);
-            //                        }
-            //                        Err(_) => return Err(reagent.kind.clone()),
-            //                    }
-            //                }
-            //                Kind::ItemProperty(property) => {
-            //                    // Does any item the pawn is has on them satisify this?
-            //                    // Check CarryComponent and ItemContainerComponent
-            //                    match check_item_property(
-            //                        state,
-            //                        initiator.entity(),
-            //                        *property,
-            //                        reagent.materials.as_slice(),
-            //                    ) {
-            //                        Ok(entity) => {
-            //                            entities.insert(reagent.clone(), entity);
-            //                        }
-            //                        Err(_) => return Err(reagent.kind.clone()),
-            //                    }
-            //                }
+            match condition::check(&parameters, &reagent.conditions) {
+                Ok(found) => {
+                    if let Some(entity) = found.first() {
+                        entities.insert(reagent.clone(), *entity);
+                    }
+                }
+                Err(failed) => {
+                    log::trace!("Reagent condition failed: {:?}", failed);
+                    return Err(reagent.clone());
+                }
+            }
         }
 
         Ok(entities)
@@ -626,14 +722,20 @@ impl ReactionExecution for ReactionDefinition {
     ) -> ReactionResult {
         let effect_table = state.resources.get::<ReactionEffectTable>().unwrap();
 
-        match self.can_initiate(state, event.initiator.unwrap()) {
+        match self.can_initiate(state, event.initiator.unwrap(), Some(event.target)) {
             Ok(reagent_entities) => {
                 for effect in &self.effects {
                     let construct_reaction_fn = effect_table
                         .get(&fnv_hash(effect.name.as_str()))
                         .expect(&format!("Failed to find effect: {}", &effect.name));
-                    let mut reaction_obj =
-                        (construct_reaction_fn)(state, self, component, event, &reagent_entities);
+                    let mut reaction_obj = (construct_reaction_fn)(
+                        state,
+                        self,
+                        component,
+                        event,
+                        &reagent_entities,
+                        &effect.parameters,
+                    );
                     // TODO:
                     return reaction_obj.tick(state, self, component, event, &reagent_entities);
                 }
@@ -672,6 +774,7 @@ pub type ReactionEffectProducer = fn(
     &ActiveReactionComponent,
     &BeginReactionEvent,
     &FxHashMap<Reagent, Entity>,
+    &EffectParameters,
 ) -> Box<dyn ReactionEffect>;
 
 pub type ReactionEffectTable =
@@ -680,14 +783,90 @@ pub type ReactionEffectTable =
 pub struct ReactionEffectRegistration {
     name: String,
     producer: ReactionEffectProducer,
+    parameters: &'static [EffectParameterSchema],
+    validate: fn(&EffectParameters) -> Result<(), failure::Error>,
 }
 impl ReactionEffectRegistration {
-    pub fn new(name: &str, producer: ReactionEffectProducer) -> Self {
+    pub fn of<E>() -> Self
+    where
+        E: 'static + ReactionEffect + Default,
+    {
         Self {
-            name: name.to_string(),
-            producer,
+            name: E::name().to_string(),
+            producer: E::new,
+            parameters: E::parameters(),
+            validate: E::validate,
+        }
+    }
+
+    pub fn validate(&self, parameters: &EffectParameters) -> Result<(), failure::Error> {
+        for parameter in parameters.iter() {
+            let kind = EffectParameterKind::from(parameter);
+            if !self.parameters.iter().any(|schema| schema.kind == kind) {
+                return Err(failure::format_err!(
+                    "{} does not take a {:?} parameter",
+                    self.name,
+                    kind
+                ));
+            }
+            if parameters
+                .iter()
+                .filter(|other| EffectParameterKind::from(*other) == kind)
+                .count()
+                > 1
+            {
+                return Err(failure::format_err!(
+                    "{} was given more than one {:?} parameter",
+                    self.name,
+                    kind
+                ));
+            }
+        }
+
+        if let Some(missing) = self.parameters.iter().find(|schema| {
+            schema.required
+                && !parameters
+                    .iter()
+                    .any(|parameter| EffectParameterKind::from(parameter) == schema.kind)
+        }) {
+            return Err(failure::format_err!(
+                "{} requires a {:?} parameter",
+                self.name,
+                missing.kind
+            ));
+        }
+
+        (self.validate)(parameters)
+            .map_err(|e| failure::format_err!("{} parameters are invalid: {}", self.name, e))
+    }
+}
+
+/// Checks every reaction names a registered effect and gives it parameters it accepts
+pub fn validate_effects(
+    reactions: &DefinitionStorage<ReactionDefinition>,
+) -> Result<(), failure::Error> {
+    let registrations = effect_registration();
+
+    for reaction in reactions.iter() {
+        for effect in &reaction.effects {
+            let registration = registrations
+                .iter()
+                .find(|registration| registration.name == effect.name)
+                .ok_or_else(|| {
+                    failure::format_err!(
+                        "Reaction '{}': unknown effect '{}'",
+                        reaction.name(),
+                        effect.name
+                    )
+                })?;
+
+            registration
+                .validate(&effect.parameters)
+                .map_err(|e| failure::format_err!("Reaction '{}': {}", reaction.name(), e))?;
         }
     }
+
+    Ok(())
 }
 
 pub fn create_effect_table() -> ReactionEffectTable {
@@ -719,6 +898,7 @@ impl ReactionEffect for ProduceItemEffect {
     }
 }
 
+/// Fells a tree. Its `Count` parameter sets how many logs, the reaction's product, it leaves.
 #[derive(Default)]
 pub struct TreeChopEffect;
 impl ReactionEffect for TreeChopEffect {
@@ -726,6 +906,13 @@ impl ReactionEffect for TreeChopEffect {
         "TreeChopEffect"
     }
 
+    fn parameters() -> &'static [EffectParameterSchema] {
+        &[EffectParameterSchema {
+            kind: EffectParameterKind::Count,
+            required: false,
+        }]
+    }
+
     fn tick(
         &mut self,
         state: GameStateRef,
@@ -768,6 +955,16 @@ impl ReactionEffect for TreeChopEffect {
             }
         };
 
+        if let Some(position) = state.world.get_component::<PositionComponent>(tree_entity) {
+            map_transformations::make_noise(
+                &state,
+                event,
+                **position,
+                rl_core::data::NoiseKind::Chopping,
+                10,
+            );
+        }
+
         let command_buffer =
             unsafe { <Write<GlobalCommandBuffer>>::fetch_unchecked(state.resources) };
 
@@ -776,3 +973,133 @@ impl ReactionEffect for TreeChopEffect {
         ReactionResult::Success
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rl_core::{
+        defs::{
+            needs::NeedKind,
+            reaction::{EffectParameter, ReactionEffect as EffectDefinition},
+            DefinitionDetails,
+        },
+        map::tile::TileKind,
+        GameState,
+    };
+
+    #[derive(Default)]
+    struct RadiusEffect;
+    impl ReactionEffect for RadiusEffect {
+        fn name() -> &'static str {
+            "RadiusEffect"
+        }
+
+        fn parameters() -> &'static [EffectParameterSchema] {
+            &[EffectParameterSchema {
+                kind: EffectParameterKind::Radius,
+                required: true,
+            }]
+        }
+
+        fn tick(
+            &mut self,
+            _: GameStateRef,
+            _: &ReactionDefinition,
+            _: &ActiveReactionComponent,
+            _: &BeginReactionEvent,
+            _: &FxHashMap<Reagent, Entity>,
+        ) -> ReactionResult {
+            ReactionResult::Success
+        }
+    }
+
+    fn parameters(parameters: &[EffectParameter]) -> EffectParameters {
+        EffectParameters(parameters.to_vec())
+    }
+
+    #[test]
+    fn validate_parameters_test() {
+        let channel = ReactionEffectRegistration::of::<map_transformations::TileChannelEffect>();
+        let consume = ReactionEffectRegistration::of::<needs::ConsumeEdibleEffect>();
+        let radius = ReactionEffectRegistration::of::<RadiusEffect>();
+
+        let ramp = EffectParameter::Ramp(TileKind::RampUpWest);
+        assert!(channel.validate(&parameters(&[])).is_ok());
+        assert!(channel.validate(&parameters(&[ramp])).is_ok());
+
+        // Unknown and duplicate kinds
+        assert!(channel
+            .validate(&parameters(&[EffectParameter::Count(5)]))
+            .is_err());
+        assert!(channel.validate(&parameters(&[ramp, ramp])).is_err());
+
+        // Missing required kinds
+        assert!(radius.validate(&parameters(&[])).is_err());
+        assert!(radius
+            .validate(&parameters(&[EffectParameter::Radius(2)]))
+            .is_ok());
+
+        // Values the effect itself refuses
+        assert!(channel
+            .validate(&parameters(&[EffectParameter::Ramp(TileKind::Floor)]))
+            .is_err());
+        assert!(consume
+            .validate(&parameters(&[EffectParameter::Need(NeedKind::Sleep)]))
+            .is_err());
+        assert!(consume
+            .validate(&parameters(&[EffectParameter::Need(NeedKind::Calories)]))
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_effects_test() -> Result<(), failure::Error> {
+        let mut state = GameState::default();
+        DefinitionStorage::<MaterialDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/materials",
+        )?;
+        DefinitionStorage::<ItemDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/items",
+        )?;
+        DefinitionStorage::<ReactionDefinition>::from_folder(
+            &mut state.resources,
+            "../assets/defs/reactions",
+        )?;
+
+        // Everything shipped is valid
+        validate_effects(
+            &state
+                .resources
+                .get::<DefinitionStorage<ReactionDefinition>>()
+                .unwrap(),
+        )?;
+
+        let reaction = |effect: &str, parameters: &[EffectParameter]| {
+            let mut reactions = DefinitionStorage::<ReactionDefinition>::new(&"test");
+            reactions.insert(ReactionDefinition {
+                details: DefinitionDetails::new("Test"),
+                category: ReactionCategory::PawnAction,
+                id: ReactionDefinitionId::default(),
+                reagents: Vec::new(),
+                product: None,
+                byproducts: Vec::new(),
+                effects: vec![EffectDefinition {
+                    name: effect.to_owned(),
+                    parameters: EffectParameters(parameters.to_vec()),
+                }],
+                duration: 1.0,
+            });
+            validate_effects(&reactions)
+        };
+        assert!(reaction("TreeChopEffect", &[EffectParameter::Count(5)]).is_ok());
+        assert!(reaction("FellEffect", &[]).is_err());
+        assert!(reaction(
+            "ConsumeEdibleEffect",
+            &[EffectParameter::Need(NeedKind::Sleep)]
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
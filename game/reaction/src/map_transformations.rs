@@ -1,8 +1,13 @@
-use crate::{ActiveReactionComponent, BeginReactionEvent, ReactionEffect, ReactionResult};
+use crate::{
+    ActiveReactionComponent, BeginReactionEvent, EffectParameterSchema, ReactionEffect,
+    ReactionResult,
+};
 use rl_core::{
     components::{Destroy, PositionComponent},
-    defs::reaction::{ReactionDefinition, Reagent},
+    data::{NoiseEvent, NoiseKind},
+    defs::reaction::{EffectParameterKind, EffectParameters, ReactionDefinition, Reagent},
     event::Channel,
+    failure,
     fxhash::FxHashMap,
     garbage_collector::DestroyEvent,
     legion::prelude::*,
@@ -27,13 +32,77 @@ fn handle_foliage(position: &PositionComponent, state: &GameStateRef) {
         });
 }
 
-#[derive(Default)]
-pub struct TileChannelEffect;
+pub(crate) fn make_noise(
+    state: &GameStateRef,
+    event: &BeginReactionEvent,
+    position: Vec3i,
+    kind: NoiseKind,
+    volume: u32,
+) {
+    if let Some(channel) = state.resources.get::<Channel<NoiseEvent>>() {
+        channel
+            .write(NoiseEvent::new(
+                event.initiator.map(|initiator| initiator.entity()),
+                position,
+                kind,
+                volume,
+            ))
+            .unwrap();
+    }
+}
+
+const RAMPS: [TileKind; 4] = [
+    TileKind::RampUpNorth,
+    TileKind::RampUpSouth,
+    TileKind::RampUpEast,
+    TileKind::RampUpWest,
+];
+
+/// Empties the target tile and makes the tile above it into a ramp, `RampUpWest` by default
+pub struct TileChannelEffect {
+    ramp: TileKind,
+}
+impl Default for TileChannelEffect {
+    fn default() -> Self {
+        Self {
+            ramp: TileKind::RampUpWest,
+        }
+    }
+}
 impl ReactionEffect for TileChannelEffect {
     fn name() -> &'static str {
         "TileChannelEffect"
     }
 
+    fn parameters() -> &'static [EffectParameterSchema] {
+        &[EffectParameterSchema {
+            kind: EffectParameterKind::Ramp,
+            required: false,
+        }]
+    }
+
+    fn validate(parameters: &EffectParameters) -> Result<(), failure::Error> {
+        match parameters.ramp() {
+            Some(kind) if !RAMPS.contains(&kind) => {
+                Err(failure::format_err!("{:?} is not a ramp", kind))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn new(
+        _: GameStateRef,
+        _: &ReactionDefinition,
+        _: &ActiveReactionComponent,
+        _: &BeginReactionEvent,
+        _: &FxHashMap<Reagent, Entity>,
+        parameters: &EffectParameters,
+    ) -> Box<dyn ReactionEffect> {
+        Box::new(Self {
+            ramp: parameters.ramp().unwrap_or(TileKind::RampUpWest),
+        })
+    }
+
     fn tick(
         &mut self,
         state: GameStateRef,
@@ -57,24 +126,49 @@ impl ReactionEffect for TileChannelEffect {
                 .make_empty(target_coord)
                 .make_ramp(
                     Vec3i::new(target_coord.x, target_coord.y, target_coord.z + 1),
-                    TileKind::RampUpWest,
+                    self.ramp,
                 )
                 .finish();
         }
 
         handle_foliage(&position, &state);
+        make_noise(&state, event, **position, NoiseKind::Digging, 12);
 
         ReactionResult::Success
     }
 }
 
+/// Makes the target tile into a floor, along with any solid tile within the `Radius` parameter on
+/// the same level
 #[derive(Default)]
-pub struct TileDigEffect;
+pub struct TileDigEffect {
+    radius: u32,
+}
 impl ReactionEffect for TileDigEffect {
     fn name() -> &'static str {
         "TileDigEffect"
     }
 
+    fn parameters() -> &'static [EffectParameterSchema] {
+        &[EffectParameterSchema {
+            kind: EffectParameterKind::Radius,
+            required: false,
+        }]
+    }
+
+    fn new(
+        _: GameStateRef,
+        _: &ReactionDefinition,
+        _: &ActiveReactionComponent,
+        _: &BeginReactionEvent,
+        _: &FxHashMap<Reagent, Entity>,
+        parameters: &EffectParameters,
+    ) -> Box<dyn ReactionEffect> {
+        Box::new(Self {
+            radius: parameters.radius().unwrap_or(0),
+        })
+    }
+
     fn tick(
         &mut self,
         state: GameStateRef,
@@ -89,17 +183,39 @@ impl ReactionEffect for TileDigEffect {
             .world
             .get_component::<PositionComponent>(target_entity)
             .unwrap();
+
+        let target_coord = **position;
+        let mut dug = vec![target_coord];
         {
             let mut map = state.resources.get_mut::<Map>().unwrap();
 
-            let target_coord = **position;
-
-            map.writer()
-                .make_floor(Vec3i::new(target_coord.x, target_coord.y, target_coord.z))
+            #[allow(clippy::cast_possible_wrap)]
+            let radius = self.radius as i32;
+            let dimensions = map.dimensions();
+            for x in target_coord.x - radius..=target_coord.x + radius {
+                for y in target_coord.y - radius..=target_coord.y + radius {
+                    let coord = Vec3i::new(x, y, target_coord.z);
+                    if coord != target_coord
+                        && x >= 0
+                        && y >= 0
+                        && x < dimensions.x
+                        && y < dimensions.y
+                        && map.get(coord).kind == TileKind::Solid
+                    {
+                        dug.push(coord);
+                    }
+                }
+            }
+
+            dug.iter()
+                .fold(map.writer(), |writer, coord| writer.make_floor(*coord))
                 .finish();
         }
 
-        handle_foliage(&position, &state);
+        for coord in dug {
+            handle_foliage(&PositionComponent::new(coord), &state);
+        }
+        make_noise(&state, event, target_coord, NoiseKind::Digging, 12);
 
         ReactionResult::Success
     }
@@ -1,4 +1,7 @@
-use crate::{ActiveReactionComponent, BeginReactionEvent, ReactionEffect, ReactionResult};
+use crate::{
+    ActiveReactionComponent, BeginReactionEvent, EffectParameterSchema, ReactionEffect,
+    ReactionResult,
+};
 use rl_core::{
     components::NeedsComponent,
     defs::{
@@ -6,21 +9,59 @@ use rl_core::{
         item::{ItemComponent, ItemDefinition},
         material::{MaterialComponent, MaterialDefinition},
         needs::{NeedKind, Nutrition, ProvidesNutrition},
-        reaction::{ReactionDefinition, Reagent},
+        reaction::{EffectParameterKind, EffectParameters, ReactionDefinition, Reagent},
+        thought::{ThoughtEvent, ThoughtTrigger},
         DefinitionComponent, DefinitionStorage,
     },
+    event::Channel,
+    failure,
     fxhash::FxHashMap,
     legion::prelude::*,
     GameStateRef,
 };
 
+/// Feeds the initiator, satisfying only the `Need` parameter if one is given
 #[derive(Default)]
-pub struct ConsumeEdibleEffect;
+pub struct ConsumeEdibleEffect {
+    need: Option<NeedKind>,
+}
+impl ConsumeEdibleEffect {
+    fn satisfies(&self, need: NeedKind) -> bool {
+        self.need.map_or(true, |only| only == need)
+    }
+}
 impl ReactionEffect for ConsumeEdibleEffect {
     fn name() -> &'static str {
         "ConsumeEdibleEffect"
     }
 
+    fn parameters() -> &'static [EffectParameterSchema] {
+        &[EffectParameterSchema {
+            kind: EffectParameterKind::Need,
+            required: false,
+        }]
+    }
+
+    fn validate(parameters: &EffectParameters) -> Result<(), failure::Error> {
+        match parameters.need() {
+            Some(NeedKind::Sleep) => Err(failure::err_msg("food can't satisfy sleep")),
+            _ => Ok(()),
+        }
+    }
+
+    fn new(
+        _: GameStateRef,
+        _: &ReactionDefinition,
+        _: &ActiveReactionComponent,
+        _: &BeginReactionEvent,
+        _: &FxHashMap<Reagent, Entity>,
+        parameters: &EffectParameters,
+    ) -> Box<dyn ReactionEffect> {
+        Box::new(Self {
+            need: parameters.need(),
+        })
+    }
+
     fn tick(
         &mut self,
         state: GameStateRef,
@@ -45,8 +86,24 @@ impl ReactionEffect for ConsumeEdibleEffect {
                     .unwrap()
             };
 
-            needs_comp.add(NeedKind::Calories, nutrition.calories.start);
-            needs_comp.add(NeedKind::Hydration, nutrition.hydration.start);
+            if self.satisfies(NeedKind::Calories) {
+                needs_comp.add(NeedKind::Calories, nutrition.calories.start);
+            }
+            if self.satisfies(NeedKind::Hydration) {
+                needs_comp.add(NeedKind::Hydration, nutrition.hydration.start);
+            }
+
+            if self.satisfies(NeedKind::Calories) && nutrition.calories.start > 0 {
+                if let Some(channel) = state.resources.get::<Channel<ThoughtEvent>>() {
+                    channel
+                        .write(ThoughtEvent::new(
+                            event.initiator.unwrap().entity(),
+                            ThoughtTrigger::AteMeal,
+                            nutrition.calories.start,
+                        ))
+                        .unwrap();
+                }
+            }
         }
 
         ReactionResult::Success
@@ -0,0 +1,132 @@
+use crate::UiWindowSet;
+use imgui::{im_str, Condition};
+use rl_ai::combat::{AttackSource, CombatEvent, CombatLog, CombatOutcome};
+use rl_core::{
+    components::NameComponent,
+    data::PartGraphId,
+    defs::{
+        body::{BodyComponent, BodyDefinition},
+        creature::{CreatureComponent, CreatureDefinition},
+        item::{ItemComponent, ItemDefinition},
+        material::MaterialDefinition,
+        Definition, DefinitionComponent, DefinitionStorage,
+    },
+    legion::prelude::*,
+};
+
+fn entity_name(
+    world: &World,
+    creatures: &DefinitionStorage<CreatureDefinition>,
+    entity: Entity,
+) -> String {
+    if let Some(name) = world.get_component::<NameComponent>(entity) {
+        return name.name.clone();
+    }
+
+    world
+        .get_component::<CreatureComponent>(entity)
+        .map_or_else(
+            || format!("{:?}", entity),
+            |creature| creature.fetch(creatures).name().to_owned(),
+        )
+}
+
+fn part_name(
+    world: &World,
+    bodies: &DefinitionStorage<BodyDefinition>,
+    entity: Entity,
+    part: PartGraphId,
+) -> String {
+    world
+        .get_component::<BodyComponent>(entity)
+        .and_then(|body| {
+            body.fetch(bodies)
+                .graph
+                .node_weight(part)
+                .map(|part| part.name().to_owned())
+        })
+        .unwrap_or_else(|| "body".to_owned())
+}
+
+fn describe(world: &World, resources: &Resources, event: &CombatEvent) -> String {
+    let (creatures, bodies, items, materials) = <(
+        Read<DefinitionStorage<CreatureDefinition>>,
+        Read<DefinitionStorage<BodyDefinition>>,
+        Read<DefinitionStorage<ItemDefinition>>,
+        Read<DefinitionStorage<MaterialDefinition>>,
+    )>::fetch(resources);
+
+    let weapon = match event.attack.source {
+        AttackSource::Part(part) => part_name(world, &bodies, event.attacker, part),
+        AttackSource::Item(item) => world.get_component::<ItemComponent>(item).map_or_else(
+            || "weapon".to_owned(),
+            |item| item.fetch(&items).name().to_owned(),
+        ),
+    };
+
+    let attacker = entity_name(world, &creatures, event.attacker);
+    let defender = entity_name(world, &creatures, event.defender);
+
+    match &event.outcome {
+        CombatOutcome::Miss => format!(
+            "{} swings at {} with their {} and misses",
+            attacker, defender, weapon
+        ),
+        CombatOutcome::Hit(hit) => {
+            let mut text = format!(
+                "{} hits {} in the {} with their {} ({:?})",
+                attacker,
+                defender,
+                part_name(world, &bodies, event.defender, hit.part),
+                weapon,
+                event.attack.kind
+            );
+            let part = world
+                .get_component::<BodyComponent>(event.defender)
+                .and_then(|body| body.fetch(&bodies).graph.node_weight(hit.part).cloned());
+            for damage in &hit.layers {
+                let layer = part
+                    .as_ref()
+                    .and_then(|part| part.layers.get(damage.layer))
+                    .and_then(|layer| layer.material.fetch(&materials))
+                    .map_or("layer", |material| material.name());
+                text.push_str(&format!(", {} -{}", layer, damage.amount));
+            }
+            if hit.penetration > 0.0 {
+                text.push_str(&format!(", {:.1} through", hit.penetration));
+            }
+            text
+        }
+    }
+}
+
+pub fn build_combat_log_window(world: &mut World, resources: &mut Resources) {
+    if !resources.contains::<CombatLog>() {
+        resources.insert(CombatLog::default());
+    }
+
+    UiWindowSet::create_with(
+        world,
+        resources,
+        "combatLogWindow",
+        true,
+        move |ui, _window_manager, world, resources, _command_buffer| {
+            let log = resources.get::<CombatLog>().unwrap();
+
+            imgui::Window::new(im_str!("combatLogWindow"))
+                .size([500.0, 250.0], Condition::Once)
+                .build(ui, || {
+                    // Newest first
+                    for event in log.entries.iter().rev() {
+                        ui.text(&format!(
+                            "{:>8.1} {}",
+                            event.time,
+                            describe(world, resources, event)
+                        ));
+                    }
+                });
+
+            true
+        },
+    );
+}
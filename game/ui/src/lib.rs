@@ -25,10 +25,12 @@ use std::{
 pub use clipboard;
 pub use imgui;
 
+pub mod combat;
 pub mod imgui_manager;
 pub mod mapgen;
 pub mod nuklear_manager;
 pub mod placement;
+pub mod schedules;
 pub mod selection;
 pub mod tasks;
 pub mod tools;
@@ -0,0 +1,132 @@
+use crate::UiWindowSet;
+use imgui::{im_str, Condition, ImString};
+use rl_core::{
+    components::{NameComponent, PawnTag},
+    legion::prelude::*,
+    schedule::{ScheduleBlock, ScheduleComponent, ScheduleStorage, ScheduleTemplate},
+    time::Time,
+};
+
+fn block_label(block: ScheduleBlock) -> &'static str {
+    match block {
+        ScheduleBlock::Anything => "A",
+        ScheduleBlock::Work => "W",
+        ScheduleBlock::Leisure => "L",
+        ScheduleBlock::Sleep => "S",
+    }
+}
+
+fn next_block(block: ScheduleBlock) -> ScheduleBlock {
+    let index = ScheduleBlock::ALL
+        .iter()
+        .position(|b| *b == block)
+        .unwrap_or(0);
+    ScheduleBlock::ALL[(index + 1) % ScheduleBlock::ALL.len()]
+}
+
+pub fn build_schedule_window(world: &mut World, resources: &mut Resources) {
+    #[derive(Default)]
+    struct ScheduleWindowState {
+        selected_template: usize,
+    }
+
+    let pawn_query =
+        <(Read<NameComponent>, Read<ScheduleComponent>)>::query().filter(tag::<PawnTag>());
+
+    resources.insert(ScheduleWindowState::default());
+
+    UiWindowSet::create_with(
+        world,
+        resources,
+        "schedulesWindow",
+        true,
+        move |ui, _window_manager, world, resources, command_buffer| {
+            let (time, mut storage, mut window_state) = unsafe {
+                <(
+                    Read<Time>,
+                    Write<ScheduleStorage>,
+                    Write<ScheduleWindowState>,
+                )>::fetch_unchecked(&resources)
+            };
+
+            imgui::Window::new(im_str!("schedulesWindow"))
+                .size([400.0, 300.0], Condition::Once)
+                .build(ui, || {
+                    ui.text(&format!("{}", time.date));
+                    ui.separator();
+
+                    let handles = storage.iter().map(|(h, _)| h).collect::<Vec<_>>();
+                    let names = storage
+                        .iter()
+                        .map(|(_, t)| ImString::new(t.name.as_str()))
+                        .collect::<Vec<_>>();
+
+                    if window_state.selected_template >= handles.len() {
+                        window_state.selected_template = 0;
+                    }
+
+                    imgui::ComboBox::new(im_str!("Template##Selection")).build_simple_string(
+                        ui,
+                        &mut window_state.selected_template,
+                        names.iter().collect::<Vec<_>>().as_slice(),
+                    );
+                    ui.same_line(0.0);
+                    if ui.button(im_str!("new"), [0.0, 0.0]) {
+                        storage.insert(ScheduleTemplate::new(&format!(
+                            "Schedule {}",
+                            handles.len() + 1
+                        )));
+                    }
+
+                    // Click an hour to cycle it between Anything, Work, Leisure and Sleep
+                    if let Some(template) = handles
+                        .get(window_state.selected_template)
+                        .and_then(|handle| storage.get_mut(*handle))
+                    {
+                        for (hour, block) in template.blocks.iter_mut().enumerate() {
+                            if hour % 12 != 0 {
+                                ui.same_line(0.0);
+                            }
+                            let label =
+                                ImString::new(format!("{}##hour{}", block_label(*block), hour));
+                            if ui.button(&label, [20.0, 0.0]) {
+                                *block = next_block(*block);
+                            }
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text(&format!("{:02}:00 {:?}", hour, block));
+                            }
+                        }
+                    }
+                    ui.separator();
+
+                    ui.columns(2, im_str!("pawnSchedules"), true);
+                    ui.text("Pawn");
+                    ui.next_column();
+                    ui.text("Schedule");
+                    ui.next_column();
+                    for (entity, (name, schedule)) in pawn_query.iter_entities(&world) {
+                        ui.text(&name.name);
+                        ui.next_column();
+
+                        let mut current = handles
+                            .iter()
+                            .position(|handle| *handle == schedule.template)
+                            .unwrap_or(0);
+                        let label = ImString::new(format!("##schedule{:?}", entity));
+                        if imgui::ComboBox::new(&label).build_simple_string(
+                            ui,
+                            &mut current,
+                            names.iter().collect::<Vec<_>>().as_slice(),
+                        ) {
+                            command_buffer
+                                .add_component(entity, ScheduleComponent::new(handles[current]));
+                        }
+                        ui.next_column();
+                    }
+                    ui.columns(1, im_str!(""), false);
+                });
+
+            true
+        },
+    );
+}
@@ -1,27 +1,57 @@
 use crate::{selection::SelectionState, UiWindowSet};
 use imgui::{im_str, Condition};
-use rl_ai::HasTasksComponent;
-use rl_core::defs::{reaction::ReactionDefinition, DefinitionId, DefinitionStorage};
+use rl_ai::{HasTasksComponent, TaskReservationEvent};
+use rl_core::defs::{
+    labor::LaborDefinition, reaction::ReactionDefinition, Definition, DefinitionId,
+    DefinitionStorage,
+};
 use rl_core::{
     components::{Destroy, PositionComponent, VirtualTaskTag},
     event::Channel,
     input::{ActionBinding, InputActionEvent},
     legion::prelude::*,
     map::Map,
+    time::Time,
     Logging,
 };
+use std::collections::VecDeque;
+
+/// Reservation changes kept for display in the task window
+const RESERVATION_HISTORY: usize = 32;
 
 pub fn build_task_window(world: &mut World, resources: &mut Resources) {
     let query = <(Read<PositionComponent>, Read<HasTasksComponent>)>::query();
 
+    if !resources.contains::<Channel<TaskReservationEvent>>() {
+        resources.insert(Channel::<TaskReservationEvent>::default());
+    }
+    let listener_id = resources
+        .get_mut::<Channel<TaskReservationEvent>>()
+        .unwrap()
+        .bind_listener(256);
+    let mut history = VecDeque::with_capacity(RESERVATION_HISTORY);
+
     UiWindowSet::create_with(
         world,
         resources,
         "tasksWindow",
         true,
         move |ui, _window_manager, world, resources, _command_buffer| {
-            let (_log, reaction_defs) =
-                <(Read<Logging>, Read<DefinitionStorage<ReactionDefinition>>)>::fetch(&resources);
+            let (_log, reaction_defs, labor_defs, time, reservation_channel) =
+                <(
+                    Read<Logging>,
+                    Read<DefinitionStorage<ReactionDefinition>>,
+                    Read<DefinitionStorage<LaborDefinition>>,
+                    Read<Time>,
+                    Read<Channel<TaskReservationEvent>>,
+                )>::fetch(&resources);
+
+            while let Some(event) = reservation_channel.read(listener_id) {
+                if history.len() == RESERVATION_HISTORY {
+                    history.pop_front();
+                }
+                history.push_back(event);
+            }
 
             imgui::Window::new(im_str!("tasksWindow"))
                 .size([350.0, 300.0], Condition::Once)
@@ -29,7 +59,7 @@ pub fn build_task_window(world: &mut World, resources: &mut Resources) {
                     ui.columns(4, im_str!("balls"), true);
                     ui.text("Position");
                     ui.next_column();
-                    ui.text("Kind");
+                    ui.text("Labor");
                     ui.next_column();
                     ui.text("Reaction");
                     ui.next_column();
@@ -40,18 +70,34 @@ pub fn build_task_window(world: &mut World, resources: &mut Resources) {
                             ui.next_column();
                             ui.text(&format!("{},{},{}", tile_pos.x, tile_pos.y, tile_pos.z));
                             ui.next_column();
-                            ui.text(&format!("{:?}", task.kind));
+                            ui.text(task.labor.fetch(&labor_defs).name());
                             ui.next_column();
                             ui.text(&task.reaction.as_str(&reaction_defs).to_string());
                             ui.next_column();
-                            ui.text(if tasks.storage.get().is_available(handle) {
-                                "AVAIL"
-                            } else {
-                                "TAKEN"
-                            });
+                            match tasks.storage.get().reservation(handle) {
+                                Some(reservation) => ui.text(&format!(
+                                    "TAKEN {:?} {:.0}s",
+                                    reservation.holder,
+                                    time.world_time - reservation.taken
+                                )),
+                                None => ui.text("AVAIL"),
+                            }
                         }
                     }
                     ui.columns(1, im_str!("asdf"), false);
+
+                    if ui
+                        .collapsing_header(im_str!("Reservations"))
+                        .default_open(false)
+                        .build()
+                    {
+                        for event in history.iter().rev() {
+                            ui.text(&format!(
+                                "{:?} {:?} by {:?}",
+                                event.change, event.entity, event.holder
+                            ));
+                        }
+                    }
                 });
 
             true
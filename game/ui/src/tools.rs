@@ -5,9 +5,19 @@ use crate::{
     UiWindowSet,
 };
 use enumflags2::BitFlags;
-use rl_ai::{HasTasksComponent, Task, TaskKind};
+use rl_ai::{
+    orders::{OrderMode, OrderQueueComponent, ProductionOrder},
+    HasTasksComponent, Task, TaskDependency, TaskQueue,
+};
 use rl_core::defs::{
-    item::{ItemKind, StockpileTileChildComponent},
+    item::{
+        ItemDefinition, ItemKind, ItemQuality, StockpileComponent, StockpileFilter,
+        StockpilePriority, StockpileTileChildComponent,
+    },
+    labor::LaborDefinition,
+    material::{
+        MaterialDefinition, MaterialKind, MaterialLimit, MaterialRef, OrganicSubKind, RockSubKind,
+    },
     reaction::ReactionDefinition,
     workshop::{WorkshopComponent, WorkshopDefinition},
     Definition, DefinitionComponent, DefinitionStorage,
@@ -52,7 +62,7 @@ struct ToolOverlayState {
 
 #[derive(Default)]
 pub struct ActiveToolProperties {
-    designate_stockpile: BitFlags<ItemKind>,
+    designate_stockpile: StockpileFilter,
 }
 
 #[allow(clippy::too_many_lines, clippy::comparison_chain)]
@@ -109,7 +119,7 @@ pub fn build_tools_overlay(
                                     resources,
                                     &map,
                                     selection.tile_area.iter(),
-                                    active_tools.designate_stockpile,
+                                    active_tools.designate_stockpile.clone(),
                                     command_buffer,
                                 )
                                 .unwrap();
@@ -151,6 +161,14 @@ pub fn build_tools_overlay(
                                             })
                                             .is_none()
                                     }),
+                                    // Wait for anything designated on the tile below
+                                    |coord| {
+                                        vec![TaskDependency::Location(Vec3i::new(
+                                            coord.x,
+                                            coord.y,
+                                            coord.z + 1,
+                                        ))]
+                                    },
                                     command_buffer,
                                     &reaction_defs,
                                 )
@@ -172,6 +190,7 @@ pub fn build_tools_overlay(
                                             })
                                             .is_none()
                                     }),
+                                    |_| Vec::new(),
                                     command_buffer,
                                     &reaction_defs,
                                 )
@@ -270,7 +289,8 @@ pub fn build_tools_overlay(
                                     resources
                                         .get_mut::<ActiveToolProperties>()
                                         .unwrap()
-                                        .designate_stockpile = ItemKind::Wood.into();
+                                        .designate_stockpile =
+                                        StockpileFilter::from(BitFlags::from(ItemKind::Wood));
 
                                     resources.get_mut::<SelectionState>().unwrap().mode =
                                         SelectionMode::MapTileBox;
@@ -280,7 +300,7 @@ pub fn build_tools_overlay(
                                     resources
                                         .get_mut::<ActiveToolProperties>()
                                         .unwrap()
-                                        .designate_stockpile = BitFlags::all();
+                                        .designate_stockpile = StockpileFilter::default();
 
                                     resources.get_mut::<SelectionState>().unwrap().mode =
                                         SelectionMode::MapTileBox;
@@ -340,7 +360,7 @@ pub fn build_tools_overlay(
                 .opened(&mut state.show_selected)
                 .build(ui, || {
                     use rl_core::components::{CarryComponent, NameComponent};
-                    use rl_core::defs::body::BodyComponent;
+                    use rl_core::defs::body::{BodyComponent, BodyDefinition};
                     let selection_state = resources.get::<SelectionState>().unwrap();
 
                     if let Some(selection) = &selection_state.last_selection {
@@ -365,6 +385,38 @@ pub fn build_tools_overlay(
                                     "Carrying: \n\t{:?}\n\t{:?}",
                                     carrying.limbs[0].1, carrying.limbs[1].1,
                                 ));
+
+                                let bodies = resources
+                                    .get::<DefinitionStorage<BodyDefinition>>()
+                                    .unwrap();
+                                let body_def = body_comp.fetch(&bodies);
+                                ui.text(&format!(
+                                    "Health: {:.0}%, Blood: {:.0}%",
+                                    body_comp.health(body_def) * 100.0,
+                                    body_comp.blood_fraction() * 100.0
+                                ));
+                                for (idx, part_state) in &body_comp.part_states {
+                                    if part_state.wounds.is_empty() && !part_state.destroyed {
+                                        continue;
+                                    }
+                                    let part = body_def.graph.node_weight(*idx).unwrap();
+                                    ui.text(&format!(
+                                        "\t{}: {:.0}%{}{}{}",
+                                        part.name(),
+                                        part_state.health(part) * 100.0,
+                                        if part_state.destroyed { ", lost" } else { "" },
+                                        if part_state.bleeding() > 0.01 {
+                                            ", bleeding"
+                                        } else {
+                                            ""
+                                        },
+                                        if part_state.infection() > 0.0 {
+                                            ", infected"
+                                        } else {
+                                            ""
+                                        },
+                                    ));
+                                }
                             } else if let Some(workshop_comp) =
                                 world.get_component::<WorkshopComponent>(entity)
                             {
@@ -378,50 +430,28 @@ pub fn build_tools_overlay(
 
                                 let workshop_def = workshop_comp.fetch(&workshops);
 
-                                let tasks = unsafe {
+                                let tasks =
+                                    world.get_component::<HasTasksComponent>(entity).unwrap();
+                                let orders = unsafe {
                                     world
-                                        .get_component_mut_unchecked::<HasTasksComponent>(entity)
+                                        .get_component_mut_unchecked::<OrderQueueComponent>(entity)
                                         .unwrap()
                                 };
 
-                                ui.columns(2, im_str!("tasks"), true);
-                                ui.text("Queued");
-                                ui.next_column();
-                                ui.text("Available");
-                                ui.next_column();
-
-                                ui.text(&workshop_def.name());
-                                ui.separator();
-
-                                for (n, (handle, task)) in
-                                    tasks.storage.get().iter_all().enumerate()
-                                {
-                                    imgui::Selectable::new(&imgui::ImString::new(format!(
-                                        "{}##{}",
-                                        &task.reaction.fetch(&reactions).name(),
-                                        n
-                                    )))
-                                    .build(ui);
-                                }
-                                ui.next_column();
-
-                                let spawn_task = |reaction_id| {
-                                    tasks.storage.get_mut().insert(Task::new(
-                                        5,
-                                        TaskKind::WOODCUTTING,
-                                        reaction_id,
-                                    ));
+                                draw_workshop_orders(ui, workshop_def, orders, &tasks, &reactions);
+                            } else if selection.category == SelectionCategory::Stockpile {
+                                let (items, materials) =
+                                    <(
+                                        Read<DefinitionStorage<ItemDefinition>>,
+                                        Read<DefinitionStorage<MaterialDefinition>>,
+                                    )>::fetch(&resources);
+                                let stockpile = unsafe {
+                                    world
+                                        .get_component_mut_unchecked::<StockpileComponent>(entity)
+                                        .unwrap()
                                 };
 
-                                for reaction in &workshop_def.reactions {
-                                    if imgui::Selectable::new(&imgui::ImString::new(
-                                        &reaction.fetch(&reactions).unwrap().name().to_owned(),
-                                    ))
-                                    .build(ui)
-                                    {
-                                        spawn_task(reaction.id());
-                                    }
-                                }
+                                draw_stockpile_settings(ui, stockpile, &items, &materials);
                             }
                         }
                     }
@@ -434,6 +464,291 @@ pub fn build_tools_overlay(
     Ok(())
 }
 
+fn draw_workshop_orders(
+    ui: &imgui::Ui<'_>,
+    workshop_def: &WorkshopDefinition,
+    orders: &mut OrderQueueComponent,
+    tasks: &HasTasksComponent,
+    reactions: &DefinitionStorage<ReactionDefinition>,
+) {
+    let modes = [im_str!("Count"), im_str!("Forever"), im_str!("Keep stock")];
+
+    ui.columns(2, im_str!("orders"), true);
+    ui.text("Orders");
+    ui.next_column();
+    ui.text("Available");
+    ui.next_column();
+
+    ui.text(&workshop_def.name());
+    ui.separator();
+
+    let mut removed = None;
+    for (n, order) in orders.orders.iter_mut().enumerate() {
+        let reaction = order.reaction.fetch(reactions);
+        let progress = match order.mode {
+            OrderMode::Count(count) => format!("{}/{}", order.completed, count),
+            OrderMode::Forever => format!("{}/forever", order.completed),
+            OrderMode::KeepStock(count) => format!("{} made, keeping {}", order.completed, count),
+        };
+        ui.text(&format!("{}: {}", reaction.name(), progress));
+
+        if order.is_finished() {
+            ui.text("Finished");
+        } else if order.is_queued() {
+            ui.text("Queued");
+        } else if let Some(blocked) = &order.blocked {
+            ui.text(&format!("{}", blocked));
+        }
+
+        let (mut mode, mut amount) = match order.mode {
+            OrderMode::Count(count) => (0, count),
+            OrderMode::Forever => (1, 0),
+            OrderMode::KeepStock(count) => (2, count),
+        };
+        let changed = imgui::ComboBox::new(&imgui::ImString::new(format!("mode##{}", n)))
+            .build_simple_string(ui, &mut mode, &modes);
+        let changed = mode != 1
+            && imgui::Slider::new(
+                &imgui::ImString::new(format!("amount##{}", n)),
+                std::ops::RangeInclusive::new(1, 100),
+            )
+            .build(ui, &mut amount)
+            || changed;
+        if changed {
+            order.mode = match mode {
+                0 => OrderMode::Count(amount.max(1)),
+                1 => OrderMode::Forever,
+                _ => OrderMode::KeepStock(amount.max(1)),
+            };
+        }
+
+        let toggle = if order.suspended { "resume" } else { "suspend" };
+        if ui.button(
+            &imgui::ImString::new(format!("{}##{}", toggle, n)),
+            [0.0, 0.0],
+        ) {
+            order.suspended ^= true;
+        }
+        ui.same_line(0.0);
+        if ui.button(&imgui::ImString::new(format!("remove##{}", n)), [0.0, 0.0]) {
+            removed = Some(n);
+        }
+        ui.separator();
+    }
+    if let Some(n) = removed {
+        orders.remove(n, tasks);
+    }
+    ui.next_column();
+
+    for reaction in &workshop_def.reactions {
+        let reaction = reaction.fetch(reactions).unwrap();
+        if imgui::Selectable::new(&imgui::ImString::new(reaction.name().to_owned())).build(ui) {
+            orders.push(ProductionOrder::new(reaction.id(), OrderMode::Count(1)));
+        }
+    }
+    ui.columns(1, im_str!(""), false);
+}
+
+fn material_limit_name(
+    limit: &MaterialLimit,
+    materials: &DefinitionStorage<MaterialDefinition>,
+) -> String {
+    match limit {
+        MaterialLimit::Any(state) => format!("Any {:?}", state),
+        MaterialLimit::Kind(kind) => format!("{:?}", kind),
+        MaterialLimit::Source => "Source".to_owned(),
+        MaterialLimit::Material(material) => material
+            .fetch(materials)
+            .map_or("Unknown", |material| material.name())
+            .to_owned(),
+    }
+}
+
+#[allow(clippy::too_many_lines, clippy::cast_sign_loss)]
+fn draw_stockpile_settings(
+    ui: &imgui::Ui<'_>,
+    mut stockpile: impl std::ops::DerefMut<Target = StockpileComponent>,
+    items: &DefinitionStorage<ItemDefinition>,
+    materials: &DefinitionStorage<MaterialDefinition>,
+) {
+    let priorities = StockpilePriority::ALL
+        .iter()
+        .map(|priority| imgui::ImString::new(priority.as_ref()))
+        .collect::<Vec<_>>();
+    let mut priority = StockpilePriority::ALL
+        .iter()
+        .position(|p| *p == stockpile.priority)
+        .unwrap_or(0);
+    if imgui::ComboBox::new(im_str!("Priority")).build_simple_string(
+        ui,
+        &mut priority,
+        priorities.iter().collect::<Vec<_>>().as_slice(),
+    ) {
+        stockpile.priority = StockpilePriority::ALL[priority];
+    }
+
+    let filter = &mut stockpile.filter;
+
+    ui.separator();
+    for kind in &[
+        ItemKind::Weapon,
+        ItemKind::Tool,
+        ItemKind::Stone,
+        ItemKind::Wood,
+        ItemKind::Trash,
+        ItemKind::Apparel,
+        ItemKind::Food,
+        ItemKind::Other,
+    ] {
+        let mut enabled = filter.kinds.contains(*kind);
+        if ui.checkbox(&imgui::ImString::new(kind.as_ref()), &mut enabled) {
+            if enabled {
+                filter.kinds.insert(*kind);
+            } else {
+                filter.kinds.remove(*kind);
+            }
+        }
+    }
+
+    ui.separator();
+    let qualities = [
+        ItemQuality::Poor,
+        ItemQuality::Normal,
+        ItemQuality::Good,
+        ItemQuality::Excellent,
+        ItemQuality::Masterwork,
+    ];
+    let quality_names = qualities
+        .iter()
+        .map(|quality| imgui::ImString::new(quality.as_ref()))
+        .collect::<Vec<_>>();
+    let quality_names = quality_names.iter().collect::<Vec<_>>();
+    let mut min = qualities
+        .iter()
+        .position(|q| *q == filter.min_quality)
+        .unwrap_or(0);
+    let mut max = qualities
+        .iter()
+        .position(|q| *q == filter.max_quality)
+        .unwrap_or(qualities.len() - 1);
+    if imgui::ComboBox::new(im_str!("Min quality")).build_simple_string(
+        ui,
+        &mut min,
+        &quality_names,
+    ) {
+        filter.min_quality = qualities[min];
+        filter.max_quality = filter.max_quality.max(filter.min_quality);
+    }
+    if imgui::ComboBox::new(im_str!("Max quality")).build_simple_string(
+        ui,
+        &mut max,
+        &quality_names,
+    ) {
+        filter.max_quality = qualities[max];
+        filter.min_quality = filter.min_quality.min(filter.max_quality);
+    }
+
+    // Freshness is edited in days, stored as world seconds
+    let mut fresh = filter.max_age.is_some();
+    if ui.checkbox(im_str!("Fresh only"), &mut fresh) {
+        filter.max_age = if fresh { Some(86400.0) } else { None };
+    }
+    if let Some(max_age) = filter.max_age.as_mut() {
+        let mut days = (*max_age / 86400.0) as u32;
+        if imgui::Slider::new(im_str!("Max days"), std::ops::RangeInclusive::new(1, 30))
+            .build(ui, &mut days)
+        {
+            *max_age = f64::from(days) * 86400.0;
+        }
+    }
+
+    ui.separator();
+    ui.text("Items (any when empty)");
+    let mut removed = None;
+    for (n, id) in filter.definitions.iter().enumerate() {
+        if ui.button(
+            &imgui::ImString::new(format!("x##definition{}", n)),
+            [0.0, 0.0],
+        ) {
+            removed = Some(n);
+        }
+        ui.same_line(0.0);
+        ui.text(id.fetch(items).name());
+    }
+    if let Some(n) = removed {
+        filter.definitions.remove(n);
+    }
+    let mut selected = 0;
+    let item_names = std::iter::once(imgui::ImString::new("Add item..."))
+        .chain(items.iter().map(|item| imgui::ImString::new(item.name())))
+        .collect::<Vec<_>>();
+    if imgui::ComboBox::new(im_str!("##addDefinition")).build_simple_string(
+        ui,
+        &mut selected,
+        item_names.iter().collect::<Vec<_>>().as_slice(),
+    ) && selected > 0
+    {
+        let id = items.iter().nth(selected - 1).unwrap().id();
+        if !filter.definitions.contains(&id) {
+            filter.definitions.push(id);
+        }
+    }
+
+    ui.separator();
+    ui.text("Materials (any when empty)");
+    let mut removed = None;
+    for (n, limit) in filter.materials.iter().enumerate() {
+        if ui.button(
+            &imgui::ImString::new(format!("x##material{}", n)),
+            [0.0, 0.0],
+        ) {
+            removed = Some(n);
+        }
+        ui.same_line(0.0);
+        ui.text(&material_limit_name(limit, materials));
+    }
+    if let Some(n) = removed {
+        filter.materials.remove(n);
+    }
+    // Only the picked material is resolved, rather than every material each frame
+    let kinds = [
+        MaterialLimit::Kind(MaterialKind::Rock(RockSubKind::Any)),
+        MaterialLimit::Kind(MaterialKind::Organic(OrganicSubKind::Wood)),
+        MaterialLimit::Kind(MaterialKind::Organic(OrganicSubKind::Any)),
+    ];
+    let mut selected = 0;
+    let limit_names = std::iter::once(imgui::ImString::new("Add material..."))
+        .chain(
+            kinds
+                .iter()
+                .map(|limit| imgui::ImString::new(material_limit_name(limit, materials))),
+        )
+        .chain(
+            materials
+                .iter()
+                .map(|material| imgui::ImString::new(material.name())),
+        )
+        .collect::<Vec<_>>();
+    if imgui::ComboBox::new(im_str!("##addMaterial")).build_simple_string(
+        ui,
+        &mut selected,
+        limit_names.iter().collect::<Vec<_>>().as_slice(),
+    ) && selected > 0
+    {
+        let limit = kinds.get(selected - 1).cloned().or_else(|| {
+            let material = materials.iter().nth(selected - 1 - kinds.len())?;
+            let mut material_ref = MaterialRef::new(material.name());
+            material_ref.resolve(materials).ok()?;
+            Some(MaterialLimit::Material(material_ref))
+        });
+        if let Some(limit) = limit {
+            if !filter.materials.contains(&limit) {
+                filter.materials.push(limit);
+            }
+        }
+    }
+}
+
 // TODO: BOTH OF THESE SHOULD USE THE CORRECT TASK KIND
 
 pub fn spawn_stockpile(
@@ -441,10 +756,10 @@ pub fn spawn_stockpile(
     resources: &Resources,
     map: &Map,
     selection_area: impl Iterator<Item = Vec3i>,
-    stores: BitFlags<ItemKind>,
+    filter: StockpileFilter,
     command_buffer: &mut CommandBuffer,
 ) -> Result<(), failure::Error> {
-    use rl_core::defs::item::StockpileComponent;
+    use rl_core::defs::item::{StockpileComponent, StockpilePriority};
     use rl_core::map::tile::TileKind;
     use rl_render_pod::sprite::SpriteLayer;
 
@@ -459,7 +774,7 @@ pub fn spawn_stockpile(
         (SpriteLayer::Ground,),
         vec![(
             EntityMeta::new(resources.get::<Time>().unwrap().stamp()),
-            StockpileComponent::new(stores, tiles.clone()),
+            StockpileComponent::new(filter, StockpilePriority::default(), tiles.clone()),
             HasTasksComponent::default(),
         )],
     )[0];
@@ -505,6 +820,11 @@ pub fn spawn_tasks_on_entity(
 
     // Lets just add mining "task" entities to the tile, with a visual ?
     let def = reaction_defs.get_by_name(reaction_name).unwrap();
+    let labor = resources
+        .get::<DefinitionStorage<LaborDefinition>>()
+        .unwrap()
+        .for_reaction(def)
+        .ok_or_else(|| failure::format_err!("No labor for reaction: {}", reaction_name))?;
 
     selection_area.for_each(|coord| {
         let tile = map.get(coord);
@@ -517,15 +837,11 @@ pub fn spawn_tasks_on_entity(
                     tasks
                         .storage
                         .get_mut()
-                        .insert(Task::new(5, TaskKind::MINING, def.id()));
+                        .insert(Task::new(5, labor, def.id()));
                 } else {
                     command_buffer.add_component(
                         entity,
-                        HasTasksComponent::from_iter(vec![Task::new(
-                            5,
-                            TaskKind::MINING,
-                            def.id(),
-                        )]),
+                        HasTasksComponent::from_iter(vec![Task::new(5, labor, def.id())]),
                     );
                 }
             });
@@ -541,11 +857,17 @@ pub fn spawn_virtual_tasks(
     map: &Map,
     reaction_name: &str,
     selection_area: impl Iterator<Item = Vec3i>,
+    dependencies: impl Fn(Vec3i) -> Vec<TaskDependency>,
     command_buffer: &mut CommandBuffer,
     reaction_defs: &DefinitionStorage<ReactionDefinition>,
 ) -> Result<(), failure::Error> {
     // Lets just add mining "task" entities to the tile, with a visual ?
     let def = reaction_defs.get_by_name(reaction_name).unwrap();
+    let labor = resources
+        .get::<DefinitionStorage<LaborDefinition>>()
+        .unwrap()
+        .for_reaction(def)
+        .ok_or_else(|| failure::format_err!("No labor for reaction: {}", reaction_name))?;
 
     let color = resources
         .get::<Settings>()
@@ -559,13 +881,15 @@ pub fn spawn_virtual_tasks(
             .can_designate(GameStateRef { world, resources }, map, coord, tile)
             .is_ok()
         {
-            use std::iter::FromIterator;
+            let mut queue = TaskQueue::default();
+            queue.insert_with_dependencies(Task::new(5, labor, def.id()), dependencies(coord));
+
             command_buffer.insert(
                 (VirtualTaskTag,),
                 vec![(
                     PositionComponent::new(coord),
                     EntityMeta::new(resources.get::<Time>().unwrap().stamp()),
-                    HasTasksComponent::from_iter(vec![Task::new(5, TaskKind::MINING, def.id())]),
+                    HasTasksComponent::from(queue),
                     Sprite::new(sprite_map::FLOOR, color & Color::a(0.5)),
                     SparseSpriteArray::default(),
                 )],